@@ -75,6 +75,10 @@ struct Args {
     /// Save a comma seperated value (CSV) file with perf to a file
     #[arg(long, short)]
     sheet: Option<PathBuf>,
+
+    /// Save debug pictures from puzzles that draw them into a directory
+    #[arg(long, value_name = "DIR")]
+    pictures: Option<PathBuf>,
 }
 
 fn run_day<DayType: puzzle::Puzzle>(
@@ -178,6 +182,11 @@ fn main() -> Result<()> {
         })
         .init();
 
+    if let Some(directory) = args.pictures {
+        fs::create_dir_all(&directory)?;
+        utils::render::set_picture_dir(directory);
+    }
+
     match args.bootstrap {
         Some(day) => {
             bootstrap(day, args.year)?;
@@ -0,0 +1,237 @@
+use grid::*;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::utils::board::*;
+use crate::utils::cycle::*;
+
+/// A cellular automaton that runs on a Board. Each generation, every cell gets
+/// a new value from a rule that looks at the cell and its neighbors. The next
+/// generation is built in a second buffer, so every cell sees the previous
+/// generation. Cells can be fixed so they never change, like stuck lights.
+pub struct Automaton<T>
+where
+    T: Clone + Copy + Debug + PartialEq + std::fmt::Display,
+{
+    /// The board holding the current generation
+    board: Board<T>,
+
+    /// The next generation is built here, then swapped into the board
+    buffer: Grid<T>,
+
+    /// Which cells count as neighbors
+    neighborhood: Neighborhood,
+
+    /// Cells that always have the same value
+    fixed: Vec<(BoardPoint, T)>,
+
+    /// How many generations have been run
+    generation: usize,
+}
+
+/// The supported neighborhoods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// All 8 nearby cells, diagonals included.
+    Moore,
+
+    /// Just the 4 straight cells.
+    #[allow(dead_code)]
+    VonNeumann,
+}
+
+impl<T> Automaton<T>
+where
+    T: Clone + Copy + Debug + PartialEq + std::fmt::Display,
+{
+    /// Create a new automaton, the board is the first generation.
+    pub fn new(board: Board<T>, neighborhood: Neighborhood) -> Automaton<T> {
+        let buffer = board.grid().clone();
+        Automaton {
+            board,
+            buffer,
+            neighborhood,
+            fixed: vec![],
+            generation: 0,
+        }
+    }
+
+    /// Return immutable ref to the board holding the current generation.
+    pub fn board(&self) -> &Board<T> {
+        &self.board
+    }
+
+    /// How many generations have been run.
+    #[allow(dead_code)]
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Force a cell to always have this value. It is set right away.
+    pub fn fix_cell(&mut self, point: BoardPoint, value: T) {
+        self.board.set_at(point, value);
+        self.fixed.push((point, value));
+    }
+
+    /// The directions to search for neighbors.
+    fn directions(&self) -> Vec<Direction> {
+        match self.neighborhood {
+            Neighborhood::Moore => Direction::iterator().collect(),
+            Neighborhood::VonNeumann => Direction::straight_iterator(),
+        }
+    }
+
+    /// Get the values of the neighbors of this cell. Off board cells are skipped.
+    #[allow(dead_code)]
+    pub fn neighbors(&self, point: BoardPoint) -> Vec<T> {
+        let mut values = vec![];
+        self.neighbors_into(point, &self.directions(), &mut values);
+        values
+    }
+
+    /// Fill in the values list with neighbor values. Reuses the list to save allocations.
+    fn neighbors_into(&self, point: BoardPoint, directions: &[Direction], values: &mut Vec<T>) {
+        values.clear();
+        for direction in directions {
+            let near = self.board.new_location_from_direction(&point, *direction);
+            if self.board.is_valid_location(&near) {
+                values.push(self.board.value_at(near));
+            }
+        }
+    }
+
+    /// Run one generation. The rule is given the cell value and the neighbor
+    /// values, and returns the new cell value.
+    pub fn step<F>(&mut self, mut rule: F)
+    where
+        F: FnMut(T, &[T]) -> T,
+    {
+        let directions = self.directions();
+        let mut neighbors = vec![];
+        for y in 0..self.board.height() {
+            for x in 0..self.board.width() {
+                let point = BoardPoint::new(x, y);
+                self.neighbors_into(point, &directions, &mut neighbors);
+                self.buffer[y as usize][x as usize] = rule(self.board.value_at(point), &neighbors);
+            }
+        }
+
+        // Fixed cells ignore the rule
+        for (point, value) in self.fixed.iter() {
+            self.buffer[point.y() as usize][point.x() as usize] = *value;
+        }
+
+        self.board.swap_grid(&mut self.buffer);
+        self.generation += 1;
+    }
+
+    /// Run a number of generations with the same rule.
+    pub fn run<F>(&mut self, generations: usize, mut rule: F)
+    where
+        F: FnMut(T, &[T]) -> T,
+    {
+        for _ in 0..generations {
+            self.step(&mut rule);
+        }
+    }
+
+    /// Count how many cells have this value.
+    pub fn count(&self, value: T) -> usize {
+        self.board.grid().iter().filter(|&&v| v == value).count()
+    }
+}
+
+impl<T> Automaton<T>
+where
+    T: Clone + Copy + Debug + PartialEq + Eq + Hash + std::fmt::Display,
+{
+    /// Run generations until the board repeats a previous generation, or
+    /// until max generations have been run. When a cycle is found the board
+    /// is left at the first repeated generation.
+    #[allow(dead_code)]
+    pub fn run_until_cycle<F>(&mut self, max_generations: usize, mut rule: F) -> Option<Cycle>
+    where
+        F: FnMut(T, &[T]) -> T,
+    {
+        let mut detector = CycleDetector::new();
+        detector.insert(self.generation, self.board.grid().flatten().clone());
+        for _ in 0..max_generations {
+            self.step(&mut rule);
+            let key = self.board.grid().flatten().clone();
+            if let Some(cycle) = detector.insert(self.generation, key) {
+                return Some(cycle);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(rows: &[&str]) -> Board<char> {
+        let mut board = Board::new();
+        for row in rows {
+            board.push_row(row.chars().collect());
+        }
+        board
+    }
+
+    fn life(cell: char, neighbors: &[char]) -> char {
+        match (cell, neighbors.iter().filter(|&&c| c == '#').count()) {
+            ('#', 2) | (_, 3) => '#',
+            _ => '.',
+        }
+    }
+
+    #[test]
+    fn neighborhoods() {
+        let rows = ["#.#", ".#.", "#.#"];
+        let moore = Automaton::new(board(&rows), Neighborhood::Moore);
+        let von_neumann = Automaton::new(board(&rows), Neighborhood::VonNeumann);
+        let center = BoardPoint::new(1, 1);
+        assert_eq!(
+            moore
+                .neighbors(center)
+                .iter()
+                .filter(|&&c| c == '#')
+                .count(),
+            4
+        );
+        assert_eq!(von_neumann.neighbors(center), ['.'; 4]);
+        assert_eq!(moore.neighbors(BoardPoint::new(0, 0)).len(), 3);
+    }
+
+    #[test]
+    fn blinker_cycles() {
+        let mut automaton = Automaton::new(
+            board(&[".....", "..#..", "..#..", "..#..", "....."]),
+            Neighborhood::Moore,
+        );
+        automaton.step(life);
+        assert_eq!(automaton.board().value_at(BoardPoint::new(1, 2)), '#');
+        assert_eq!(automaton.board().value_at(BoardPoint::new(2, 1)), '.');
+
+        let cycle = automaton.run_until_cycle(10, life);
+        assert_eq!(
+            cycle,
+            Some(Cycle {
+                start: 1,
+                length: 2
+            })
+        );
+        assert_eq!(automaton.generation(), 3);
+        assert_eq!(automaton.count('#'), 3);
+    }
+
+    #[test]
+    fn fixed_cells_ignore_the_rule() {
+        let mut automaton = Automaton::new(board(&["...", "...", "..."]), Neighborhood::Moore);
+        automaton.fix_cell(BoardPoint::new(0, 0), '#');
+        automaton.run(3, life);
+        assert_eq!(automaton.count('#'), 1);
+        assert_eq!(automaton.board().value_at(BoardPoint::new(0, 0)), '#');
+    }
+}
@@ -1,5 +1,7 @@
+use crate::utils::geometry::Diamond;
+use crate::utils::point::{Point, X, Y};
+use crate::utils::primes::gcd;
 use grid::*;
-use rusttype::Point;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::iter::zip;
@@ -29,7 +31,7 @@ where
 }
 
 /// Simple way to use Point
-pub type BoardPoint = Point<i32>;
+pub type BoardPoint = Point<2>;
 
 /// Unique id for each player on grid
 pub type PlayerId = usize;
@@ -78,8 +80,68 @@ impl Direction {
             Direction::DownRight => Direction::UpLeft,
         }
     }
+
+    /// Parse a direction from the small text format, like U or DR.
+    pub fn from_letters(letters: &str) -> Option<Direction> {
+        Direction::iter().find(|d| d.to_string() == letters)
+    }
+
+    /// Rotate clockwise by some degrees, a multiple of 45. Negative degrees
+    /// are counter clockwise.
+    pub fn rotate(self, degrees: i32) -> Direction {
+        assert!(degrees % 45 == 0, "Can only rotate by 45 degrees");
+        let index = COMPASS.iter().position(|&d| d == self).unwrap() as i32;
+        COMPASS[(index + degrees / 45).rem_euclid(8) as usize]
+    }
+
+    /// Turn clockwise by a number of quarter turns. Negative turns are counter clockwise.
+    pub fn turn_clockwise(self, quarter_turns: i32) -> Direction {
+        self.rotate(quarter_turns * 90)
+    }
+
+    /// Turn 90 degrees to the left.
+    pub fn turn_left(self) -> Direction {
+        self.rotate(-90)
+    }
+
+    /// Turn 90 degrees to the right.
+    pub fn turn_right(self) -> Direction {
+        self.rotate(90)
+    }
+
+    /// Turn to face the other way.
+    #[allow(dead_code)]
+    pub fn turn_around(self) -> Direction {
+        Direction::opposite_direction(self)
+    }
+
+    /// How much one step in this direction moves a point.
+    pub fn offset(self) -> BoardPoint {
+        match self {
+            Direction::Up => BoardPoint::new(0, -1),
+            Direction::Down => BoardPoint::new(0, 1),
+            Direction::Left => BoardPoint::new(-1, 0),
+            Direction::Right => BoardPoint::new(1, 0),
+            Direction::UpLeft => BoardPoint::new(-1, -1),
+            Direction::UpRight => BoardPoint::new(1, -1),
+            Direction::DownLeft => BoardPoint::new(-1, 1),
+            Direction::DownRight => BoardPoint::new(1, 1),
+        }
+    }
 }
 
+/// All directions in clockwise order, starting from up.
+const COMPASS: [Direction; 8] = [
+    Direction::Up,
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft,
+];
+
 impl ToString for Direction {
     /// Impl ToString trait to pretty print direction dir in small text format
     fn to_string(&self) -> String {
@@ -119,6 +181,13 @@ where
         &self.grid
     }
 
+    /// Swap the grid values with another grid of the same size. Players and
+    /// grid state are untouched. Useful for double buffering generations.
+    pub fn swap_grid(&mut self, grid: &mut Grid<T>) {
+        assert_eq!(self.grid.size(), grid.size());
+        std::mem::swap(&mut self.grid, grid);
+    }
+
     /// Add a new row to the board
     pub fn push_row(&mut self, row: Vec<T>) {
         let len = row.len();
@@ -136,12 +205,13 @@ where
     }
 
     /// Add a row to the top of the board. Useful for things like tetris that grow up.
+    #[allow(dead_code)]
     pub fn push_front_row(&mut self, row: Vec<T>) {
         let len = row.len();
         self.grid.insert_row(0, row);
 
         for player in self.players.iter_mut() {
-            player.point.y += 1;
+            player.point[Y] += 1;
         }
 
         // Push in empty state for this row
@@ -157,7 +227,7 @@ where
 
     /// Add in a new player to the board.
     pub fn add_player(&mut self, point: BoardPoint, id: T) -> PlayerId {
-        assert!(point.x < self.width() && point.y < self.height());
+        assert!(point.x() < self.width() && point.y() < self.height());
         let player_id = self.players.len();
         self.players.push(Player {
             point,
@@ -167,8 +237,8 @@ where
         });
 
         // Update state
-        let x_: usize = point.x as usize;
-        let y_: usize = point.y as usize;
+        let x_: usize = point.x() as usize;
+        let y_: usize = point.y() as usize;
         self.grid_state[y_][x_].players_here.push(player_id);
 
         player_id
@@ -180,7 +250,7 @@ where
     pub fn add_players_from_value(&mut self, player_value: T, background_value: T) {
         for y in 0..self.height() {
             for x in 0..self.width() {
-                let location = BoardPoint { x, y };
+                let location = BoardPoint::new(x, y);
                 let value = self.value_at(location);
                 if value == player_value {
                     self.add_player(location, player_value);
@@ -213,8 +283,8 @@ where
 
     /// Get the grid state at this location.
     fn state(&self, location: BoardPoint) -> State {
-        let x_: usize = location.x as usize;
-        let y_: usize = location.y as usize;
+        let x_: usize = location.x() as usize;
+        let y_: usize = location.y() as usize;
         self.grid_state[y_][x_].clone()
     }
 
@@ -258,8 +328,8 @@ where
 
     /// Set the board value here.
     pub fn set_at(&mut self, point: BoardPoint, value: T) {
-        let x_: usize = point.x as usize;
-        let y_: usize = point.y as usize;
+        let x_: usize = point.x() as usize;
+        let y_: usize = point.y() as usize;
         self.grid[y_][x_] = value;
     }
 
@@ -267,12 +337,13 @@ where
     /// This does not include players.
     #[allow(dead_code)]
     pub fn value_at(&self, point: BoardPoint) -> T {
-        let x_: usize = point.x as usize;
-        let y_: usize = point.y as usize;
+        let x_: usize = point.x() as usize;
+        let y_: usize = point.y() as usize;
         self.grid[y_][x_]
     }
 
     /// Get the grid value at this location. Or if a player is here, give the player value.
+    #[allow(dead_code)]
     pub fn value_at_with_player(&self, point: BoardPoint) -> T {
         let mut value = self.value_at(point);
         if let Some(player_id) = self.which_player_is_here(point) {
@@ -281,20 +352,31 @@ where
         value
     }
 
+    /// Get the value of the first visible player at this location, if any.
+    pub fn visible_player_value_at(&self, point: BoardPoint) -> Option<T> {
+        let x_: usize = point.x() as usize;
+        let y_: usize = point.y() as usize;
+        self.grid_state[y_][x_]
+            .players_here
+            .iter()
+            .find(|&&id| self.players[id].visible)
+            .map(|&id| self.players[id].id)
+    }
+
     /// Set the location for a player.
     pub fn set_player_location(&mut self, player: PlayerId, point: BoardPoint) {
         // Update grid state
         let old_point = self.players[player].point;
-        let old_x: usize = old_point.x as usize;
-        let old_y: usize = old_point.y as usize;
+        let old_x: usize = old_point.x() as usize;
+        let old_y: usize = old_point.y() as usize;
         let index = self.grid_state[old_y][old_x]
             .players_here
             .iter()
             .position(|&x| x == player)
             .unwrap();
         self.grid_state[old_y][old_x].players_here.remove(index);
-        let new_x: usize = point.x as usize;
-        let new_y: usize = point.y as usize;
+        let new_x: usize = point.x() as usize;
+        let new_y: usize = point.y() as usize;
         self.grid_state[new_y][new_x].players_here.push(player);
 
         self.players[player].point = point;
@@ -307,16 +389,17 @@ where
 
     /// Get the grid value where this player is.
     pub fn player_value(&self, player: PlayerId) -> T {
-        let x: usize = self.players[player].point.x as usize;
-        let y: usize = self.players[player].point.y as usize;
+        let x: usize = self.players[player].point.x() as usize;
+        let y: usize = self.players[player].point.y() as usize;
         self.grid[y][x]
     }
 
     // Search all players, finding the smallest y value.
+    #[allow(dead_code)]
     pub fn player_minimum_height(&self) -> i32 {
         let mut min_player_y = self.height() - 1;
         for player in &self.players {
-            min_player_y = std::cmp::min(min_player_y, player.point.y);
+            min_player_y = std::cmp::min(min_player_y, player.point.y());
         }
         min_player_y
     }
@@ -351,29 +434,15 @@ where
         location: &BoardPoint,
         direction: Direction,
     ) -> BoardPoint {
-        let offset = match direction {
-            Direction::Up => BoardPoint { x: 0, y: -1 },
-            Direction::Down => BoardPoint { x: 0, y: 1 },
-            Direction::Left => BoardPoint { x: -1, y: 0 },
-            Direction::Right => BoardPoint { x: 1, y: 0 },
-            Direction::UpLeft => BoardPoint { x: -1, y: -1 },
-            Direction::UpRight => BoardPoint { x: 1, y: -1 },
-            Direction::DownLeft => BoardPoint { x: -1, y: 1 },
-            Direction::DownRight => BoardPoint { x: 1, y: 1 },
-        };
-        let new_location = BoardPoint {
-            x: location.x + offset.x,
-            y: location.y + offset.y,
-        };
-
-        new_location
+        *location + direction.offset()
     }
 
-    fn is_valid_location(&self, location: &BoardPoint) -> bool {
-        if location.x < 0
-            || location.y < 0
-            || location.x >= self.width()
-            || location.y >= self.height()
+    /// Is this location on the board?
+    pub fn is_valid_location(&self, location: &BoardPoint) -> bool {
+        if location.x() < 0
+            || location.y() < 0
+            || location.x() >= self.width()
+            || location.y() >= self.height()
         {
             return false;
         }
@@ -397,10 +466,10 @@ where
             if self.wraparound_mode {
                 // Wrap around to other side
                 match direction {
-                    Direction::Up => new_location.y = self.height() - 1,
-                    Direction::Down => new_location.y = 0,
-                    Direction::Left => new_location.x = self.width() - 1,
-                    Direction::Right => new_location.x = 0,
+                    Direction::Up => new_location[Y] = self.height() - 1,
+                    Direction::Down => new_location[Y] = 0,
+                    Direction::Left => new_location[X] = self.width() - 1,
+                    Direction::Right => new_location[X] = 0,
                     _ => panic!("Unsupported wrap around direction"),
                 }
             } else {
@@ -437,25 +506,25 @@ where
     fn step_player_wraparound(&self, location: &mut BoardPoint, direction: Direction) -> T {
         let (offset_x, offset_y, start_x, start_y) = match direction {
             // Search the column
-            Direction::Up => (0, -1, location.x, self.height() - 1),
-            Direction::Down => (0, 1, location.x, 0),
+            Direction::Up => (0, -1, location.x(), self.height() - 1),
+            Direction::Down => (0, 1, location.x(), 0),
 
             // Search the row
-            Direction::Left => (-1, 0, self.width() - 1, location.y),
-            Direction::Right => (1, 0, 0, location.y),
+            Direction::Left => (-1, 0, self.width() - 1, location.y()),
+            Direction::Right => (1, 0, 0, location.y()),
             _ => panic!("Unexpected"),
         };
 
         // Search the row or column
-        location.x = start_x;
-        location.y = start_y;
+        location[X] = start_x;
+        location[Y] = start_y;
         loop {
-            let value = self.grid[location.y as usize][location.x as usize];
+            let value = self.grid[location.y() as usize][location.x() as usize];
             if !self.wraparound.contains(&value) {
                 return value;
             }
-            location.x += offset_x;
-            location.y += offset_y;
+            location[X] += offset_x;
+            location[Y] += offset_y;
         }
     }
 
@@ -464,8 +533,8 @@ where
         let p1 = self.players[player1];
         let p2 = self.players[player2];
 
-        if (p1.point.x - 1..=p1.point.x + 1).contains(&p2.point.x)
-            && (p1.point.y - 1..=p1.point.y + 1).contains(&p2.point.y)
+        if (p1.point.x() - 1..=p1.point.x() + 1).contains(&p2.point.x())
+            && (p1.point.y() - 1..=p1.point.y() + 1).contains(&p2.point.y())
         {
             return true;
         }
@@ -526,6 +595,7 @@ where
     }
 
     /// Get the value of nearby squares in all directions including diagonal, near a point
+    #[allow(dead_code)]
     pub fn values_near_point(&mut self, point: BoardPoint) -> Vec<T> {
         let mut values = vec![];
         for direction in Direction::iter() {
@@ -546,14 +616,14 @@ where
 
         match s {
             // Move straight
-            s if s.point.x == d.point.x && s.point.y > d.point.y => Direction::Up,
-            s if s.point.x == d.point.x && s.point.y < d.point.y => Direction::Down,
-            s if s.point.y == d.point.y && s.point.x > d.point.x => Direction::Left,
-            s if s.point.y == d.point.y && s.point.x < d.point.x => Direction::Right,
-            s if s.point.x > d.point.x && s.point.y > d.point.y => Direction::UpLeft,
-            s if s.point.x > d.point.x && s.point.y < d.point.y => Direction::DownLeft,
-            s if s.point.x < d.point.x && s.point.y > d.point.y => Direction::UpRight,
-            s if s.point.x < d.point.x && s.point.y < d.point.y => Direction::DownRight,
+            s if s.point.x() == d.point.x() && s.point.y() > d.point[Y] => Direction::Up,
+            s if s.point.x() == d.point.x() && s.point.y() < d.point[Y] => Direction::Down,
+            s if s.point.y() == d.point.y() && s.point.x() > d.point[X] => Direction::Left,
+            s if s.point.y() == d.point.y() && s.point.x() < d.point[X] => Direction::Right,
+            s if s.point.x() > d.point.x() && s.point.y() > d.point[Y] => Direction::UpLeft,
+            s if s.point.x() > d.point.x() && s.point.y() < d.point[Y] => Direction::DownLeft,
+            s if s.point.x() < d.point.x() && s.point.y() > d.point[Y] => Direction::UpRight,
+            s if s.point.x() < d.point.x() && s.point.y() < d.point[Y] => Direction::DownRight,
             _ => panic!("Fix me"),
         }
     }
@@ -565,8 +635,8 @@ where
         let s = self.players[start];
         let d = self.players[destination];
 
-        let dx = d.point.x - s.point.x;
-        let dy = d.point.y - s.point.y;
+        let dx = d.point.x() - s.point.x();
+        let dy = d.point.y() - s.point.y();
 
         match s {
             // Move straight
@@ -649,8 +719,8 @@ where
             }
 
             // Check if we've ever been here at a more optimized path
-            let x: usize = job.location.x as usize;
-            let y: usize = job.location.y as usize;
+            let x: usize = job.location.x() as usize;
+            let y: usize = job.location.y() as usize;
             let step_count = self.grid_state[y][x].step_count;
             if job.count >= step_count {
                 continue;
@@ -670,7 +740,7 @@ where
                     // See if we are allowed to move here
                     if valid_move(my_char, near_char) {
                         // Check if we are done
-                        if new_location.x == taget.x && new_location.y == taget.y {
+                        if new_location.x() == taget.x() && new_location.y() == taget.y() {
                             log::debug!("THIS IS THE END = {}", job.count);
                             let final_count = job.count + 1;
                             if final_count < shortest_path {
@@ -692,26 +762,453 @@ where
         shortest_path
     }
 
-    /// Draw a manhattan circle on the board.
+    /// Draw a manhattan circle on the board. Parts off the board are skipped.
     #[allow(dead_code)]
     pub fn draw_manhattan_radius(&mut self, point: BoardPoint, dist: i32, value: T) {
-        let mut x_offset = 0;
-        let y_min = point.y - dist;
-        let y_max = point.y + dist;
-        for y in y_min..point.y {
-            for x in (point.x - x_offset)..=(point.x + x_offset) {
-                self.set_at(BoardPoint { x, y }, value);
+        let diamond = Diamond::new(point, dist);
+        let (start, end) = diamond.rows().into_inner();
+        for y in std::cmp::max(start, 0)..=std::cmp::min(end, self.height() - 1) {
+            let row = diamond.row(y).unwrap();
+            let x_min = std::cmp::max(*row.start(), 0);
+            let x_max = std::cmp::min(*row.end(), self.width() - 1);
+            for x in x_min..=x_max {
+                self.set_at(BoardPoint::new(x, y), value);
             }
-            x_offset += 1;
+        }
+    }
+
+    /// Move every square of the board to a new location, giving a board of a
+    /// new size. Players move with their square. The map must give a unique
+    /// location on the new board for every old location.
+    fn remap<F>(&mut self, width: i32, height: i32, map: F)
+    where
+        F: Fn(BoardPoint) -> BoardPoint,
+    {
+        if self.grid.is_empty() {
+            return;
         }
 
-        for y in point.y..=y_max {
-            for x in (point.x - x_offset)..=(point.x + x_offset) {
-                self.set_at(BoardPoint { x, y }, value);
+        let mut grid = Grid::init(height as usize, width as usize, self.grid[0][0]);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let new_point = map(BoardPoint::new(x, y));
+                grid[new_point.y() as usize][new_point.x() as usize] =
+                    self.grid[y as usize][x as usize];
             }
-            x_offset -= 1;
+        }
+        self.grid = grid;
+
+        // Rebuild the state, keeping players at their moved squares
+        self.grid_state = Grid::init(
+            height as usize,
+            width as usize,
+            State {
+                step_count: u32::MAX,
+                players_here: vec![],
+            },
+        );
+        for player in self.players.iter_mut() {
+            player.point = map(player.point);
+            let x_: usize = player.point.x() as usize;
+            let y_: usize = player.point.y() as usize;
+            self.grid_state[y_][x_].players_here.push(player.player_id);
         }
     }
+
+    /// Rotate the board clockwise by a multiple of 90 degrees. Negative
+    /// degrees rotate counter clockwise.
+    #[allow(dead_code)]
+    pub fn rotate(&mut self, degrees: i32) {
+        assert_eq!(degrees % 90, 0, "Can only rotate by 90 degree steps");
+        let (width, height) = (self.width(), self.height());
+        match degrees.rem_euclid(360) {
+            0 => (),
+            90 => self.remap(height, width, |p| {
+                BoardPoint::new(height - 1 - p.y(), p.x())
+            }),
+            180 => self.remap(width, height, |p| {
+                BoardPoint::new(width - 1 - p.x(), height - 1 - p.y())
+            }),
+            270 => self.remap(height, width, |p| BoardPoint::new(p.y(), width - 1 - p.x())),
+            _ => panic!("Unexpected rotation {degrees}"),
+        }
+    }
+
+    /// Flip the board left to right, like a mirror.
+    #[allow(dead_code)]
+    pub fn flip_horizontal(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        self.remap(width, height, |p| BoardPoint::new(width - 1 - p.x(), p.y()));
+    }
+
+    /// Flip the board top to bottom.
+    #[allow(dead_code)]
+    pub fn flip_vertical(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        self.remap(width, height, |p| {
+            BoardPoint::new(p.x(), height - 1 - p.y())
+        });
+    }
+
+    /// Swap rows and columns, so x,y moves to y,x.
+    #[allow(dead_code)]
+    pub fn transpose(&mut self) {
+        let (width, height) = (self.width(), self.height());
+        self.remap(height, width, |p| BoardPoint::new(p.y(), p.x()));
+    }
+
+    /// Copy a rectangle of this board into a new board. Walls and wraparound
+    /// settings are copied. Players inside the rectangle are copied too, they
+    /// get new player ids in the same order as on this board.
+    pub fn sub_board(&self, top_left: BoardPoint, width: i32, height: i32) -> Board<T> {
+        assert!(self.is_valid_location(&top_left));
+        assert!(top_left.x() + width <= self.width() && top_left.y() + height <= self.height());
+
+        let mut board = Board::new();
+        for y in top_left.y()..top_left.y() + height {
+            let start = (y * self.width() + top_left.x()) as usize;
+            let row = self.grid.flatten()[start..start + width as usize].to_vec();
+            board.push_row(row);
+        }
+
+        board.walls = self.walls.clone();
+        board.players_are_walls = self.players_are_walls;
+        board.wraparound = self.wraparound.clone();
+        board.wraparound_mode = self.wraparound_mode;
+
+        for player in self.players.iter() {
+            let point = BoardPoint::new(
+                player.point.x() - top_left.x(),
+                player.point.y() - top_left.y(),
+            );
+            if board.is_valid_location(&point) {
+                let id = board.add_player(point, player.id);
+                board.set_player_visible(id, player.visible);
+            }
+        }
+
+        board
+    }
+
+    /// Copy the values of another board onto this board, with the other
+    /// board's top left at this location. Only grid values are copied, players
+    /// on both boards are untouched.
+    #[allow(dead_code)]
+    pub fn paste(&mut self, other: &Board<T>, at: BoardPoint) {
+        assert!(self.is_valid_location(&at));
+        assert!(at.x() + other.width() <= self.width() && at.y() + other.height() <= self.height());
+        for y in 0..other.height() {
+            for x in 0..other.width() {
+                let value = other.value_at(BoardPoint::new(x, y));
+                self.set_at(BoardPoint::new(at.x() + x, at.y() + y), value);
+            }
+        }
+    }
+
+    /// Walk in a straight line from a point, giving each location and value
+    /// along the way. The start is not included. Stops at the edge of the
+    /// board, or after giving the first wall.
+    pub fn ray(&self, from: BoardPoint, direction: Direction) -> Ray<'_, T, fn(T) -> bool> {
+        self.ray_until(from, direction, |_| false)
+    }
+
+    /// Walk in a straight line from a point like ray, but also stop after
+    /// giving the first value where blocked is true. Useful for line of sight,
+    /// where the blocking square itself can be seen.
+    pub fn ray_until<F>(&self, from: BoardPoint, direction: Direction, blocked: F) -> Ray<'_, T, F>
+    where
+        F: FnMut(T) -> bool,
+    {
+        Ray {
+            board: self,
+            location: from,
+            direction,
+            blocked,
+            done: false,
+        }
+    }
+
+    /// Set every square on a line between two points, both ends included.
+    /// Lines must be straight or 45 degree diagonals.
+    pub fn draw_line(&mut self, a: BoardPoint, b: BoardPoint, value: T) {
+        let diff = b - a;
+        assert!(
+            diff.x() == 0 || diff.y() == 0 || diff.x().abs() == diff.y().abs(),
+            "Line {a:?} to {b:?} is not straight or diagonal"
+        );
+
+        let step = diff.signum();
+        for i in 0..=a.chebyshev(&b) {
+            self.set_at(a + step * i, value);
+        }
+    }
+
+    /// Find all squares that can be seen from a point. A square can be seen
+    /// if no wall is exactly on the line between the point and the square.
+    /// Walls can be seen, but block the squares behind them.
+    #[allow(dead_code)]
+    pub fn visible_from(&self, point: BoardPoint) -> Vec<BoardPoint> {
+        let mut visible = vec![];
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let dx = x - point.x();
+                let dy = y - point.y();
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                // Step along the line, only stopping on squares the line goes exactly through
+                let steps = gcd(dx as i64, dy as i64) as i32;
+                let (step_x, step_y) = (dx / steps, dy / steps);
+                let blocked = (1..steps).any(|i| {
+                    self.is_wall_here(BoardPoint::new(
+                        point.x() + i * step_x,
+                        point.y() + i * step_y,
+                    ))
+                });
+                if !blocked {
+                    visible.push(BoardPoint::new(x, y));
+                }
+            }
+        }
+
+        visible
+    }
+
+    /// Find all locations reachable from a starting point, moving only in
+    /// straight steps onto squares where the predicate is true. The start is
+    /// included if the predicate is true for it.
+    #[allow(dead_code)]
+    pub fn flood_fill<F>(&self, from: BoardPoint, mut predicate: F) -> Vec<BoardPoint>
+    where
+        F: FnMut(T) -> bool,
+    {
+        let mut filled = vec![];
+        if !self.is_valid_location(&from) || !predicate(self.value_at(from)) {
+            return filled;
+        }
+
+        let mut seen = Grid::init(self.grid.rows(), self.grid.cols(), false);
+        seen[from.y() as usize][from.x() as usize] = true;
+        let mut jobs = VecDeque::from([from]);
+        while let Some(location) = jobs.pop_front() {
+            filled.push(location);
+            for direction in Direction::straight_iterator() {
+                let near = self.new_location_from_direction(&location, direction);
+                if !self.is_valid_location(&near) || seen[near.y() as usize][near.x() as usize] {
+                    continue;
+                }
+                if predicate(self.value_at(near)) {
+                    seen[near.y() as usize][near.x() as usize] = true;
+                    jobs.push_back(near);
+                }
+            }
+        }
+
+        filled
+    }
+
+    /// Label every connected area of the board. Two squares next to each
+    /// other (straight, not diagonal) are in the same region when
+    /// same_region(a, b) is true. Gives the region id of each square and
+    /// stats for each region like area, perimeter, side count and bounding box.
+    #[allow(dead_code)]
+    pub fn connected_components<F>(&self, mut same_region: F) -> Regions<T>
+    where
+        F: FnMut(T, T) -> bool,
+    {
+        let mut ids = Grid::init(self.grid.rows(), self.grid.cols(), RegionId::MAX);
+        let mut regions = vec![];
+
+        // Flood fill from each square that is not part of a region yet
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if ids[y as usize][x as usize] != RegionId::MAX {
+                    continue;
+                }
+
+                let id = regions.len();
+                let start = BoardPoint::new(x, y);
+                let value = self.value_at(start);
+                let mut points = vec![];
+                ids[y as usize][x as usize] = id;
+                let mut jobs = VecDeque::from([start]);
+                while let Some(location) = jobs.pop_front() {
+                    points.push(location);
+                    let location_value = self.value_at(location);
+                    for direction in Direction::straight_iterator() {
+                        let near = self.new_location_from_direction(&location, direction);
+                        if !self.is_valid_location(&near)
+                            || ids[near.y() as usize][near.x() as usize] != RegionId::MAX
+                        {
+                            continue;
+                        }
+                        if same_region(location_value, self.value_at(near)) {
+                            ids[near.y() as usize][near.x() as usize] = id;
+                            jobs.push_back(near);
+                        }
+                    }
+                }
+
+                regions.push(Region {
+                    id,
+                    value,
+                    area: points.len(),
+                    perimeter: 0,
+                    sides: 0,
+                    min: start,
+                    max: start,
+                    points,
+                });
+            }
+        }
+
+        let mut output = Regions { ids, regions };
+        for id in 0..output.regions.len() {
+            output.measure_region(id);
+        }
+        output
+    }
+}
+
+/// Walks a straight line across the board. Create with Board::ray or Board::ray_until.
+pub struct Ray<'a, T, F>
+where
+    T: Clone + Copy + Debug + PartialEq + std::fmt::Display,
+{
+    board: &'a Board<T>,
+    location: BoardPoint,
+    direction: Direction,
+    blocked: F,
+    done: bool,
+}
+
+impl<'a, T, F> Iterator for Ray<'a, T, F>
+where
+    T: Clone + Copy + Debug + PartialEq + std::fmt::Display,
+    F: FnMut(T) -> bool,
+{
+    type Item = (BoardPoint, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        self.location = self
+            .board
+            .new_location_from_direction(&self.location, self.direction);
+        if !self.board.is_valid_location(&self.location) {
+            self.done = true;
+            return None;
+        }
+
+        let value = self.board.value_at(self.location);
+        if self.board.is_wall_here(self.location) || (self.blocked)(value) {
+            self.done = true;
+        }
+        Some((self.location, value))
+    }
+}
+
+/// A connected area of the board, found by Board::connected_components.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Region<T> {
+    /// Id of this region, index into the regions list
+    pub id: RegionId,
+
+    /// Value of the first square found in this region
+    pub value: T,
+
+    /// Every square in this region
+    pub points: Vec<BoardPoint>,
+
+    /// How many squares are in this region
+    pub area: usize,
+
+    /// How many square edges touch another region or the edge of the board
+    pub perimeter: usize,
+
+    /// How many straight fence sides it takes to go around this region,
+    /// including around holes
+    pub sides: usize,
+
+    /// Top left of the bounding box, inclusive
+    pub min: BoardPoint,
+
+    /// Bottom right of the bounding box, inclusive
+    pub max: BoardPoint,
+}
+
+/// Unique id for each region on the board
+pub type RegionId = usize;
+
+/// All of the regions on a board, plus the region id of each square.
+#[derive(Debug, Clone)]
+pub struct Regions<T> {
+    /// Region id of each square, indexed as [y][x]
+    pub ids: Grid<RegionId>,
+
+    /// Stats for each region
+    pub regions: Vec<Region<T>>,
+}
+
+impl<T> Regions<T> {
+    /// Get the region id at this location.
+    #[allow(dead_code)]
+    pub fn id_at(&self, point: BoardPoint) -> RegionId {
+        self.ids[point.y() as usize][point.x() as usize]
+    }
+
+    /// Is this location in this region? Off board locations are in no region.
+    fn in_region(&self, id: RegionId, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.ids.cols() as i32 || y >= self.ids.rows() as i32 {
+            return false;
+        }
+        self.ids[y as usize][x as usize] == id
+    }
+
+    /// Fill in perimeter, side count and bounding box for a region.
+    /// Sides are counted by counting corners, a shape has as many sides as corners.
+    fn measure_region(&mut self, id: RegionId) {
+        let mut perimeter = 0;
+        let mut corners = 0;
+        let mut min = self.regions[id].points[0];
+        let mut max = min;
+        for point in self.regions[id].points.iter() {
+            min = min.component_min(point);
+            max = max.component_max(point);
+
+            let (x, y) = (point.x(), point.y());
+            let up = self.in_region(id, x, y - 1);
+            let down = self.in_region(id, x, y + 1);
+            let left = self.in_region(id, x - 1, y);
+            let right = self.in_region(id, x + 1, y);
+            perimeter += [up, down, left, right].iter().filter(|&&i| !i).count();
+
+            // Check each corner of this square, made from two straight sides and the diagonal
+            for (side1, side2, dx, dy) in [
+                (up, left, -1, -1),
+                (up, right, 1, -1),
+                (down, left, -1, 1),
+                (down, right, 1, 1),
+            ] {
+                let diagonal = self.in_region(id, x + dx, y + dy);
+                let outside_corner = !side1 && !side2;
+                let inside_corner = side1 && side2 && !diagonal;
+                if outside_corner || inside_corner {
+                    corners += 1;
+                }
+            }
+        }
+
+        let region = &mut self.regions[id];
+        region.perimeter = perimeter;
+        region.sides = corners;
+        region.min = min;
+        region.max = max;
+    }
 }
 
 /// Internal only.
@@ -742,3 +1239,114 @@ struct State {
     /// Which players are in this square
     players_here: Vec<PlayerId>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(rows: &[&str]) -> Board<char> {
+        let mut board = Board::new();
+        for row in rows {
+            board.push_row(row.chars().collect());
+        }
+        board
+    }
+
+    fn rows(board: &Board<char>) -> Vec<String> {
+        (0..board.height())
+            .map(|y| {
+                (0..board.width())
+                    .map(|x| board.value_at(BoardPoint::new(x, y)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transforms() {
+        let mut board = board(&["abc", "def"]);
+        let player = board.add_player(BoardPoint::new(2, 0), '@');
+        board.rotate(90);
+        assert_eq!(rows(&board), ["da", "eb", "fc"]);
+        assert_eq!(board.player_location(player), BoardPoint::new(1, 2));
+        board.rotate(-90);
+        assert_eq!(rows(&board), ["abc", "def"]);
+        board.rotate(180);
+        assert_eq!(rows(&board), ["fed", "cba"]);
+        board.flip_horizontal();
+        assert_eq!(rows(&board), ["def", "abc"]);
+        board.flip_vertical();
+        assert_eq!(rows(&board), ["abc", "def"]);
+        board.transpose();
+        assert_eq!(rows(&board), ["ad", "be", "cf"]);
+        assert_eq!(board.player_location(player), BoardPoint::new(0, 2));
+    }
+
+    #[test]
+    fn sub_board_and_paste() {
+        let mut board = board(&["abcd", "efgh", "ijkl"]);
+        board.add_player(BoardPoint::new(0, 0), '@');
+        board.add_player(BoardPoint::new(2, 2), '@');
+        let sub = board.sub_board(BoardPoint::new(1, 1), 2, 2);
+        assert_eq!(rows(&sub), ["fg", "jk"]);
+        assert_eq!(sub.players_len(), 1);
+        assert_eq!(sub.player_location(0), BoardPoint::new(1, 1));
+
+        board.paste(&sub, BoardPoint::new(2, 0));
+        assert_eq!(rows(&board), ["abfg", "efjk", "ijkl"]);
+    }
+
+    #[test]
+    fn ray_and_line_of_sight() {
+        let mut board = board(&[".....", ".#...", ".....", "....."]);
+        board.add_wall('#');
+        let ray: Vec<_> = board.ray(BoardPoint::new(0, 1), Direction::Right).collect();
+        assert_eq!(ray, [(BoardPoint::new(1, 1), '#')]);
+        let ray = board.ray_until(BoardPoint::new(0, 0), Direction::Down, |v| v == '#');
+        assert_eq!(ray.count(), 3);
+
+        // The wall hides the squares right behind it
+        let visible = board.visible_from(BoardPoint::new(0, 0));
+        assert!(visible.contains(&BoardPoint::new(1, 1)));
+        assert!(!visible.contains(&BoardPoint::new(2, 2)));
+        assert!(!visible.contains(&BoardPoint::new(3, 3)));
+        assert!(visible.contains(&BoardPoint::new(3, 2)));
+        assert_eq!(visible.len(), 20 - 3);
+    }
+
+    #[test]
+    fn regions() {
+        let board = board(&["AAAA", "BBCD", "BBCC", "EEEC"]);
+        let regions = board.connected_components(|a, b| a == b);
+        let stats: Vec<_> = regions
+            .regions
+            .iter()
+            .map(|r| (r.value, r.area, r.perimeter, r.sides))
+            .collect();
+        assert_eq!(
+            stats,
+            [
+                ('A', 4, 10, 4),
+                ('B', 4, 8, 4),
+                ('C', 4, 10, 8),
+                ('D', 1, 4, 4),
+                ('E', 3, 8, 4)
+            ]
+        );
+        let c = regions.id_at(BoardPoint::new(3, 3));
+        assert_eq!(regions.regions[c].min, BoardPoint::new(2, 1));
+        assert_eq!(regions.regions[c].max, BoardPoint::new(3, 3));
+
+        let start = BoardPoint::new(0, 1);
+        assert_eq!(board.flood_fill(start, |v| v == 'B').len(), 4);
+        assert!(board.flood_fill(start, |v| v == 'A').is_empty());
+    }
+
+    #[test]
+    fn draw_shapes() {
+        let mut board = board(&[".....", ".....", "....."]);
+        board.draw_manhattan_radius(BoardPoint::new(1, 1), 1, '#');
+        board.draw_line(BoardPoint::new(2, 0), BoardPoint::new(4, 2), '\\');
+        assert_eq!(rows(&board), [".#\\..", "###\\.", ".#..\\"]);
+    }
+}
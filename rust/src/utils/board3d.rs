@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 
 use crate::utils::board::*;
+use crate::utils::point::Point;
 
 /// A 3D board for walking a player around. It's a 3D cube with 6 sides.
 /// The player walks around the sides. Walls are supported.
@@ -17,6 +18,9 @@ where
     /// The players on the 3D map. The player is always present on all 6 boards
     /// but hidden when not on that board.
     players: Vec<Player3D>,
+
+    /// Where each of the 6 boards is on the flat map, if known
+    placements: Vec<Option<FacePlacement>>,
 }
 
 /// A unique id for each of the 6 faces on the cube
@@ -28,6 +32,18 @@ pub type BoardId = usize;
 struct Player3D {
     board_id: BoardId,
     direction_offset: i32,
+
+    /// Every step taken, as the board, location and board direction after the step
+    trail: Vec<(BoardId, BoardPoint, Direction)>,
+}
+
+/// Where a board sits on the flat map the cube was folded from. Origin is the
+/// top left of the board on the map. Quarter turns is how many times the board
+/// is rotated clockwise on the map.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FacePlacement {
+    pub origin: BoardPoint,
+    pub quarter_turns: i32,
 }
 
 /// Each of the 4 board edges connects to another board
@@ -38,7 +54,7 @@ struct BoardConfig {
 
 /// The 4 types of edges with a default of none. Index of none is set high to
 /// break bad code.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub enum Edge {
     Top = 0,
     Right,
@@ -65,10 +81,104 @@ where
             ],
             configs: vec![BoardConfig::new(); 6],
             players: vec![],
+            placements: vec![None; 6],
         }
     }
 
+    /// Create a 3D board by folding a flat map into a cube. The map is cut
+    /// into squares of face size, squares that are empty are not part of the
+    /// cube. Any of the 11 cube nets works, in any rotation or mirror. Faces
+    /// are numbered in reading order, left to right then top to bottom. All of
+    /// the edge connections are found by folding the net, so no set_edge calls
+    /// are needed.
+    pub fn new_from_net(map: &Board<T>, face_size: i32, empty: T) -> Board3D<T> {
+        // Find the faces, in reading order
+        let mut origins = vec![];
+        for y in (0..map.height()).step_by(face_size as usize) {
+            for x in (0..map.width()).step_by(face_size as usize) {
+                let origin = BoardPoint::new(x, y);
+                if map.value_at(origin) != empty {
+                    origins.push(origin);
+                }
+            }
+        }
+        assert_eq!(origins.len(), 6, "A cube net must have 6 faces");
+
+        let mut board3d = Board3D {
+            boards: origins
+                .iter()
+                .map(|origin| map.sub_board(*origin, face_size, face_size))
+                .collect(),
+            configs: vec![BoardConfig::new(); 6],
+            players: vec![],
+            placements: origins
+                .iter()
+                .map(|&origin| {
+                    Some(FacePlacement {
+                        origin,
+                        quarter_turns: 0,
+                    })
+                })
+                .collect(),
+        };
+
+        // Fold the net. Start with face 0 and walk to neighbor faces in the
+        // map, rolling the cube over the shared edge each time.
+        let mut orientations: Vec<Option<Orientation>> = vec![None; 6];
+        orientations[0] = Some(Orientation {
+            right: Point::<3>::new(1, 0, 0),
+            down: Point::<3>::new(0, 1, 0),
+            normal: Point::<3>::new(0, 0, 1),
+        });
+        let mut jobs = vec![0];
+        while let Some(id) = jobs.pop() {
+            let orientation = orientations[id].unwrap();
+            for edge in Edge::iterator() {
+                let neighbor = origins
+                    .iter()
+                    .position(|&o| o == origins[id] + edge.offset() * face_size);
+                if let Some(neighbor) = neighbor {
+                    if orientations[neighbor].is_none() {
+                        orientations[neighbor] = Some(orientation.roll(&edge));
+                        jobs.push(neighbor);
+                    }
+                }
+            }
+        }
+        let orientations: Vec<Orientation> = orientations
+            .into_iter()
+            .map(|o| o.expect("Faces of a cube net must be connected"))
+            .collect();
+        for (i, a) in orientations.iter().enumerate() {
+            for b in orientations.iter().skip(i + 1) {
+                assert_ne!(a.normal, b.normal, "Map does not fold into a cube");
+            }
+        }
+
+        // Connect each edge to the face that is on the other side of it in 3D
+        for (id1, orientation1) in orientations.iter().enumerate() {
+            for edge1 in Edge::iterator() {
+                let id2 = orientations
+                    .iter()
+                    .position(|o| o.normal == orientation1.edge_direction(&edge1))
+                    .unwrap();
+                let orientation2 = &orientations[id2];
+                let edge2 = Edge::iterator()
+                    .find(|e| orientation2.edge_direction(e) == orientation1.normal)
+                    .unwrap();
+
+                // Edges are inverse when their values count up in opposite directions
+                let inverse = orientation1.edge_values_direction(&edge1)
+                    != orientation2.edge_values_direction(&edge2);
+                board3d.set_edge(EdgeConnection::new(id1, edge1, id2, edge2, inverse));
+            }
+        }
+
+        board3d
+    }
+
     /// Populate a row on a specific board
+    #[allow(dead_code)]
     pub fn push_row(&mut self, board_id: BoardId, row: Vec<T>) {
         self.boards[board_id].push_row(row);
     }
@@ -97,6 +207,7 @@ where
         self.players.push(Player3D {
             board_id,
             direction_offset: 0,
+            trail: vec![],
         });
 
         player_id
@@ -119,23 +230,50 @@ where
     /// Get the current direction of the player given the input of the last client direction command.
     /// Converts from client commands into actually board direction.
     pub fn player_direction(&self, player_id: PlayerId, direction: Direction) -> Direction {
-        // Lookup the requested direction index
-        let directions = vec![
-            Direction::Up,
-            Direction::Right,
-            Direction::Down,
-            Direction::Left,
-        ];
-        let direction_index = directions.iter().position(|&x| x == direction).unwrap();
+        direction.turn_clockwise(self.players[player_id].direction_offset)
+    }
+
+    /// Set where a board sits on the flat map. Boards made by new_from_net
+    /// already know where they are.
+    #[allow(dead_code)]
+    pub fn set_face_placement(
+        &mut self,
+        board_id: BoardId,
+        origin: BoardPoint,
+        quarter_turns: i32,
+    ) {
+        self.placements[board_id] = Some(FacePlacement {
+            origin,
+            quarter_turns,
+        });
+    }
+
+    /// Convert a location on a board into a location on the flat map.
+    fn global_point(&self, board_id: BoardId, point: BoardPoint) -> BoardPoint {
+        let placement = self.placements[board_id].expect("Board has no placement on the map");
+        let n = self.width() - 1;
+        let rotated = match placement.quarter_turns.rem_euclid(4) {
+            0 => point,
+            1 => BoardPoint::new(n - point.y(), point.x()),
+            2 => BoardPoint::new(n - point.x(), n - point.y()),
+            _ => BoardPoint::new(point.y(), n - point.x()),
+        };
+        placement.origin + rotated
+    }
 
-        // Use offset to get the real index
-        let direction_offset = self.players[player_id].direction_offset;
-        let new_index = (direction_index as i32 + direction_offset)
-            .rem_euclid(directions.len() as i32) as usize;
+    /// Get the location of the player on the flat map.
+    pub fn global_location(&self, player_id: PlayerId) -> BoardPoint {
+        let (board_id, point) = self.player_location(player_id);
+        self.global_point(board_id, point)
+    }
 
-        // Return the actual direction
-        let real_direction = directions[new_index];
-        real_direction
+    /// Get the direction the player is facing on the flat map, given the last
+    /// client direction command.
+    pub fn global_facing(&self, player_id: PlayerId, direction: Direction) -> Direction {
+        let board_id = self.players[player_id].board_id;
+        let placement = self.placements[board_id].expect("Board has no placement on the map");
+        self.player_direction(player_id, direction)
+            .turn_clockwise(placement.quarter_turns)
     }
 
     /// Configure how boards connect. The connection is applied to both boards.
@@ -184,7 +322,7 @@ where
         // Get expected index, it is the opposite the direction. For example,
         // when moving down we come to the top edge of the new board.
         let expected_edge = Direction::opposite_direction(direction);
-        let directions = vec![
+        let directions = [
             // This order must match order in Edge::
             Direction::Up,
             Direction::Right,
@@ -207,7 +345,21 @@ where
     }
 
     /// Move the player on the 3d board. If they move off one board move the player to the correct new board.
+    /// Each step is saved to the player's trail.
     pub fn step_player(&mut self, player_id: PlayerId, direction: Direction) -> Option<T> {
+        let value = self.step_player_helper(player_id, direction);
+        if value.is_some() {
+            let (board_id, location) = self.player_location(player_id);
+            let facing = self.player_direction(player_id, direction);
+            self.players[player_id]
+                .trail
+                .push((board_id, location, facing));
+        }
+        value
+    }
+
+    /// Move the player on the 3d board, see step_player.
+    fn step_player_helper(&mut self, player_id: PlayerId, direction: Direction) -> Option<T> {
         let (board_id, location) = self.player_location(player_id);
 
         // Use requested direction with current board direction offset to find real direction
@@ -219,18 +371,18 @@ where
             ..Default::default()
         };
         let mut value = 0; // The location value to use on the new board
-        if real_direction == Direction::Left && location.x == 0 {
+        if real_direction == Direction::Left && location.x() == 0 {
             connection = self.configs[board_id].connections[Edge::Left as usize].clone();
-            value = location.y;
-        } else if real_direction == Direction::Right && location.x == self.width() - 1 {
+            value = location.y();
+        } else if real_direction == Direction::Right && location.x() == self.width() - 1 {
             connection = self.configs[board_id].connections[Edge::Right as usize].clone();
-            value = location.y;
-        } else if real_direction == Direction::Up && location.y == 0 {
+            value = location.y();
+        } else if real_direction == Direction::Up && location.y() == 0 {
             connection = self.configs[board_id].connections[Edge::Top as usize].clone();
-            value = location.x;
-        } else if real_direction == Direction::Down && location.y == self.height() - 1 {
+            value = location.x();
+        } else if real_direction == Direction::Down && location.y() == self.height() - 1 {
             connection = self.configs[board_id].connections[Edge::Bottom as usize].clone();
-            value = location.x;
+            value = location.x();
         } else {
             moved_to_new_board = false;
         }
@@ -242,22 +394,20 @@ where
 
             // Get location on the new board
             let new_location = match new_board_edge {
-                Edge::Left => BoardPoint {
-                    x: 0,
-                    y: self.convert_location_value(value, connection.inverse),
-                },
-                Edge::Right => BoardPoint {
-                    x: self.width() - 1,
-                    y: self.convert_location_value(value, connection.inverse),
-                },
-                Edge::Top => BoardPoint {
-                    x: self.convert_location_value(value, connection.inverse),
-                    y: 0,
-                },
-                Edge::Bottom => BoardPoint {
-                    x: self.convert_location_value(value, connection.inverse),
-                    y: self.height() - 1,
-                },
+                Edge::Left => {
+                    BoardPoint::new(0, self.convert_location_value(value, connection.inverse))
+                }
+                Edge::Right => BoardPoint::new(
+                    self.width() - 1,
+                    self.convert_location_value(value, connection.inverse),
+                ),
+                Edge::Top => {
+                    BoardPoint::new(self.convert_location_value(value, connection.inverse), 0)
+                }
+                Edge::Bottom => BoardPoint::new(
+                    self.convert_location_value(value, connection.inverse),
+                    self.height() - 1,
+                ),
                 _ => panic!("Unsupported edge {new_board_edge:?}"),
             };
 
@@ -295,6 +445,134 @@ where
             println!("");
         }
     }
+
+    /// Print the flat map the cube was folded from, with the player's trail
+    /// drawn as arrows and the player itself on top.
+    pub fn print_net_with_trail(&self, player_id: PlayerId) {
+        let size = self.width();
+        let mut width = 0;
+        let mut height = 0;
+        for placement in self.placements.iter() {
+            let origin = placement.expect("Board has no placement on the map").origin;
+            width = std::cmp::max(width, origin.x() + size);
+            height = std::cmp::max(height, origin.y() + size);
+        }
+
+        // Draw the boards
+        let mut net = vec![vec![" ".to_string(); width as usize]; height as usize];
+        for (board_id, board) in self.boards.iter().enumerate() {
+            for y in 0..size {
+                for x in 0..size {
+                    let point = BoardPoint::new(x, y);
+                    let global = self.global_point(board_id, point);
+                    net[global.y() as usize][global.x() as usize] =
+                        board.value_at(point).to_string();
+                }
+            }
+        }
+
+        // Draw the trail
+        for (board_id, point, facing) in self.players[player_id].trail.iter() {
+            let global = self.global_point(*board_id, *point);
+            let quarter_turns = self.placements[*board_id].unwrap().quarter_turns;
+            let arrow = match facing.turn_clockwise(quarter_turns) {
+                Direction::Up => "^",
+                Direction::Right => ">",
+                Direction::Down => "v",
+                _ => "<",
+            };
+            net[global.y() as usize][global.x() as usize] = arrow.to_string();
+        }
+
+        // Draw the player
+        let (board_id, point) = self.player_location(player_id);
+        if let Some(value) = self.boards[board_id].visible_player_value_at(point) {
+            let global = self.global_point(board_id, point);
+            net[global.y() as usize][global.x() as usize] = value.to_string();
+        }
+
+        for row in net {
+            println!("{}", row.concat());
+        }
+    }
+}
+
+impl Edge {
+    /// Iterate through the 4 real edges.
+    fn iterator() -> impl Iterator<Item = Edge> {
+        [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left].into_iter()
+    }
+
+    /// Which way to move to cross this edge.
+    fn offset(&self) -> BoardPoint {
+        match self {
+            Edge::Top => BoardPoint::new(0, -1),
+            Edge::Right => BoardPoint::new(1, 0),
+            Edge::Bottom => BoardPoint::new(0, 1),
+            Edge::Left => BoardPoint::new(-1, 0),
+            Edge::None => panic!("No offset for none edge"),
+        }
+    }
+}
+
+/// Internal use only.
+/// How a face sits on the cube when folding a net. These are the 3D
+/// directions of the face's x axis, y axis, and the way the face looks out.
+#[derive(Clone, Copy, Debug)]
+struct Orientation {
+    right: Point<3>,
+    down: Point<3>,
+    normal: Point<3>,
+}
+
+impl Orientation {
+    /// Get the orientation of the neighbor face that is across this edge.
+    fn roll(&self, edge: &Edge) -> Orientation {
+        match edge {
+            Edge::Right => Orientation {
+                right: -self.normal,
+                down: self.down,
+                normal: self.right,
+            },
+            Edge::Left => Orientation {
+                right: self.normal,
+                down: self.down,
+                normal: -self.right,
+            },
+            Edge::Bottom => Orientation {
+                right: self.right,
+                down: -self.normal,
+                normal: self.down,
+            },
+            Edge::Top => Orientation {
+                right: self.right,
+                down: self.normal,
+                normal: -self.down,
+            },
+            Edge::None => panic!("Cannot roll over none edge"),
+        }
+    }
+
+    /// The 3D direction of moving off the face over this edge.
+    fn edge_direction(&self, edge: &Edge) -> Point<3> {
+        match edge {
+            Edge::Top => -self.down,
+            Edge::Right => self.right,
+            Edge::Bottom => self.down,
+            Edge::Left => -self.right,
+            Edge::None => panic!("No direction for none edge"),
+        }
+    }
+
+    /// The 3D direction that location values count up along this edge. Top
+    /// and bottom use x, left and right use y.
+    fn edge_values_direction(&self, edge: &Edge) -> Point<3> {
+        match edge {
+            Edge::Top | Edge::Bottom => self.right,
+            Edge::Left | Edge::Right => self.down,
+            Edge::None => panic!("No direction for none edge"),
+        }
+    }
 }
 
 impl BoardConfig {
@@ -360,3 +638,82 @@ struct BoardEdgeConnection {
     board_edge: BoardEdge,
     inverse: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draw a cube net with faces of size 2 at these face positions.
+    fn net(faces: &[(i32, i32)]) -> Board<char> {
+        let width = faces.iter().map(|f| f.0).max().unwrap() + 1;
+        let height = faces.iter().map(|f| f.1).max().unwrap() + 1;
+        let mut map = Board::new();
+        for y in 0..height * 2 {
+            let row = (0..width * 2)
+                .map(|x| match faces.contains(&(x / 2, y / 2)) {
+                    true => '.',
+                    false => ' ',
+                })
+                .collect();
+            map.push_row(row);
+        }
+        map
+    }
+
+    #[test]
+    fn walk_around_folded_nets() {
+        let nets = [
+            vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (1, 3)],
+            vec![(2, 0), (0, 1), (1, 1), (2, 1), (2, 2), (3, 2)],
+            vec![(0, 0), (1, 0), (1, 1), (2, 1), (2, 2), (3, 2)],
+        ];
+        for faces in nets {
+            let map = net(&faces);
+            let mut cube = Board3D::new_from_net(&map, 2, ' ');
+            let player = cube.add_player(0, BoardPoint::new(1, 0), '@');
+            for direction in Direction::straight_iterator() {
+                let start = cube.global_location(player);
+                let facing = cube.global_facing(player, direction);
+
+                // Going straight around a cube gets back to the start
+                for _ in 0..8 {
+                    let before = cube.global_location(player);
+                    let (board_id, _) = cube.player_location(player);
+                    let forward = cube.global_facing(player, direction).offset();
+                    assert_eq!(cube.step_player(player, direction), Some('.'));
+                    let after = cube.global_location(player);
+                    assert_eq!(map.value_at(after), '.');
+                    if cube.player_location(player).0 == board_id {
+                        assert_eq!(after, before + forward);
+                    }
+                }
+                assert_eq!(cube.global_location(player), start);
+                assert_eq!(cube.global_facing(player, direction), facing);
+            }
+        }
+    }
+
+    #[test]
+    fn walls_block_moving_to_another_face() {
+        let mut map = net(&[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (1, 3)]);
+        map.set_at(BoardPoint::new(2, 2), '#');
+        let mut cube = Board3D::new_from_net(&map, 2, ' ');
+        cube.add_wall('#');
+        let player = cube.add_player(0, BoardPoint::new(0, 1), '@');
+        assert_eq!(cube.step_player(player, Direction::Down), None);
+        assert_eq!(cube.player_location(player), (0, BoardPoint::new(0, 1)));
+    }
+
+    #[test]
+    fn face_placement() {
+        let mut cube: Board3D<char> = Board3D::new();
+        for board_id in 0..6 {
+            cube.push_row(board_id, vec!['.', '.']);
+            cube.push_row(board_id, vec!['.', '.']);
+        }
+        cube.set_face_placement(0, BoardPoint::new(10, 4), 1);
+        let player = cube.add_player(0, BoardPoint::new(0, 0), '@');
+        assert_eq!(cube.global_location(player), BoardPoint::new(11, 4));
+        assert_eq!(cube.global_facing(player, Direction::Up), Direction::Right);
+    }
+}
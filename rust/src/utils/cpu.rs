@@ -1,22 +1,54 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::zip;
+
+use crate::utils::cpu_optimizer::*;
+use crate::utils::cpu_trace::*;
+use crate::utils::cycle::*;
+
 /// A CPU that supports certain instructions, has cycle time count, and registers.
-/// It can be expanded to support more instructions and registers.
-/// And the cycle times for instructions could be configurable at creation.
+/// The registers, the text for each instruction and how long each instruction
+/// takes all come from a CpuConfig. Programs can be parsed from text and run
+/// all at once, one step at a time, or until a breakpoint.
+/// Every instruction run can be recorded in a trace, when turned on.
+/// Simple counting loops can be optimized into multiplication, Collatz loops
+/// can be run in one step, and running can stop when the CPU gets back to a
+/// state it has already been in.
 pub struct Cpu {
-    /// What was CPU state at each time cycle.
-    pub state_history: Vec<State>,
+    /// Every instruction run, used to rebuild the state at any cycle. None
+    /// unless tracing is turned on, as it grows with every instruction.
+    trace: Option<Trace>,
 
     /// The current state.
     state: State,
 
-    /// How long each instruction takes.
-    instruction_cycle_count: Vec<u32>,
+    /// Registers, instruction text and cycle counts.
+    config: CpuConfig,
 
     /// The program to run.
     program: Vec<Instruction>,
+
+    /// Program counters to stop at before running the instruction.
+    breakpoints: HashSet<usize>,
+
+    /// Stop running once time gets this high.
+    max_cycles: Option<u32>,
+
+    /// Run counted and Collatz loops in one step.
+    optimize: bool,
+
+    /// Also run each optimized loop the slow way, to check the answer matches.
+    check_optimizer: bool,
+
+    /// Every (pc, registers) seen while running, to find infinite loops.
+    loop_detector: Option<CycleDetector<(usize, Vec<i32>)>>,
+
+    /// How many times step has run an instruction or loop.
+    steps: usize,
 }
 
 /// Instructions that the CPU supports.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Instruction {
     /// Add an i32 to a register.
     Add(Register, i32),
@@ -33,6 +65,9 @@ pub enum Instruction {
     /// Increment the value of a register by 1.
     Increment(Register),
 
+    /// Decrement the value of a register by 1.
+    Decrement(Register),
+
     /// Jump to an offset in the program, adds value to PC.
     Jump(isize),
 
@@ -43,28 +78,64 @@ pub enum Instruction {
     JumpIfOne(Register, isize),
 }
 
+/// The kind of an instruction, without any operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Add,
+    Noop,
+    Half,
+    Triple,
+    Increment,
+    Decrement,
+    Jump,
+    JumpIfEven,
+    JumpIfOne,
+}
+
 impl Instruction {
-    /// Convert instruction into an index to look into instruction_cycle_count list
-    pub fn index(&self) -> usize {
+    /// Get the kind of this instruction, used to look up the cycle count.
+    pub fn opcode(&self) -> Opcode {
         match self {
-            Instruction::Add(_, _) => 0,
-            Instruction::Noop => 1,
-            Instruction::Half(_) => 2,
-            Instruction::Triple(_) => 3,
-            Instruction::Increment(_) => 4,
-            Instruction::Jump(_) => 5,
-            Instruction::JumpIfEven(_, _) => 6,
-            Instruction::JumpIfOne(_, _) => 7,
+            Instruction::Add(_, _) => Opcode::Add,
+            Instruction::Noop => Opcode::Noop,
+            Instruction::Half(_) => Opcode::Half,
+            Instruction::Triple(_) => Opcode::Triple,
+            Instruction::Increment(_) => Opcode::Increment,
+            Instruction::Decrement(_) => Opcode::Decrement,
+            Instruction::Jump(_) => Opcode::Jump,
+            Instruction::JumpIfEven(_, _) => Opcode::JumpIfEven,
+            Instruction::JumpIfOne(_, _) => Opcode::JumpIfOne,
         }
     }
 }
 
-/// The available registers.
-#[derive(Debug, Clone)]
-pub enum Register {
-    X = 0,
-    A,
-    B,
+/// Show an instruction as text, like "jie r0 +2". Registers are shown by
+/// index, so this does not need a CpuConfig.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Add(r, i) => write!(f, "add {r} {i}"),
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Half(r) => write!(f, "hlf {r}"),
+            Instruction::Triple(r) => write!(f, "tpl {r}"),
+            Instruction::Increment(r) => write!(f, "inc {r}"),
+            Instruction::Decrement(r) => write!(f, "dec {r}"),
+            Instruction::Jump(o) => write!(f, "jmp {o:+}"),
+            Instruction::JumpIfEven(r, o) => write!(f, "jie {r} {o:+}"),
+            Instruction::JumpIfOne(r, o) => write!(f, "jio {r} {o:+}"),
+        }
+    }
+}
+
+/// A register, as an index into the register list of the CpuConfig.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Register(pub usize);
+
+/// Show a register by index, like r0.
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{}", self.0)
+    }
 }
 
 /// State of the CPU at a given time.
@@ -80,114 +151,600 @@ pub struct State {
     pc: usize,
 }
 
+impl State {
+    /// Create a state from its parts.
+    pub fn new(time: u32, registers: Vec<i32>, pc: usize) -> State {
+        State {
+            time,
+            registers,
+            pc,
+        }
+    }
+
+    /// Get how many cycles since the start of the program.
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    /// Get the program counter.
+    #[allow(dead_code)]
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+}
+
+/// Why the CPU stopped running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The program counter left the end of the program.
+    Halted,
+
+    /// The program counter reached a breakpoint.
+    Breakpoint(usize),
+
+    /// Time reached the max cycles.
+    MaxCycles,
+
+    /// The CPU got back to a pc and registers it was at before, so it will
+    /// never halt. The cycle is counted in steps.
+    InfiniteLoop(Cycle),
+}
+
+/// Things that can go wrong when parsing or running a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    /// A jump went before the start of the program.
+    InvalidJump { pc: usize, offset: isize },
+
+    /// A line of the program has an instruction that is not in the config.
+    UnknownMnemonic { line: usize, mnemonic: String },
+
+    /// A line of the program names a register that is not in the config.
+    UnknownRegister { line: usize, register: String },
+
+    /// A line of the program has missing or bad operands.
+    BadOperand { line: usize, text: String },
+
+    /// An optimized loop gave a different answer than running it slowly.
+    OptimizerMismatch { pc: usize },
+
+    /// A register or the time got too big to store.
+    Overflow { pc: usize },
+
+    /// Running an optimized loop slowly did not get back to its head with
+    /// the counter at one, and stopped for this reason instead.
+    OptimizerStopped { pc: usize, stop: Stop },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::InvalidJump { pc, offset } => {
+                write!(f, "Jump by {offset} at pc {pc} is before the program")
+            }
+            CpuError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "Line {line}: unknown instruction {mnemonic}")
+            }
+            CpuError::UnknownRegister { line, register } => {
+                write!(f, "Line {line}: unknown register {register}")
+            }
+            CpuError::BadOperand { line, text } => write!(f, "Line {line}: bad operands in {text}"),
+            CpuError::OptimizerMismatch { pc } => {
+                write!(f, "Optimized loop at pc {pc} does not match running it")
+            }
+            CpuError::Overflow { pc } => write!(f, "Overflow at pc {pc}"),
+            CpuError::OptimizerStopped { pc, stop } => {
+                write!(
+                    f,
+                    "Optimized loop at pc {pc} stopped with {stop:?} when run"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+/// Text for an instruction in a program. Some instructions always use the
+/// same register, so it is not written in the program, like addx.
+#[derive(Debug, Clone)]
+struct Mnemonic {
+    opcode: Opcode,
+    register: Option<Register>,
+}
+
+/// How to build a CPU. Has the register names and starting values, the text
+/// for each instruction, and how many cycles each instruction takes.
+/// Instructions take 1 cycle unless set otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct CpuConfig {
+    registers: Vec<(String, i32)>,
+    mnemonics: HashMap<String, Mnemonic>,
+    cycles: HashMap<Opcode, u32>,
+}
+
+impl CpuConfig {
+    /// Create an empty config, with no registers or instructions.
+    pub fn new() -> CpuConfig {
+        CpuConfig::default()
+    }
+
+    /// The elf handheld device, with register x starting at 1.
+    /// Supports noop and addx, where addx takes 2 cycles.
+    pub fn handheld() -> CpuConfig {
+        CpuConfig::new()
+            .register("x", 1)
+            .mnemonic("noop", Opcode::Noop)
+            .mnemonic_with_register("addx", Opcode::Add, "x")
+            .cycles(Opcode::Add, 2)
+    }
+
+    /// The turing lock computer, with registers a and b.
+    pub fn turing_lock() -> CpuConfig {
+        CpuConfig::new()
+            .register("a", 0)
+            .register("b", 0)
+            .mnemonic("hlf", Opcode::Half)
+            .mnemonic("tpl", Opcode::Triple)
+            .mnemonic("inc", Opcode::Increment)
+            .mnemonic("jmp", Opcode::Jump)
+            .mnemonic("jie", Opcode::JumpIfEven)
+            .mnemonic("jio", Opcode::JumpIfOne)
+    }
+
+    /// Add a register with a starting value.
+    pub fn register(mut self, name: &str, value: i32) -> CpuConfig {
+        self.registers.push((name.to_string(), value));
+        self
+    }
+
+    /// Add the text for an instruction.
+    pub fn mnemonic(mut self, text: &str, opcode: Opcode) -> CpuConfig {
+        let mnemonic = Mnemonic {
+            opcode,
+            register: None,
+        };
+        self.mnemonics.insert(text.to_string(), mnemonic);
+        self
+    }
+
+    /// Add the text for an instruction that always uses the same register.
+    /// The register must already be added.
+    pub fn mnemonic_with_register(mut self, text: &str, opcode: Opcode, name: &str) -> CpuConfig {
+        let register = self.find_register(name);
+        assert!(register.is_some(), "Register {name} is not added");
+        let mnemonic = Mnemonic { opcode, register };
+        self.mnemonics.insert(text.to_string(), mnemonic);
+        self
+    }
+
+    /// Set how many cycles an instruction takes.
+    pub fn cycles(mut self, opcode: Opcode, cycles: u32) -> CpuConfig {
+        self.cycles.insert(opcode, cycles);
+        self
+    }
+
+    /// Find a register by name.
+    pub fn find_register(&self, name: &str) -> Option<Register> {
+        self.registers
+            .iter()
+            .position(|(n, _)| n == name)
+            .map(Register)
+    }
+
+    /// Parse a text program into instructions. Each line is one instruction,
+    /// with operands split by white space or commas, like "jio a, +2".
+    pub fn parse(&self, text: &str) -> Result<Vec<Instruction>, CpuError> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| self.parse_line(i + 1, line))
+            .collect()
+    }
+
+    /// Parse one line of a program into an instruction.
+    fn parse_line(&self, line: usize, text: &str) -> Result<Instruction, CpuError> {
+        let mut operands = Operands {
+            config: self,
+            line,
+            text,
+            parts: text.split([' ', ',']).filter(|p| !p.is_empty()).collect(),
+            next: 0,
+        };
+        let mnemonic = operands.next()?;
+        let Some(found) = self.mnemonics.get(mnemonic) else {
+            return Err(CpuError::UnknownMnemonic {
+                line,
+                mnemonic: mnemonic.to_string(),
+            });
+        };
+
+        // Registers come first, unless the mnemonic always uses the same one
+        let fixed = found.register;
+        let instruction = match found.opcode {
+            Opcode::Add => Instruction::Add(operands.register(fixed)?, operands.number()? as i32),
+            Opcode::Noop => Instruction::Noop,
+            Opcode::Half => Instruction::Half(operands.register(fixed)?),
+            Opcode::Triple => Instruction::Triple(operands.register(fixed)?),
+            Opcode::Increment => Instruction::Increment(operands.register(fixed)?),
+            Opcode::Decrement => Instruction::Decrement(operands.register(fixed)?),
+            Opcode::Jump => Instruction::Jump(operands.number()?),
+            Opcode::JumpIfEven => {
+                Instruction::JumpIfEven(operands.register(fixed)?, operands.number()?)
+            }
+            Opcode::JumpIfOne => {
+                Instruction::JumpIfOne(operands.register(fixed)?, operands.number()?)
+            }
+        };
+
+        if operands.next < operands.parts.len() {
+            return Err(operands.bad_operand());
+        }
+        Ok(instruction)
+    }
+}
+
+/// Internal only.
+/// Reads the parts of one line of a program in order.
+struct Operands<'a> {
+    config: &'a CpuConfig,
+    line: usize,
+    text: &'a str,
+    parts: Vec<&'a str>,
+    next: usize,
+}
+
+impl<'a> Operands<'a> {
+    /// Error for this line having missing or bad operands.
+    fn bad_operand(&self) -> CpuError {
+        CpuError::BadOperand {
+            line: self.line,
+            text: self.text.to_string(),
+        }
+    }
+
+    /// Get the next part of the line.
+    fn next(&mut self) -> Result<&'a str, CpuError> {
+        let part = self
+            .parts
+            .get(self.next)
+            .ok_or_else(|| self.bad_operand())?;
+        self.next += 1;
+        Ok(part)
+    }
+
+    /// Get a register, or use the fixed register without reading the line.
+    fn register(&mut self, fixed: Option<Register>) -> Result<Register, CpuError> {
+        if let Some(register) = fixed {
+            return Ok(register);
+        }
+        let name = self.next()?;
+        self.config
+            .find_register(name)
+            .ok_or_else(|| CpuError::UnknownRegister {
+                line: self.line,
+                register: name.to_string(),
+            })
+    }
+
+    /// Get a number, like 5, -3 or +2.
+    fn number(&mut self) -> Result<isize, CpuError> {
+        let part = self.next()?;
+        part.parse().map_err(|_| self.bad_operand())
+    }
+}
+
 impl Cpu {
-    /// Create a new CPU. Time and regs are init to 1.
-    pub fn new() -> Cpu {
-        let cpu = Cpu {
-            state_history: Vec::new(),
+    /// Create a new CPU. Time starts at 1 and registers are set from the config.
+    pub fn new(config: CpuConfig) -> Cpu {
+        let registers: Vec<i32> = config.registers.iter().map(|(_, v)| *v).collect();
+        Cpu {
+            trace: None,
 
             // Init time and register states
             state: State {
                 time: 1,
-                registers: vec![
-                    1, // x
-                    0, // a
-                    0, // b
-                ],
+                registers,
                 pc: 0,
             },
-
-            // Each instruction must have a cycle count
-            instruction_cycle_count: vec![
-                2, // Add
-                1, // Noop
-                1, // Half
-                1, // Triple
-                1, // Increment
-                1, // Jump
-                1, // JumpIfEven
-                1, // JumpIfOdd
-            ],
-
+            config,
             program: vec![],
-        };
-        cpu
+            breakpoints: HashSet::new(),
+            max_cycles: None,
+            optimize: false,
+            check_optimizer: false,
+            loop_detector: None,
+            steps: 0,
+        }
     }
 
     /// How many cycles an instruction takes.
     pub fn cycle_count(&self, instruction: &Instruction) -> u32 {
-        self.instruction_cycle_count[instruction.index()]
+        *self.config.cycles.get(&instruction.opcode()).unwrap_or(&1)
+    }
+
+    /// Get the trace of every instruction run so far, or None if tracing is off.
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
     }
 
-    /// Increate time by one and save state to history.
-    fn step(&mut self) {
-        self.state_history.push(self.state.clone());
-        self.state.time += 1;
+    /// Record every instruction run in a trace. The trace starts from the
+    /// current registers, so turn it on before running to trace a whole
+    /// program. Turning it off drops the trace.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace.then(|| Trace::new(self.state.registers.clone()));
+    }
+
+    /// Get the current time.
+    pub fn time(&self) -> u32 {
+        self.state.time
+    }
+
+    /// Get the program counter.
+    #[allow(dead_code)]
+    pub fn pc(&self) -> usize {
+        self.state.pc
     }
 
     /// Get the value of a register.
     pub fn reg(&self, register: Register) -> i32 {
-        self.state.registers[register as usize]
+        self.state.registers[register.0]
     }
 
     /// Set the value of a register.
     pub fn set_reg(&mut self, register: Register, value: i32) {
-        self.state.registers[register as usize] = value;
+        self.state.registers[register.0] = value;
+    }
+
+    /// Get a register from its name. Panics if the name is not in the config.
+    pub fn register(&self, name: &str) -> Register {
+        self.config
+            .find_register(name)
+            .unwrap_or_else(|| panic!("Unknown register {name}"))
     }
 
     /// Add a new instruction to the program.
+    #[allow(dead_code)]
     pub fn add_instruction(&mut self, instruction: &Instruction) {
         self.program.push(instruction.clone());
     }
 
-    /// Run a program.
-    pub fn run(&mut self) {
-        while self.state.pc < self.program.len() {
-            let instruction = self.program[self.state.pc].clone();
-            self.run_instruction(&instruction);
-            self.state.pc += 1;
+    /// Parse a text program and add it to the program.
+    pub fn load(&mut self, text: &str) -> Result<(), CpuError> {
+        let instructions = self.config.parse(text)?;
+        self.program.extend(instructions);
+        Ok(())
+    }
+
+    /// Stop before running the instruction at this program counter.
+    #[allow(dead_code)]
+    pub fn add_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.insert(pc);
+    }
+
+    /// Remove a breakpoint.
+    #[allow(dead_code)]
+    pub fn remove_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.remove(&pc);
+    }
+
+    /// Stop running once time gets this high, or None to never stop.
+    #[allow(dead_code)]
+    pub fn set_max_cycles(&mut self, max_cycles: Option<u32>) {
+        self.max_cycles = max_cycles;
+    }
+
+    /// Run loops in one step. Counted loops use multiplication instead of
+    /// adding each time around, and Collatz loops run without the CPU. Loops
+    /// with a breakpoint inside, or that would go past the max cycles, are
+    /// still run slowly so they stop in the right place.
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    /// Also run each optimized loop the slow way and compare the results.
+    /// Slow, but useful to trust the optimizer on a new program.
+    pub fn set_check_optimizer(&mut self, check: bool) {
+        self.check_optimizer = check;
+    }
+
+    /// Stop running if the CPU gets back to a pc and registers it has already
+    /// been in. This remembers every state, so it uses a lot of memory.
+    #[allow(dead_code)]
+    pub fn set_loop_detection(&mut self, detect: bool) {
+        self.loop_detector = detect.then(CycleDetector::new);
+    }
+
+    /// Is the program counter past the end of the program?
+    pub fn is_halted(&self) -> bool {
+        self.state.pc >= self.program.len()
+    }
+
+    /// Run the instruction at the program counter. Return false if halted.
+    pub fn step(&mut self) -> Result<bool, CpuError> {
+        if self.is_halted() {
+            return Ok(false);
+        }
+        self.steps += 1;
+
+        // Try to run a whole loop at once
+        if self.optimize {
+            if let Some(fast) = FastLoop::find(&self.program, self.state.pc) {
+                if self.run_fast_loop(&fast)? {
+                    return Ok(true);
+                }
+            }
         }
+
+        let instruction = self.program[self.state.pc].clone();
+        self.run_instruction(&instruction)?;
+        Ok(true)
     }
 
-    /// Run an instruction.
-    pub fn run_instruction(&mut self, instruction: &Instruction) {
+    /// Run a loop in one step, return false if it can not be. The loop is
+    /// left at its head with the counter at one, so the next step leaves the
+    /// loop. The trace gets one entry for the whole loop, at the head.
+    fn run_fast_loop(&mut self, fast: &FastLoop) -> Result<bool, CpuError> {
+        let head = fast.head();
+        if self.breakpoints.iter().any(|pc| fast.body().contains(pc)) {
+            return Ok(false);
+        }
+        let result = fast.run(&self.program, &self.state.registers, |i| {
+            self.cycle_count(i)
+        })?;
+        let Some(result) = result else {
+            return Ok(false);
+        };
+        let time = self
+            .state
+            .time
+            .checked_add(result.cycles)
+            .ok_or(CpuError::Overflow { pc: head })?;
+        if self.max_cycles.is_some_and(|max| time > max) {
+            return Ok(false);
+        }
+        log::debug!("Optimize loop at {head} for {} cycles", result.cycles);
+
+        if self.check_optimizer {
+            self.check_fast_loop(fast, &result.registers, time)?;
+        }
+
+        let before = std::mem::replace(&mut self.state.registers, result.registers);
+        self.state.time = time;
+        self.record_at(head, self.program[head].clone(), before, result.cycles);
+        Ok(true)
+    }
+
+    /// Run a loop slowly and check it ends with these registers at this time.
+    /// It uses the normal run, with the optimizer off, a breakpoint at the
+    /// head, the max cycles set to the expected time and loop detection on,
+    /// so a loop that does not get back to its head stops with an error
+    /// instead of running forever. The CPU is put back how it was after.
+    fn check_fast_loop(
+        &mut self,
+        fast: &FastLoop,
+        registers: &[i32],
+        time: u32,
+    ) -> Result<(), CpuError> {
+        let head = fast.head();
+        let state = self.state.clone();
+        let trace = self.trace.take();
+        let steps = self.steps;
+        let optimize = std::mem::replace(&mut self.optimize, false);
+        let breakpoints = std::mem::replace(&mut self.breakpoints, HashSet::from([head]));
+        let max_cycles = self.max_cycles.replace(time);
+        let loop_detector = self.loop_detector.replace(CycleDetector::new());
+
+        let mut stop = self.run();
+        while stop == Ok(Stop::Breakpoint(head)) && self.reg(fast.counter()) != 1 {
+            stop = self.run();
+        }
+        let slow = std::mem::replace(&mut self.state, state);
+
+        self.trace = trace;
+        self.steps = steps;
+        self.optimize = optimize;
+        self.breakpoints = breakpoints;
+        self.max_cycles = max_cycles;
+        self.loop_detector = loop_detector;
+
+        let stop = stop?;
+        if stop != Stop::Breakpoint(head) {
+            return Err(CpuError::OptimizerStopped { pc: head, stop });
+        }
+        if slow.registers != registers || slow.time != time {
+            return Err(CpuError::OptimizerMismatch { pc: head });
+        }
+        Ok(())
+    }
+
+    /// Run the program until it halts, reaches a breakpoint or the max cycles.
+    /// At least one instruction is run, so running again from a breakpoint
+    /// continues past it.
+    pub fn run(&mut self) -> Result<Stop, CpuError> {
+        let mut first = true;
+        loop {
+            if self.is_halted() {
+                return Ok(Stop::Halted);
+            }
+            if !first && self.breakpoints.contains(&self.state.pc) {
+                return Ok(Stop::Breakpoint(self.state.pc));
+            }
+            if self.max_cycles.is_some_and(|max| self.state.time > max) {
+                return Ok(Stop::MaxCycles);
+            }
+            if let Some(detector) = self.loop_detector.as_mut() {
+                let key = (self.state.pc, self.state.registers.clone());
+                if let Some(cycle) = detector.insert(self.steps, key) {
+                    return Ok(Stop::InfiniteLoop(cycle));
+                }
+            }
+            self.step()?;
+            first = false;
+        }
+    }
+
+    /// Run the program until the program counter gets to a value, or it stops
+    /// for any other reason.
+    #[allow(dead_code)]
+    pub fn run_until_pc(&mut self, pc: usize) -> Result<Stop, CpuError> {
+        let added = self.breakpoints.insert(pc);
+        let stop = self.run();
+        if added {
+            self.breakpoints.remove(&pc);
+        }
+        stop
+    }
+
+    /// Run an instruction and move the program counter.
+    pub fn run_instruction(&mut self, instruction: &Instruction) -> Result<(), CpuError> {
         log::debug!(
             "\nStart cycle {:03}: begin executing {:?}",
             self.state.time,
             instruction
         );
 
-        // Step processor state
-        let count = self.cycle_count(&instruction);
-        for _ in 0..count {
-            log::debug!(
-                "CPU: {} {:?} {:?}",
-                self.state_history.len(),
-                self.state.registers,
-                instruction
-            );
-            self.step();
-        }
+        // Remember the registers, to record what changed
+        let count = self.cycle_count(instruction);
+        let before = self.trace.is_some().then(|| self.state.registers.clone());
 
-        // Perform operation
+        // Perform operation, a register that gets too big is an error
+        let pc = self.state.pc;
+        let overflow = || CpuError::Overflow { pc };
+        let registers = &mut self.state.registers;
+        let mut offset = 1;
         match instruction {
-            Instruction::Add(r, i) => self.state.registers[r.clone() as usize] += i,
+            Instruction::Add(r, i) => {
+                registers[r.0] = registers[r.0].checked_add(*i).ok_or_else(overflow)?
+            }
             Instruction::Noop => (),
-            Instruction::Half(r) => self.state.registers[r.clone() as usize] /= 2,
-            Instruction::Triple(r) => self.state.registers[r.clone() as usize] *= 3,
-            Instruction::Increment(r) => self.state.registers[r.clone() as usize] += 1,
-            Instruction::Jump(o) => self.offset_pc(o),
+            Instruction::Half(r) => registers[r.0] /= 2,
+            Instruction::Triple(r) => {
+                registers[r.0] = registers[r.0].checked_mul(3).ok_or_else(overflow)?
+            }
+            Instruction::Increment(r) => {
+                registers[r.0] = registers[r.0].checked_add(1).ok_or_else(overflow)?
+            }
+            Instruction::Decrement(r) => {
+                registers[r.0] = registers[r.0].checked_sub(1).ok_or_else(overflow)?
+            }
+            Instruction::Jump(o) => offset = *o,
             Instruction::JumpIfEven(r, o) => {
-                if self.state.registers[r.clone() as usize] % 2 == 0 {
-                    self.offset_pc(o);
+                if registers[r.0] % 2 == 0 {
+                    offset = *o;
                 }
             }
             Instruction::JumpIfOne(r, o) => {
-                if self.state.registers[r.clone() as usize] == 1 {
-                    self.offset_pc(o);
+                if registers[r.0] == 1 {
+                    offset = *o;
                 }
             }
         }
+        self.offset_pc(offset)?;
+        self.state.time = self.state.time.checked_add(count).ok_or_else(overflow)?;
+        if let Some(before) = before {
+            self.record_at(pc, instruction.clone(), before, count);
+        }
 
         log::debug!(
             "End of cycle{:03}: finish executing {:?} (Registers are now {:?})",
@@ -195,11 +752,194 @@ impl Cpu {
             instruction,
             self.state.registers,
         );
+        Ok(())
+    }
+
+    /// Record an instruction in the trace, with the registers that changed.
+    /// The instruction is at pc, and the time and pc are already updated.
+    /// Does nothing if tracing is off.
+    fn record_at(&mut self, pc: usize, instruction: Instruction, before: Vec<i32>, cycles: u32) {
+        let Some(trace) = self.trace.as_mut() else {
+            return;
+        };
+        let changes = zip(before, self.state.registers.iter())
+            .enumerate()
+            .filter(|(_, (old, new))| old != *new)
+            .map(|(i, (_, new))| (Register(i), *new))
+            .collect();
+        trace.push(TraceEntry {
+            cycle: self.state.time - cycles,
+            cycles,
+            pc,
+            next_pc: self.state.pc,
+            instruction,
+            changes,
+        });
+    }
+
+    /// Change the value of PC by a value. Jumping before the program is an error.
+    fn offset_pc(&mut self, offset: isize) -> Result<(), CpuError> {
+        let pc = self.state.pc;
+        self.state.pc = pc
+            .checked_add_signed(offset)
+            .ok_or(CpuError::InvalidJump { pc, offset })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The turing lock with a counter register and a few more instructions.
+    fn config() -> CpuConfig {
+        CpuConfig::turing_lock()
+            .register("c", 0)
+            .mnemonic("dec", Opcode::Decrement)
+            .mnemonic("add", Opcode::Add)
+            .cycles(Opcode::Triple, 3)
+    }
+
+    const COUNTED: &str = "jio c, +5\ninc a\nadd b, 3\ndec c\njmp -4";
+    const COLLATZ: &str = "jio a, +8\ninc b\njie a, +4\ntpl a\ninc a\njmp +2\nhlf a\njmp -7";
+
+    /// Run a program, with the optimizer on or off, from these registers.
+    fn run(program: &str, registers: &[(&str, i32)], optimize: bool) -> Cpu {
+        let mut cpu = Cpu::new(config());
+        cpu.load(program).unwrap();
+        for (name, value) in registers {
+            cpu.set_reg(cpu.register(name), *value);
+        }
+        cpu.set_optimize(optimize);
+        cpu.set_check_optimizer(optimize);
+        cpu.set_trace(true);
+        assert_eq!(cpu.run(), Ok(Stop::Halted));
+        cpu
+    }
+
+    #[test]
+    fn parse_errors() {
+        let config = config();
+        assert_eq!(config.parse("inc a\n\njio a, +2").unwrap().len(), 2);
+        assert_eq!(
+            config.parse("inc a\nmul a"),
+            Err(CpuError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "mul".to_string()
+            })
+        );
+        assert_eq!(
+            config.parse("inc d"),
+            Err(CpuError::UnknownRegister {
+                line: 1,
+                register: "d".to_string()
+            })
+        );
+        assert!(matches!(
+            config.parse("jmp"),
+            Err(CpuError::BadOperand { line: 1, .. })
+        ));
+        assert!(matches!(
+            config.parse("inc a b"),
+            Err(CpuError::BadOperand { line: 1, .. })
+        ));
+        assert!(matches!(
+            config.parse("add a, x"),
+            Err(CpuError::BadOperand { line: 1, .. })
+        ));
     }
 
-    /// Change the value of PC by a value
-    fn offset_pc(&mut self, offset: &isize) {
-        // Offset one less than the value as the PC will be auto incremented by 1
-        self.state.pc = (self.state.pc as isize + offset - 1) as usize;
+    #[test]
+    fn handheld_timing() {
+        let mut cpu = Cpu::new(CpuConfig::handheld());
+        cpu.load("noop\naddx 3\naddx -5").unwrap();
+        cpu.set_trace(true);
+        assert_eq!(cpu.run(), Ok(Stop::Halted));
+        assert_eq!(cpu.time(), 6);
+        assert_eq!(cpu.reg(cpu.register("x")), -1);
+        let trace = cpu.trace().unwrap();
+        assert_eq!(trace.state_during(3).registers, [1]);
+        assert_eq!(trace.state_during(4).registers, [4]);
+    }
+
+    #[test]
+    fn optimized_loops_match_running_slowly() {
+        for (program, registers) in [(COUNTED, [("c", 10)]), (COLLATZ, [("a", 27)])] {
+            let slow = run(program, &registers, false);
+            let fast = run(program, &registers, true);
+            assert_eq!(fast.state.registers, slow.state.registers);
+            assert_eq!(fast.time(), slow.time());
+            assert!(fast.trace().unwrap().entries().len() < 5);
+        }
+        let counted = run(COUNTED, &[("c", 10)], true);
+        assert_eq!(counted.state.registers, [9, 27, 1]);
+        let collatz = run(COLLATZ, &[("a", 27)], true);
+        assert_eq!(collatz.state.registers, [1, 111, 0]);
+
+        // Counters that never get to one are left to run slowly
+        let down = run(COUNTED, &[("c", 1)], true);
+        assert_eq!(down.state.registers, [0, 0, 1]);
+    }
+
+    #[test]
+    fn breakpoints_and_max_cycles() {
+        let mut cpu = Cpu::new(config());
+        cpu.load(COUNTED).unwrap();
+        cpu.set_reg(cpu.register("c"), 10);
+        cpu.set_optimize(true);
+
+        // A breakpoint inside the loop stops the optimizer from skipping it
+        cpu.add_breakpoint(2);
+        assert_eq!(cpu.run(), Ok(Stop::Breakpoint(2)));
+        assert_eq!(cpu.reg(cpu.register("a")), 1);
+        assert_eq!(cpu.run(), Ok(Stop::Breakpoint(2)));
+        assert_eq!(cpu.reg(cpu.register("a")), 2);
+        cpu.remove_breakpoint(2);
+
+        // So does a loop that would go past the max cycles
+        let time = cpu.time();
+        cpu.set_max_cycles(Some(time + 10));
+        assert_eq!(cpu.run(), Ok(Stop::MaxCycles));
+        assert_eq!(cpu.time(), time + 11);
+        cpu.set_max_cycles(None);
+
+        assert_eq!(cpu.run_until_pc(0), Ok(Stop::Breakpoint(0)));
+        assert_eq!(cpu.pc(), 0);
+        assert_eq!(cpu.run(), Ok(Stop::Halted));
+        assert_eq!(cpu.state.registers, [9, 27, 1]);
+    }
+
+    #[test]
+    fn infinite_loop() {
+        // -1 goes to -2 and back to -1 forever, the odd step takes 6 steps and
+        // the even step 4
+        let mut cpu = Cpu::new(config());
+        cpu.load("jio a, +7\njie a, +4\ntpl a\ninc a\njmp +2\nhlf a\njmp -6")
+            .unwrap();
+        cpu.set_reg(cpu.register("a"), -1);
+        cpu.set_optimize(true);
+        cpu.set_loop_detection(true);
+        let stop = cpu.run().unwrap();
+        assert!(matches!(stop, Stop::InfiniteLoop(Cycle { length: 10, .. })));
+    }
+
+    #[test]
+    fn run_errors() {
+        let mut cpu = Cpu::new(config());
+        cpu.load("inc a\njmp -2").unwrap();
+        assert_eq!(cpu.run(), Err(CpuError::InvalidJump { pc: 1, offset: -2 }));
+
+        let mut cpu = Cpu::new(config());
+        cpu.load("inc a\ntpl a").unwrap();
+        cpu.set_reg(cpu.register("a"), i32::MAX / 3);
+        assert_eq!(cpu.run(), Err(CpuError::Overflow { pc: 1 }));
+        assert_eq!(cpu.run().unwrap_err().to_string(), "Overflow at pc 1");
+
+        // The optimizer checks for overflow too
+        let mut cpu = Cpu::new(config());
+        cpu.load(COUNTED).unwrap();
+        cpu.set_reg(cpu.register("c"), i32::MAX);
+        cpu.set_optimize(true);
+        assert_eq!(cpu.run(), Err(CpuError::Overflow { pc: 0 }));
     }
 }
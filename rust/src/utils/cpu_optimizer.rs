@@ -0,0 +1,410 @@
+use std::ops::Range;
+
+use crate::utils::cpu::*;
+
+/// A loop that can be run in one step. Every loop here has a jio at its head,
+/// and is left at the head once that register is one, so the next step
+/// leaves the loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FastLoop {
+    Counted(CountedLoop),
+    Collatz(CollatzLoop),
+}
+
+/// The registers and cycles taken after running a loop in one step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopResult {
+    pub registers: Vec<i32>,
+    pub cycles: u32,
+}
+
+impl FastLoop {
+    /// Check if the program has a loop that can be run in one step, starting
+    /// at this program counter.
+    pub fn find(program: &[Instruction], head: usize) -> Option<FastLoop> {
+        CountedLoop::find(program, head)
+            .map(FastLoop::Counted)
+            .or_else(|| CollatzLoop::find(program, head).map(FastLoop::Collatz))
+    }
+
+    /// Program counter of the jio at the top of the loop.
+    pub fn head(&self) -> usize {
+        match self {
+            FastLoop::Counted(counted) => counted.head,
+            FastLoop::Collatz(collatz) => collatz.head,
+        }
+    }
+
+    /// The register the jio checks for one.
+    pub fn counter(&self) -> Register {
+        match self {
+            FastLoop::Counted(counted) => counted.counter,
+            FastLoop::Collatz(collatz) => collatz.value,
+        }
+    }
+
+    /// Program counters of every instruction in the loop.
+    pub fn body(&self) -> Range<usize> {
+        let instructions = match self {
+            FastLoop::Counted(counted) => counted.instructions,
+            FastLoop::Collatz(collatz) => collatz.instructions,
+        };
+        self.head()..self.head() + instructions
+    }
+
+    /// Run the loop in one step, with how many cycles each instruction takes.
+    /// None if it can not be run from these registers, like a counter that
+    /// never gets to one. An error if a register or the cycles get too big.
+    pub fn run<F>(
+        &self,
+        program: &[Instruction],
+        registers: &[i32],
+        cycle_count: F,
+    ) -> Result<Option<LoopResult>, CpuError>
+    where
+        F: Fn(&Instruction) -> u32,
+    {
+        match self {
+            FastLoop::Counted(counted) => counted.run(program, registers, cycle_count),
+            FastLoop::Collatz(collatz) => collatz.run(program, registers, cycle_count),
+        }
+    }
+}
+
+/// A loop that counts a register down to one, and only adds to registers.
+/// Running it many times is the same as multiplying each add by the number
+/// of times around the loop, so it can be done in one step. It looks like
+///
+/// ```text
+/// head: jio c, +exit
+///       inc x
+///       dec c
+///       jmp head
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountedLoop {
+    /// Program counter of the jio at the top of the loop.
+    pub head: usize,
+
+    /// The register that is checked for one.
+    pub counter: Register,
+
+    /// How much each register changes every time around the loop.
+    pub deltas: Vec<(Register, i32)>,
+
+    /// How many instructions run every time around the loop.
+    pub instructions: usize,
+}
+
+impl CountedLoop {
+    /// Check if the program has a counted loop starting at this program counter.
+    pub fn find(program: &[Instruction], head: usize) -> Option<CountedLoop> {
+        let Some(Instruction::JumpIfOne(counter, exit)) = program.get(head) else {
+            return None;
+        };
+
+        // Find the jump back to the head, everything before it must be an add
+        let mut deltas: Vec<(Register, i32)> = vec![];
+        for (pc, instruction) in program.iter().enumerate().skip(head + 1) {
+            let (register, delta) = match instruction {
+                Instruction::Jump(o) if pc.checked_add_signed(*o) == Some(head) => {
+                    // The exit must leave the loop
+                    if head.checked_add_signed(*exit)? <= pc {
+                        return None;
+                    }
+                    return Some(CountedLoop {
+                        head,
+                        counter: *counter,
+                        deltas,
+                        instructions: pc - head + 1,
+                    });
+                }
+                _ => add_delta(instruction)?,
+            };
+            match deltas.iter_mut().find(|(r, _)| *r == register) {
+                Some((_, total)) => *total += delta,
+                None => deltas.push((register, delta)),
+            }
+        }
+        None
+    }
+
+    /// How many times the loop runs before the counter is one. None if it
+    /// never gets to one, or if it is already one.
+    pub fn iterations(&self, registers: &[i32]) -> Option<i32> {
+        let step = self.delta(self.counter);
+        let distance = 1i32.checked_sub(registers[self.counter.0])?;
+        if step == 0 || distance == 0 || distance % step != 0 || distance / step < 0 {
+            return None;
+        }
+        Some(distance / step)
+    }
+
+    /// How much a register changes every time around the loop.
+    fn delta(&self, register: Register) -> i32 {
+        self.deltas
+            .iter()
+            .find(|(r, _)| *r == register)
+            .map_or(0, |(_, d)| *d)
+    }
+
+    /// Get the registers after running the loop the given number of times, by
+    /// multiplying each add. None if a register gets too big.
+    pub fn apply(&self, registers: &[i32], iterations: i32) -> Option<Vec<i32>> {
+        apply_deltas(registers, &self.deltas, iterations)
+    }
+
+    /// Run the loop in one step, see FastLoop::run.
+    fn run<F>(
+        &self,
+        program: &[Instruction],
+        registers: &[i32],
+        cycle_count: F,
+    ) -> Result<Option<LoopResult>, CpuError>
+    where
+        F: Fn(&Instruction) -> u32,
+    {
+        let Some(iterations) = self.iterations(registers) else {
+            return Ok(None);
+        };
+        let overflow = || CpuError::Overflow { pc: self.head };
+        let body = &program[self.head..self.head + self.instructions];
+        let cycles = body
+            .iter()
+            .map(cycle_count)
+            .sum::<u32>()
+            .checked_mul(iterations as u32)
+            .ok_or_else(overflow)?;
+        let registers = self.apply(registers, iterations).ok_or_else(overflow)?;
+        Ok(Some(LoopResult { registers, cycles }))
+    }
+}
+
+/// A loop that runs the Collatz steps on a register until it is one, halving
+/// it when even and making it 3x+1 when odd. Other registers can be added to
+/// every time around, like counting the steps. It looks like
+///
+/// ```text
+/// head: jio x, +exit
+///       inc y
+///       jie x, +4
+///       tpl x
+///       inc x
+///       jmp +2
+///       hlf x
+///       jmp head
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollatzLoop {
+    /// Program counter of the jio at the top of the loop.
+    pub head: usize,
+
+    /// The register the Collatz steps are run on.
+    pub value: Register,
+
+    /// How much other registers change every time around the loop.
+    pub deltas: Vec<(Register, i32)>,
+
+    /// Program counter of the jie that picks the odd or even step.
+    pub branch: usize,
+
+    /// How many instructions are in the loop.
+    pub instructions: usize,
+}
+
+impl CollatzLoop {
+    /// Check if the program has a Collatz loop starting at this program counter.
+    pub fn find(program: &[Instruction], head: usize) -> Option<CollatzLoop> {
+        let Some(Instruction::JumpIfOne(value, exit)) = program.get(head) else {
+            return None;
+        };
+
+        // Adds to other registers come first
+        let mut deltas: Vec<(Register, i32)> = vec![];
+        let mut branch = head + 1;
+        while let Some((register, delta)) = program.get(branch).and_then(add_delta) {
+            if register == *value {
+                return None;
+            }
+            match deltas.iter_mut().find(|(r, _)| *r == register) {
+                Some((_, total)) => *total += delta,
+                None => deltas.push((register, delta)),
+            }
+            branch += 1;
+        }
+
+        // Then the odd and even steps, and the jump back to the head
+        let back = branch + 5;
+        let steps = [
+            Instruction::JumpIfEven(*value, 4),
+            Instruction::Triple(*value),
+            Instruction::Increment(*value),
+            Instruction::Jump(2),
+            Instruction::Half(*value),
+            Instruction::Jump(head as isize - back as isize),
+        ];
+        if program.get(branch..=back)? != steps {
+            return None;
+        }
+
+        // The exit must leave the loop
+        if head.checked_add_signed(*exit)? <= back {
+            return None;
+        }
+        Some(CollatzLoop {
+            head,
+            value: *value,
+            deltas,
+            branch,
+            instructions: back - head + 1,
+        })
+    }
+
+    /// Run the loop in one step, see FastLoop::run.
+    fn run<F>(
+        &self,
+        program: &[Instruction],
+        registers: &[i32],
+        cycle_count: F,
+    ) -> Result<Option<LoopResult>, CpuError>
+    where
+        F: Fn(&Instruction) -> u32,
+    {
+        // Zero and negative numbers never get to one, and at one the loop is
+        // left right away
+        let mut value = registers[self.value.0];
+        if value <= 1 {
+            return Ok(None);
+        }
+
+        // Count how many times each step is taken
+        let overflow = || CpuError::Overflow { pc: self.head };
+        let (mut odd, mut even) = (0u32, 0u32);
+        while value != 1 {
+            if value % 2 == 0 {
+                value /= 2;
+                even += 1;
+            } else {
+                value = value
+                    .checked_mul(3)
+                    .and_then(|v| v.checked_add(1))
+                    .ok_or_else(overflow)?;
+                odd += 1;
+            }
+        }
+
+        // Every time around runs the head, the adds and the branch, then the
+        // odd step or the even step, then the jump back
+        let cycles = |pcs: Range<usize>| program[pcs].iter().map(&cycle_count).sum::<u32>();
+        let back = self.head + self.instructions - 1;
+        let shared = cycles(self.head..self.branch + 1) + cycles(back..back + 1);
+        let odd_step = cycles(self.branch + 1..self.branch + 4);
+        let even_step = cycles(self.branch + 4..back);
+        let iterations = odd + even;
+        let cycles = shared
+            .checked_mul(iterations)
+            .and_then(|c| c.checked_add(odd_step.checked_mul(odd)?))
+            .and_then(|c| c.checked_add(even_step.checked_mul(even)?))
+            .ok_or_else(overflow)?;
+
+        let iterations = i32::try_from(iterations).map_err(|_| overflow())?;
+        let mut registers =
+            apply_deltas(registers, &self.deltas, iterations).ok_or_else(overflow)?;
+        registers[self.value.0] = value;
+        Ok(Some(LoopResult { registers, cycles }))
+    }
+}
+
+/// The register and amount an instruction adds, if it only adds.
+fn add_delta(instruction: &Instruction) -> Option<(Register, i32)> {
+    match instruction {
+        Instruction::Add(r, i) => Some((*r, *i)),
+        Instruction::Increment(r) => Some((*r, 1)),
+        Instruction::Decrement(r) => Some((*r, -1)),
+        _ => None,
+    }
+}
+
+/// Get the registers after adding each delta the given number of times. None
+/// if a register gets too big.
+fn apply_deltas(registers: &[i32], deltas: &[(Register, i32)], times: i32) -> Option<Vec<i32>> {
+    let mut registers = registers.to_vec();
+    for (register, delta) in deltas.iter() {
+        let total = delta.checked_mul(times)?;
+        registers[register.0] = registers[register.0].checked_add(total)?;
+    }
+    Some(registers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(program: &str) -> Vec<Instruction> {
+        CpuConfig::turing_lock()
+            .mnemonic("dec", Opcode::Decrement)
+            .parse(program)
+            .unwrap()
+    }
+
+    #[test]
+    fn find_counted_loop() {
+        let program = parse("inc a\njio b, +5\ninc a\ninc a\ndec b\njmp -4");
+        assert_eq!(FastLoop::find(&program, 0), None);
+        let Some(FastLoop::Counted(counted)) = FastLoop::find(&program, 1) else {
+            panic!("Should be a counted loop");
+        };
+        assert_eq!(counted.deltas, [(Register(0), 2), (Register(1), -1)]);
+        assert_eq!(FastLoop::Counted(counted.clone()).body(), 1..6);
+        assert_eq!(counted.iterations(&[0, 4]), Some(3));
+        assert_eq!(counted.iterations(&[0, 1]), None);
+        assert_eq!(counted.iterations(&[0, -4]), None);
+        assert_eq!(counted.apply(&[5, 4], 3), Some(vec![11, 1]));
+
+        // Anything but adds in the loop, or an exit inside the loop, is not counted
+        assert_eq!(
+            FastLoop::find(&parse("jio b, +4\ntpl a\ndec b\njmp -3"), 0),
+            None
+        );
+        assert_eq!(FastLoop::find(&parse("jio b, +2\ndec b\njmp -2"), 0), None);
+        assert_eq!(FastLoop::find(&parse("jio b, +3\ndec b"), 0), None);
+    }
+
+    #[test]
+    fn find_collatz_loop() {
+        let program = parse("jio a, +8\ninc b\njie a, +4\ntpl a\ninc a\njmp +2\nhlf a\njmp -7");
+        let Some(FastLoop::Collatz(collatz)) = FastLoop::find(&program, 0) else {
+            panic!("Should be a Collatz loop");
+        };
+        assert_eq!(collatz.branch, 2);
+        assert_eq!(collatz.deltas, [(Register(1), 1)]);
+
+        // Adds to the Collatz register, or a different step, are not Collatz
+        let program = parse("jio a, +8\ninc a\njie a, +4\ntpl a\ninc a\njmp +2\nhlf a\njmp -7");
+        assert_eq!(FastLoop::find(&program, 0), None);
+        let program = parse("jio a, +8\ninc b\njie a, +4\ntpl b\ninc a\njmp +2\nhlf a\njmp -7");
+        assert_eq!(FastLoop::find(&program, 0), None);
+    }
+
+    #[test]
+    fn run_loops() {
+        let cycles = |i: &Instruction| match i {
+            Instruction::Triple(_) => 3,
+            _ => 1,
+        };
+        let program = parse("jio a, +8\ninc b\njie a, +4\ntpl a\ninc a\njmp +2\nhlf a\njmp -7");
+        let fast = FastLoop::find(&program, 0).unwrap();
+
+        // 3 -> 10 -> 5 -> 16 -> 8 -> 4 -> 2 -> 1, two odd steps and five even
+        let result = fast.run(&program, &[3, 0], cycles).unwrap().unwrap();
+        assert_eq!(result.registers, [1, 7]);
+        assert_eq!(result.cycles, 7 * 4 + 2 * 5 + 5);
+        for value in [1, 0, -5] {
+            assert_eq!(fast.run(&program, &[value, 0], cycles), Ok(None));
+        }
+        assert_eq!(
+            fast.run(&program, &[i32::MAX, 0], cycles),
+            Err(CpuError::Overflow { pc: 0 })
+        );
+    }
+}
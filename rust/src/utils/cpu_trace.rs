@@ -0,0 +1,262 @@
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::utils::cpu::*;
+
+/// A record of everything a CPU ran. Each instruction run is one entry, with
+/// the registers it changed. Instead of keeping the state of every cycle, the
+/// state at any cycle is rebuilt by replaying the changes from the start.
+/// Traces can be written to a text file and read back to replay later.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// Register values before the first instruction.
+    start: Vec<i32>,
+
+    /// Each instruction run, in order.
+    entries: Vec<TraceEntry>,
+}
+
+/// One instruction that was run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// The first cycle of the instruction.
+    pub cycle: u32,
+
+    /// How many cycles the instruction took.
+    pub cycles: u32,
+
+    /// The program counter of the instruction.
+    pub pc: usize,
+
+    /// The program counter after the instruction.
+    pub next_pc: usize,
+
+    /// The instruction that was run.
+    pub instruction: Instruction,
+
+    /// Registers that changed, with their new value.
+    pub changes: Vec<(Register, i32)>,
+}
+
+impl TraceEntry {
+    /// The cycles this instruction was running for.
+    pub fn cycle_range(&self) -> RangeInclusive<u32> {
+        self.cycle..=self.cycle + self.cycles - 1
+    }
+}
+
+impl Trace {
+    /// Create a new trace, from the registers at the start.
+    pub fn new(start: Vec<i32>) -> Trace {
+        Trace {
+            start,
+            entries: vec![],
+        }
+    }
+
+    /// Add an instruction that was run.
+    pub fn push(&mut self, entry: TraceEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Get all the instructions that were run.
+    #[allow(dead_code)]
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Get the instructions that were running during any of these cycles.
+    #[allow(dead_code)]
+    pub fn between(&self, cycles: RangeInclusive<u32>) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter().filter(move |e| {
+            let range = e.cycle_range();
+            range.start() <= cycles.end() && range.end() >= cycles.start()
+        })
+    }
+
+    /// Rebuild the CPU state during a cycle, by replaying changes from the
+    /// start. Registers only change at the end of an instruction's last cycle.
+    pub fn state_during(&self, cycle: u32) -> State {
+        let mut registers = self.start.clone();
+        let mut pc = 0;
+        for entry in self.entries.iter() {
+            if *entry.cycle_range().end() >= cycle {
+                // This instruction is still running, or has not started yet
+                if entry.cycle <= cycle {
+                    pc = entry.pc;
+                }
+                break;
+            }
+            for (register, value) in entry.changes.iter() {
+                registers[register.0] = *value;
+            }
+            pc = entry.next_pc;
+        }
+        State::new(cycle, registers, pc)
+    }
+
+    /// Rebuild the CPU state during each of these cycles.
+    pub fn sample(&self, cycles: impl Iterator<Item = u32>) -> Vec<State> {
+        cycles.map(|cycle| self.state_during(cycle)).collect()
+    }
+
+    /// Save the trace as a text file.
+    #[allow(dead_code)]
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Read a trace from a text file.
+    #[allow(dead_code)]
+    pub fn read(path: &Path) -> Result<Trace> {
+        let text = std::fs::read_to_string(path)?;
+        Trace::parse(&text)
+    }
+
+    /// Parse a trace from text. The first line is the start registers, like
+    /// "start 1 0". Then each line is an entry, like
+    /// "3 2 1 2 add r0 15 | r0=16", for cycle, cycles, pc, next pc,
+    /// instruction and changes.
+    pub fn parse(text: &str) -> Result<Trace> {
+        let mut lines = text.lines();
+        let Some(start) = lines.next().and_then(|l| l.strip_prefix("start")) else {
+            bail!("Trace must begin with start registers");
+        };
+        let start = start
+            .split_whitespace()
+            .map(|v| v.parse())
+            .collect::<Result<_, _>>()?;
+
+        let mut trace = Trace::new(start);
+        for line in lines {
+            let entry = parse_entry(line).with_context(|| format!("Bad trace line {line}"))?;
+            trace.push(entry);
+        }
+        Ok(trace)
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "start")?;
+        for value in self.start.iter() {
+            write!(f, " {value}")?;
+        }
+        writeln!(f)?;
+
+        for entry in self.entries.iter() {
+            write!(
+                f,
+                "{} {} {} {} {} |",
+                entry.cycle, entry.cycles, entry.pc, entry.next_pc, entry.instruction
+            )?;
+            for (register, value) in entry.changes.iter() {
+                write!(f, " {register}={value}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse one entry line of a trace.
+fn parse_entry(line: &str) -> Result<TraceEntry> {
+    let Some((left, changes)) = line.split_once('|') else {
+        bail!("Missing changes");
+    };
+    let mut parts = left.split_whitespace();
+    let mut number = || -> Result<usize> { Ok(parts.next().context("Missing number")?.parse()?) };
+    let cycle = number()? as u32;
+    let cycles = number()? as u32;
+    let pc = number()?;
+    let next_pc = number()?;
+    let instruction = parse_instruction(&parts.collect::<Vec<_>>())?;
+
+    let changes = changes
+        .split_whitespace()
+        .map(|change| {
+            let (register, value) = change.split_once('=').context("Bad change")?;
+            Ok((parse_register(register)?, value.parse()?))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(TraceEntry {
+        cycle,
+        cycles,
+        pc,
+        next_pc,
+        instruction,
+        changes,
+    })
+}
+
+/// Parse a register written like r0.
+fn parse_register(text: &str) -> Result<Register> {
+    let Some(index) = text.strip_prefix('r') else {
+        bail!("Bad register {text}");
+    };
+    Ok(Register(index.parse()?))
+}
+
+/// Parse an instruction written the way Instruction is displayed.
+fn parse_instruction(parts: &[&str]) -> Result<Instruction> {
+    let instruction = match parts {
+        ["add", r, v] => Instruction::Add(parse_register(r)?, v.parse()?),
+        ["noop"] => Instruction::Noop,
+        ["hlf", r] => Instruction::Half(parse_register(r)?),
+        ["tpl", r] => Instruction::Triple(parse_register(r)?),
+        ["inc", r] => Instruction::Increment(parse_register(r)?),
+        ["dec", r] => Instruction::Decrement(parse_register(r)?),
+        ["jmp", o] => Instruction::Jump(o.parse()?),
+        ["jie", r, o] => Instruction::JumpIfEven(parse_register(r)?, o.parse()?),
+        ["jio", r, o] => Instruction::JumpIfOne(parse_register(r)?, o.parse()?),
+        _ => bail!("Bad instruction {parts:?}"),
+    };
+    Ok(instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace() -> Trace {
+        let mut cpu = Cpu::new(CpuConfig::handheld());
+        cpu.load("noop\naddx 3\naddx -5").unwrap();
+        cpu.set_trace(true);
+        cpu.run().unwrap();
+        cpu.trace().unwrap().clone()
+    }
+
+    #[test]
+    fn replay() {
+        let trace = trace();
+        assert_eq!(trace.entries().len(), 3);
+        let x: Vec<i32> = trace.sample(1..=6).iter().map(|s| s.registers[0]).collect();
+        assert_eq!(x, [1, 1, 1, 4, 4, -1]);
+        assert_eq!(trace.state_during(3).pc(), 1);
+        assert_eq!(trace.state_during(3).time(), 3);
+        let pcs: Vec<usize> = trace.between(3..=4).map(|e| e.pc).collect();
+        assert_eq!(pcs, [1, 2]);
+    }
+
+    #[test]
+    fn write_and_read() {
+        let trace = trace();
+        let text = trace.to_string();
+        assert!(text.starts_with("start 1\n1 1 0 1 noop |\n2 2 1 2 add r0 3 | r0=4\n"));
+        assert_eq!(Trace::parse(&text).unwrap(), trace);
+
+        let path = std::env::temp_dir().join("aoc_cpu_trace.txt");
+        trace.write(&path).unwrap();
+        let read = Trace::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, trace);
+
+        assert!(Trace::parse("1 1 0 1 noop |").is_err());
+        assert!(Trace::parse("start 1\n1 1 0 1 noop").is_err());
+        assert!(Trace::parse("start 1\n1 1 0 1 noop | x0=1").is_err());
+    }
+}
@@ -1,18 +1,43 @@
+use anyhow::Result;
 use grid::*;
+use std::path::Path;
+
+use crate::utils::cpu::*;
 
 /// A CRT screen display. It draws a pixel if the user set sprite location
 /// overlaps the internal timer.
-/// The sprite is 3 units wide but could be made a configurable width.
+/// The sprite is centered on its location, and is 3 units wide by default.
 /// The timer scans the whole display one row at a time. The user set sprite
 /// location is only the width of a single row, which will match whatever row
 /// the timer currently is on.
-/// A pixel is either on or off but could be expanded to have more states (like color).
+/// Pixels are states, shown with a palette. By default a pixel is off '.' or
+/// on '#', but more states can be added, like colors.
+/// The sprite location can come from a CPU register, and the CRT can record
+/// a frame each time it finishes a row.
 pub struct Crt {
-    grid: Grid<char>,
+    grid: Grid<usize>,
     width: usize,
     time: usize,
+
+    /// How many pixels wide the sprite is.
+    sprite_width: usize,
+
+    /// Char to show for each pixel state.
+    palette: Vec<char>,
+
+    /// Register the sprite location is read from when driven by a CPU.
+    register: Option<Register>,
+
+    /// Whole screen after each finished row, if recording.
+    frames: Option<Vec<String>>,
 }
 
+/// Pixel state when the sprite is not drawn.
+pub const PIXEL_OFF: usize = 0;
+
+/// Pixel state when the sprite is drawn.
+pub const PIXEL_ON: usize = 1;
+
 impl Crt {
     /// Create a new CRT. Time starts at zero.
     pub fn new(width: usize, height: usize) -> Crt {
@@ -20,45 +45,111 @@ impl Crt {
             grid: Grid::new(height, width),
             width,
             time: 0,
+            sprite_width: 3,
+            palette: vec!['.', '#'],
+            register: None,
+            frames: None,
         }
     }
 
-    /// Set a pixel to a certain value. The at value is the current time, it
+    /// Set how many pixels wide the sprite is. Even widths have one more pixel
+    /// to the right of the location than to the left.
+    #[allow(dead_code)]
+    pub fn set_sprite_width(&mut self, sprite_width: usize) {
+        assert!(sprite_width > 0, "Sprite must be at least 1 wide");
+        self.sprite_width = sprite_width;
+    }
+
+    /// Set the chars to show for each pixel state.
+    #[allow(dead_code)]
+    pub fn set_palette(&mut self, palette: &[char]) {
+        assert!(palette.len() >= 2, "Palette needs an off and on char");
+        self.palette = palette.to_vec();
+    }
+
+    /// Read the sprite location from this register when driven by a CPU.
+    pub fn attach(&mut self, register: Register) {
+        self.register = Some(register);
+    }
+
+    /// Record a frame of the whole screen each time a row is finished.
+    #[allow(dead_code)]
+    pub fn record_frames(&mut self) {
+        self.frames = Some(vec![]);
+    }
+
+    /// Get the recorded frames, one per finished row.
+    pub fn frames(&self) -> &[String] {
+        self.frames.as_deref().unwrap_or_default()
+    }
+
+    /// Save the recorded frames to a text file, split by blank lines.
+    #[allow(dead_code)]
+    pub fn write_frames(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.frames().join("\n"))?;
+        Ok(())
+    }
+
+    /// Has every pixel been drawn?
+    pub fn is_full(&self) -> bool {
+        self.time >= self.grid.rows() * self.width
+    }
+
+    /// Set a pixel to a certain state. The at value is the current time, it
     /// scans over the full grid before wrapping around.
-    fn set_pixel(&mut self, at: usize, value: char) {
+    fn set_pixel(&mut self, at: usize, state: usize) {
+        let at = at % (self.grid.rows() * self.width);
         let x = at % self.width;
         let y = at / self.width;
-        self.grid[y][x] = value;
+        self.grid[y][x] = state;
     }
 
     /// Print the grid value as one long string.
     pub fn to_string(&self) -> String {
-        self.grid.iter().fold("".to_string(), |mut a, c| {
-            a.push(*c);
-            a
-        })
+        self.grid.iter().map(|state| self.palette[*state]).collect()
     }
 
-    /// Print just the current row that the CRT is scanning based on the current time.
+    /// Print the grid as rows, for easy display.
+    pub fn to_rows(&self) -> String {
+        let chars: Vec<char> = self.to_string().chars().collect();
+        chars
+            .chunks(self.width)
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
+    }
+
+    /// Print just the current row that the CRT is scanning based on the current
+    /// time, when debug logging is on.
     fn print_current_row(&self) {
-        let start = self.time / self.width;
-        let word = self.to_string();
-        let part = &word[start..start + self.width];
+        // Building the row runs every cycle, skip it unless it will be shown
+        if !log::log_enabled!(log::Level::Debug) {
+            return;
+        }
+        let row = self.time % (self.grid.rows() * self.width) / self.width;
+        let part: String = self.grid.iter_row(row).map(|s| self.palette[*s]).collect();
         log::debug!("Current CRT row: {}", part);
     }
 
-    /// Based on the user set sprite, print where the sprite location is.
-    pub fn print_sprite(&self, sprite: usize) {
-        let mut draw_sprite = vec!['.'; self.width];
-        if sprite > 0 && sprite < self.width - 1 {
-            draw_sprite[sprite - 1] = '#';
-            draw_sprite[sprite] = '#';
-            draw_sprite[sprite + 1] = '#';
+    /// Which pixels of a row the sprite covers. May go past either edge.
+    fn sprite_range(&self, sprite: i32) -> std::ops::RangeInclusive<i32> {
+        let left = (self.sprite_width as i32 - 1) / 2;
+        let right = self.sprite_width as i32 / 2;
+        sprite - left..=sprite + right
+    }
+
+    /// Based on the user set sprite, print where the sprite location is, when
+    /// debug logging is on. Parts of the sprite past the edge of the row are
+    /// not shown.
+    pub fn print_sprite(&self, sprite: i32) {
+        // Building the sprite runs every cycle, skip it unless it will be shown
+        if !log::log_enabled!(log::Level::Debug) {
+            return;
         }
-        log::debug!(
-            "Sprite position: {}\n",
-            draw_sprite.into_iter().collect::<String>()
-        );
+        let range = self.sprite_range(sprite);
+        let draw_sprite: String = (0..self.width as i32)
+            .map(|x| self.palette[range.contains(&x) as usize])
+            .collect();
+        log::debug!("Sprite position: {}\n", draw_sprite);
     }
 
     /// Increment the internal timer of the CRT and set the value of the pixel
@@ -69,9 +160,9 @@ impl Crt {
         let row_clock: i32 = self.time as i32 % self.width as i32;
 
         // If the row time overlaps the sprite, then light up the pixel
-        let pixel = match self.time {
-            _ if (sprite - 1..=sprite + 1).contains(&row_clock) => '#',
-            _ => '.',
+        let state = match self.sprite_range(sprite).contains(&row_clock) {
+            true => PIXEL_ON,
+            false => PIXEL_OFF,
         };
 
         log::debug!(
@@ -80,10 +171,98 @@ impl Crt {
             self.time,
             sprite,
         );
+        self.step_state(state);
+    }
 
-        self.set_pixel(self.time, pixel);
+    /// Increment the internal timer of the CRT and set the pixel to any state
+    /// in the palette.
+    pub fn step_state(&mut self, state: usize) {
+        assert!(
+            state < self.palette.len(),
+            "Pixel state {state} has no char"
+        );
+        self.set_pixel(self.time, state);
         self.print_current_row();
 
         self.time += 1;
+        if self.time.is_multiple_of(self.width) {
+            if let Some(mut frames) = self.frames.take() {
+                frames.push(self.to_rows());
+                self.frames = Some(frames);
+            }
+        }
+    }
+
+    /// Run a CPU and draw a pixel for every cycle, using the attached register
+    /// as the sprite location. Registers change at the end of an instruction,
+    /// so every cycle of an instruction uses the value from before it.
+    /// Stops when the CPU halts or the screen is full.
+    pub fn run(&mut self, cpu: &mut Cpu) -> Result<()> {
+        let register = self.register.expect("Attach a register to run a CPU");
+        while !self.is_full() {
+            let sprite = cpu.reg(register);
+            let time = cpu.time();
+            self.print_sprite(sprite);
+            if !cpu.step()? {
+                break;
+            }
+            for _ in time..cpu.time() {
+                self.step(sprite);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprite_widths() {
+        let mut crt = Crt::new(6, 1);
+        for _ in 0..6 {
+            crt.step(2);
+        }
+        assert_eq!(crt.to_string(), ".###..");
+
+        let mut crt = Crt::new(6, 1);
+        crt.set_sprite_width(2);
+        crt.set_palette(&[' ', '@']);
+        for _ in 0..6 {
+            crt.step(0);
+        }
+        assert_eq!(crt.to_string(), "@@    ");
+    }
+
+    #[test]
+    fn frames() {
+        let mut crt = Crt::new(2, 2);
+        crt.set_palette(&['.', '#', 'o']);
+        crt.record_frames();
+        for state in [PIXEL_ON, 2, PIXEL_OFF] {
+            crt.step_state(state);
+        }
+        assert!(!crt.is_full());
+        crt.step_state(PIXEL_ON);
+        assert!(crt.is_full());
+        assert_eq!(crt.frames(), ["#o\n..\n", "#o\n.#\n"]);
+        assert_eq!(crt.to_rows(), "#o\n.#\n");
+
+        let path = std::env::temp_dir().join("aoc_crt_frames.txt");
+        crt.write_frames(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(text, "#o\n..\n\n#o\n.#\n");
+    }
+
+    #[test]
+    fn run_cpu() {
+        let mut cpu = Cpu::new(CpuConfig::handheld());
+        cpu.load("addx 3\nnoop\naddx -3\nnoop").unwrap();
+        let mut crt = Crt::new(8, 1);
+        crt.attach(cpu.register("x"));
+        crt.run(&mut cpu).unwrap();
+        assert_eq!(crt.to_string(), "##.##...");
     }
 }
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Where a sequence of states starts to repeat. The state at step `start` is
+/// the same as the state at step `start + length`, and so on forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Get the first step that has the same state as any later step.
+    pub fn equivalent_step(&self, step: u64) -> usize {
+        let start = self.start as u64;
+        if step < start {
+            return step as usize;
+        }
+        (start + (step - start) % self.length as u64) as usize
+    }
+
+    /// Get a metric at any step, like the height of a stack after a trillion
+    /// steps. `metrics` has the metric for each step done so far, and must go
+    /// through one full cycle. The metric is assumed to change by the same
+    /// amount every cycle.
+    pub fn extrapolate(&self, metrics: &[i64], step: u64) -> i64 {
+        let end = self.start + self.length;
+        assert!(metrics.len() > end, "Need metrics for one full cycle");
+        if step < metrics.len() as u64 {
+            return metrics[step as usize];
+        }
+
+        let per_cycle = metrics[end] - metrics[self.start];
+        let cycles = (step - self.start as u64) / self.length as u64;
+        metrics[self.equivalent_step(step)] + cycles as i64 * per_cycle
+    }
+}
+
+/// Find a cycle by remembering every state seen. Insert the state of each
+/// step, and a cycle is returned as soon as a state is seen again. Steps may
+/// be skipped, like while a simulation warms up. This works for any state that
+/// can be hashed, but keeps every state in memory.
+#[derive(Debug)]
+pub struct CycleDetector<K> {
+    seen: HashMap<K, usize>,
+    cycle: Option<Cycle>,
+}
+
+impl<K> CycleDetector<K>
+where
+    K: Hash + Eq,
+{
+    /// Create a new detector with nothing seen yet.
+    pub fn new() -> CycleDetector<K> {
+        CycleDetector {
+            seen: HashMap::new(),
+            cycle: None,
+        }
+    }
+
+    /// Add the state seen at a step. Return the cycle once found.
+    pub fn insert(&mut self, step: usize, key: K) -> Option<Cycle> {
+        if self.cycle.is_none() {
+            if let Some(start) = self.seen.insert(key, step) {
+                self.cycle = Some(Cycle {
+                    start,
+                    length: step - start,
+                });
+            }
+        }
+        self.cycle
+    }
+
+    /// Get the cycle, if one has been found.
+    #[allow(dead_code)]
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+}
+
+/// Find a cycle with Floyd's tortoise and hare. This only keeps two states in
+/// memory, but runs the step function about three times more than hashing.
+#[allow(dead_code)]
+pub fn floyd<S, F>(start: S, step: F) -> Cycle
+where
+    S: PartialEq + Clone,
+    F: Fn(&S) -> S,
+{
+    // The hare goes twice as fast, they meet somewhere inside the cycle
+    let mut tortoise = step(&start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    // Restart the tortoise, moving both at the same speed they meet at the start
+    let mut start_step = 0;
+    tortoise = start;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start_step += 1;
+    }
+
+    // Walk the hare once around the cycle
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    Cycle {
+        start: start_step,
+        length,
+    }
+}
+
+/// Find a cycle with Brent's algorithm. This only keeps two states in memory,
+/// and usually runs the step function fewer times than Floyd's.
+pub fn brent<S, F>(start: S, step: F) -> Cycle
+where
+    S: PartialEq + Clone,
+    F: Fn(&S) -> S,
+{
+    // Search powers of two for the cycle length
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(&start);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    // Put the hare one cycle ahead, then move both until they meet at the start
+    tortoise = start.clone();
+    hare = start;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    let mut start_step = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start_step += 1;
+    }
+
+    Cycle {
+        start: start_step,
+        length,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Find a cycle by hashing every state, to check the other finders.
+    fn hashed<S, F>(start: S, step: F) -> Cycle
+    where
+        S: Hash + Eq + Clone,
+        F: Fn(&S) -> S,
+    {
+        let mut detector = CycleDetector::new();
+        let mut state = start;
+        for steps in 0.. {
+            if let Some(cycle) = detector.insert(steps, state.clone()) {
+                return cycle;
+            }
+            state = step(&state);
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn finders_agree() {
+        // x * x + 1 mod m has a tail then a cycle, for many starts and moduli
+        for m in 1..200u64 {
+            for start in 0..m.min(20) {
+                let step = |x: &u64| (x * x + 1) % m;
+                let expected = hashed(start, step);
+                assert_eq!(floyd(start, step), expected, "floyd {start} mod {m}");
+                assert_eq!(brent(start, step), expected, "brent {start} mod {m}");
+            }
+        }
+    }
+
+    #[test]
+    fn known_cycle() {
+        // 0 1 2 3 4 5 6 then back to 3
+        let step = |x: &u32| if *x == 6 { 3 } else { x + 1 };
+        let cycle = Cycle {
+            start: 3,
+            length: 4,
+        };
+        assert_eq!(floyd(0, step), cycle);
+        assert_eq!(brent(0, step), cycle);
+        assert_eq!(hashed(0, step), cycle);
+
+        // A fixed point is a cycle of length one
+        assert_eq!(
+            brent(7, |x: &u32| *x),
+            Cycle {
+                start: 0,
+                length: 1
+            }
+        );
+        assert_eq!(
+            floyd(7, |x: &u32| *x),
+            Cycle {
+                start: 0,
+                length: 1
+            }
+        );
+    }
+
+    #[test]
+    fn detector_skips_steps() {
+        let mut detector = CycleDetector::new();
+        assert_eq!(detector.insert(10, 'a'), None);
+        assert_eq!(detector.insert(12, 'b'), None);
+        let cycle = Cycle {
+            start: 10,
+            length: 5,
+        };
+        assert_eq!(detector.insert(15, 'a'), Some(cycle));
+        assert_eq!(detector.insert(16, 'z'), Some(cycle));
+        assert_eq!(detector.cycle(), Some(cycle));
+    }
+
+    #[test]
+    fn extrapolate_metric() {
+        // Grows by 1 in a tail of 2 steps, then by 3, 0, 2 forever
+        let cycle = Cycle {
+            start: 2,
+            length: 3,
+        };
+        let metrics = [0, 1, 2, 5, 5, 7];
+        let slow = |step: u64| {
+            let mut value = 2;
+            for i in 2..step {
+                value += [3, 0, 2][((i - 2) % 3) as usize];
+            }
+            if step < 2 {
+                step as i64
+            } else {
+                value
+            }
+        };
+        for step in 0..50 {
+            assert_eq!(cycle.extrapolate(&metrics, step), slow(step), "step {step}");
+        }
+        assert_eq!(cycle.equivalent_step(1), 1);
+        assert_eq!(cycle.equivalent_step(1_000_000_000_000), 4);
+        assert_eq!(
+            cycle.extrapolate(&metrics, 1_000_000_000_000),
+            5 * 333_333_333_333
+        );
+    }
+}
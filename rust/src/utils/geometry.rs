@@ -0,0 +1,155 @@
+use std::ops::RangeInclusive;
+
+use crate::utils::point::Point;
+use crate::utils::ranges::RangeSet;
+
+/// A manhattan circle, every point within a taxi cab distance of the center.
+/// On a grid this is a diamond, a square turned 45 degrees. The diamond is
+/// never drawn, it is all math, so it can be millions of squares big.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Diamond {
+    pub center: Point<2>,
+    pub radius: i32,
+}
+
+impl Diamond {
+    /// Create a diamond. A radius of 0 is just the center point.
+    pub fn new(center: Point<2>, radius: i32) -> Diamond {
+        Diamond { center, radius }
+    }
+
+    /// Create the diamond around a center that just reaches the edge point.
+    pub fn from_edge(center: Point<2>, edge: Point<2>) -> Diamond {
+        Diamond::new(center, center.manhattan(&edge))
+    }
+
+    /// Is this point inside the diamond, or on its edge?
+    pub fn contains(&self, point: Point<2>) -> bool {
+        self.center.manhattan(&point) <= self.radius
+    }
+
+    /// The rows that the diamond touches.
+    pub fn rows(&self) -> RangeInclusive<i32> {
+        self.center.y() - self.radius..=self.center.y() + self.radius
+    }
+
+    /// The part of a row inside the diamond, if the diamond touches the row.
+    /// Each row away from the center is one smaller on both sides.
+    pub fn row(&self, y: i32) -> Option<RangeInclusive<i32>> {
+        let half_width = self.radius - (self.center.y() - y).abs();
+        if half_width < 0 {
+            return None;
+        }
+        let x = self.center.x();
+        Some(x - half_width..=x + half_width)
+    }
+
+    /// The 4 lines just outside the diamond, in turned coordinates. The first
+    /// two are lines where x + y is the same, the last two where x - y is the
+    /// same. A point not in any diamond, boxed in by diamonds, is on these lines.
+    fn outside_lines(&self) -> [i64; 4] {
+        let (x, y, r) = (
+            self.center.x() as i64,
+            self.center.y() as i64,
+            self.radius as i64 + 1,
+        );
+        [x + y - r, x + y + r, x - y - r, x - y + r]
+    }
+}
+
+/// Find which squares of a row are inside any of the diamonds.
+pub fn row_coverage(diamonds: &[Diamond], y: i32) -> RangeSet<i32> {
+    diamonds.iter().filter_map(|d| d.row(y)).collect()
+}
+
+/// Find a point inside the box from min to max, inclusive, that is not in any
+/// diamond. This is meant for when there is a single uncovered point. Such a
+/// point is boxed in, so it sits where the lines just outside the diamonds,
+/// or the edges of the box, cross. Only those crossings are checked.
+pub fn find_uncovered(diamonds: &[Diamond], min: Point<2>, max: Point<2>) -> Option<Point<2>> {
+    // Lines where x + y is the same, and lines where x - y is the same
+    let mut sums = vec![];
+    let mut diffs = vec![];
+    for diamond in diamonds {
+        let lines = diamond.outside_lines();
+        sums.extend(&lines[..2]);
+        diffs.extend(&lines[2..]);
+    }
+    sums.sort();
+    sums.dedup();
+    diffs.sort();
+    diffs.dedup();
+
+    // Crossings of the turned lines with each other
+    let mut candidates = vec![];
+    for &sum in sums.iter() {
+        for &diff in diffs.iter() {
+            if (sum + diff) % 2 == 0 {
+                candidates.push(((sum + diff) / 2, (sum - diff) / 2));
+            }
+        }
+    }
+
+    // Crossings of the turned lines with the edges of the box, and the box corners
+    let (min_x, min_y) = (min.x() as i64, min.y() as i64);
+    let (max_x, max_y) = (max.x() as i64, max.y() as i64);
+    for x in [min_x, max_x] {
+        candidates.extend(sums.iter().map(|s| (x, s - x)));
+        candidates.extend(diffs.iter().map(|d| (x, x - d)));
+        candidates.extend([(x, min_y), (x, max_y)]);
+    }
+    for y in [min_y, max_y] {
+        candidates.extend(sums.iter().map(|s| (s - y, y)));
+        candidates.extend(diffs.iter().map(|d| (d + y, y)));
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(x, y)| (min_x..=max_x).contains(x) && (min_y..=max_y).contains(y))
+        .map(|(x, y)| Point::<2>::new(x as i32, y as i32))
+        .find(|&point| !diamonds.iter().any(|d| d.contains(point)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_and_row_agree() {
+        let diamond = Diamond::new(Point::<2>::new(2, 3), 2);
+        assert_eq!(diamond.rows(), 1..=5);
+        assert_eq!(diamond.row(0), None);
+        assert_eq!(diamond.row(1), Some(2..=2));
+        assert_eq!(diamond.row(3), Some(0..=4));
+        assert_eq!(diamond.row(6), None);
+        for y in diamond.rows() {
+            for x in diamond.row(y).unwrap() {
+                assert!(diamond.contains(Point::<2>::new(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn coverage_and_uncovered() {
+        let diamonds = [
+            Diamond::new(Point::<2>::new(0, 0), 1),
+            Diamond::new(Point::<2>::new(3, 0), 1),
+        ];
+        let covered = row_coverage(&diamonds, 0);
+        assert_eq!(covered.ranges(), &[-1..=4]);
+        assert_eq!(covered.len(), 6);
+
+        let box_diamonds = [
+            Diamond::new(Point::<2>::new(0, 0), 3),
+            Diamond::new(Point::<2>::new(4, 0), 3),
+            Diamond::new(Point::<2>::new(0, 4), 3),
+            Diamond::new(Point::<2>::new(4, 4), 3),
+        ];
+        let min = Point::<2>::new(0, 0);
+        let max = Point::<2>::new(4, 4);
+        assert_eq!(
+            find_uncovered(&box_diamonds, min, max),
+            Some(Point::<2>::new(2, 2))
+        );
+    }
+}
@@ -1,98 +1,328 @@
+use std::collections::VecDeque;
+
+use crate::utils::point::Point;
+
 /// A 3D grid. This differs from Board3d, which is a cube shaped game board.
 /// This is a true 3d map, all internal locations are accessible.
+/// Each axis has its own size, and coordinates can be negative. Setting a
+/// value outside of the grid grows the grid to fit it. Reading a value outside
+/// of the grid gives the init value, so the grid can be used as a sparse map.
+/// The values are stored with room to grow, so setting points one at a time
+/// does not copy the whole grid each time.
+#[derive(Debug, Clone)]
 pub struct Grid3d<T> {
-    grid: Vec<Vec<Vec<T>>>,
-    size: usize,
-}
+    /// All stored values, in x then y then z order
+    values: Vec<T>,
 
-#[derive(Debug, Clone)]
-pub struct Point3d {
-    pub x: usize,
-    pub y: usize,
-    pub z: usize,
+    /// The smallest stored point, inclusive
+    origin: Point3d,
+
+    /// How many values are stored on each axis
+    lengths: Point3d,
+
+    /// The smallest point in the grid, inclusive
+    min: Point3d,
+
+    /// The largest point in the grid, inclusive. Smaller than min if empty.
+    max: Point3d,
+
+    /// Value for new or outside locations
+    init_value: T,
 }
 
+/// Simple way to use a 3D Point
+pub type Point3d = Point<3>;
+
 impl<T> Grid3d<T>
 where
     T: Clone,
 {
-    /// Create a new 3D grid of a fixed size. Only cube is supported.
+    /// Create a new 3D cube grid, from 0 to size - 1 on each axis.
+    #[allow(dead_code)]
     pub fn new(size: usize, init_value: T) -> Grid3d<T> {
+        let max = size as i32 - 1;
+        Grid3d::new_bounds(Point3d::default(), Point3d::splat(max), init_value)
+    }
+
+    /// Create a new 3D grid from min to max, inclusive.
+    pub fn new_bounds(min: Point3d, max: Point3d, init_value: T) -> Grid3d<T> {
+        let lengths = (max - min + Point3d::splat(1)).component_max(&Point3d::zero());
         Grid3d {
-            grid: vec![vec![vec![init_value; size]; size]; size],
-            size,
+            values: vec![init_value.clone(); (lengths.x() * lengths.y() * lengths.z()) as usize],
+            origin: min,
+            lengths,
+            min,
+            max,
+            init_value,
         }
     }
 
-    /// Set the value at this location.
-    pub fn set_at(&mut self, point: &Point3d, value: T) {
-        self.grid[point.x][point.y][point.z] = value;
+    /// Create an empty grid, it grows as values are set.
+    pub fn new_empty(init_value: T) -> Grid3d<T> {
+        Grid3d {
+            values: vec![],
+            origin: Point3d::default(),
+            lengths: Point3d::default(),
+            min: Point3d::default(),
+            max: Point3d::splat(-1),
+            init_value,
+        }
     }
 
-    /// Get the value at this location.
-    pub fn value_at(&self, point: &Point3d) -> T {
-        self.grid[point.x][point.y][point.z].clone()
+    /// Is the grid empty?
+    pub fn is_empty(&self) -> bool {
+        self.max.zip(&self.min, |max, min| (max < min) as i32) != Point3d::zero()
+    }
+
+    /// The smallest point in the grid, inclusive.
+    pub fn min(&self) -> Point3d {
+        self.min
+    }
+
+    /// The largest point in the grid, inclusive.
+    pub fn max(&self) -> Point3d {
+        self.max
+    }
+
+    /// Is this point inside the grid?
+    pub fn contains(&self, point: &Point3d) -> bool {
+        Self::within(point, self.min, self.max)
+    }
+
+    /// Is this point between min and max, inclusive?
+    fn within(point: &Point3d, min: Point3d, max: Point3d) -> bool {
+        (min.x()..=max.x()).contains(&point.x())
+            && (min.y()..=max.y()).contains(&point.y())
+            && (min.z()..=max.z()).contains(&point.z())
+    }
+
+    /// Is there a stored value for this point?
+    fn is_stored(&self, point: &Point3d) -> bool {
+        Self::within(
+            point,
+            self.origin,
+            self.origin + self.lengths - Point3d::splat(1),
+        )
     }
 
-    /// Get the size of the cube, this is single x, y or z length.
-    pub fn size(&self) -> usize {
-        self.size
+    /// Get the index into the values list, the point must be stored.
+    fn index(&self, point: &Point3d) -> usize {
+        let x = (point.x() - self.origin.x()) as usize;
+        let y = (point.y() - self.origin.y()) as usize;
+        let z = (point.z() - self.origin.z()) as usize;
+        (x * self.lengths.y() as usize + y) * self.lengths.z() as usize + z
     }
 
-    /// Resize the grid down to this size
-    pub fn resize(&mut self, size: usize) {
-        assert!(size < self.size);
-        for _ in size..self.size {
-            self.grid.pop();
+    /// Set the value at this location. The grid grows if the point is outside.
+    pub fn set_at(&mut self, point: &Point3d, value: T) {
+        if !self.is_stored(point) {
+            self.grow(point);
         }
-        for x in 0..size {
-            for _ in size..self.size {
-                self.grid[x].pop();
-            }
+        if self.is_empty() {
+            (self.min, self.max) = (*point, *point);
+        } else {
+            self.min = self.min.component_min(point);
+            self.max = self.max.component_max(point);
         }
-        for x in 0..size {
-            for y in 0..size {
-                for _ in size..self.size {
-                    self.grid[x][y].pop();
-                }
+        let index = self.index(point);
+        self.values[index] = value;
+    }
+
+    /// Store more values so the point fits. Each axis that is too small at
+    /// least doubles, so growing one point at a time is not slow.
+    fn grow(&mut self, point: &Point3d) {
+        if self.values.is_empty() {
+            self.store(*point, *point);
+            return;
+        }
+        let top = self.origin + self.lengths - Point3d::splat(1);
+        let mut low = self.origin;
+        let mut high = top;
+        for axis in 0..3 {
+            if point[axis] < low[axis] {
+                low[axis] = point[axis] - self.lengths[axis];
             }
+            if point[axis] > high[axis] {
+                high[axis] = point[axis] + self.lengths[axis];
+            }
+        }
+        self.store(low, high);
+    }
+
+    /// Store values from low to high, inclusive. Stored values inside both the
+    /// old and new store are kept.
+    fn store(&mut self, low: Point3d, high: Point3d) {
+        let lengths = (high - low + Point3d::splat(1)).component_max(&Point3d::zero());
+        let count = (lengths.x() * lengths.y() * lengths.z()) as usize;
+        let old = Grid3d {
+            values: std::mem::replace(&mut self.values, vec![self.init_value.clone(); count]),
+            origin: std::mem::replace(&mut self.origin, low),
+            lengths: std::mem::replace(&mut self.lengths, lengths),
+            min: self.min,
+            max: self.max,
+            init_value: self.init_value.clone(),
+        };
+        let top = old.origin + old.lengths - Point3d::splat(1);
+        let (from, to) = (old.origin.component_max(&low), top.component_min(&high));
+        for point in Self::points_between(from, to) {
+            let index = self.index(&point);
+            self.values[index] = old.values[old.index(&point)].clone();
         }
+    }
+
+    /// Get the value at this location. Outside of the grid is the init value.
+    pub fn value_at(&self, point: &Point3d) -> T {
+        if !self.contains(point) {
+            return self.init_value.clone();
+        }
+        self.values[self.index(point)].clone()
+    }
+
+    /// Resize the grid to go from min to max, inclusive. It can grow or
+    /// shrink. Values inside both the old and new grid are kept.
+    #[allow(dead_code)]
+    pub fn resize(&mut self, min: Point3d, max: Point3d) {
+        // Stored values outside the grid are always the init value
+        self.store(min, max);
+        (self.min, self.max) = (min, max);
+    }
 
-        self.size = size;
+    /// Iterate through every point in the grid.
+    pub fn points(&self) -> impl Iterator<Item = Point3d> {
+        Self::points_between(self.min, self.max)
+    }
+
+    /// Iterate through every point from min to max, inclusive.
+    fn points_between(min: Point3d, max: Point3d) -> impl Iterator<Item = Point3d> {
+        (min.x()..=max.x()).flat_map(move |x| {
+            (min.y()..=max.y())
+                .flat_map(move |y| (min.z()..=max.z()).map(move |z| Point3d::new(x, y, z)))
+        })
     }
 
     /// Return a list of all nearby values. This only includes straight moves,
-    /// not diagonal.
+    /// not diagonal. Only values inside the grid are included.
+    #[allow(dead_code)]
     pub fn nearby_values(&self, point: &Point3d) -> Vec<T> {
-        let mut values = vec![];
-
-        let size_signed = self.size as isize;
-        let x = point.x as isize;
-        let y = point.y as isize;
-        let z = point.z as isize;
-        let x_range = [-1, 1, 0, 0, 0, 0];
-        let y_range = [0, 0, -1, 1, 0, 0];
-        let z_range = [0, 0, 0, 0, -1, 1];
-        for ((dx, dy), dz) in x_range.iter().zip(y_range.iter()).zip(z_range.iter()) {
-            let x = x + dx;
-            let y = y + dy;
-            let z = z + dz;
-            if x < 0 || x >= size_signed {
-                continue;
-            }
-            if y < 0 || y >= size_signed {
-                continue;
-            }
-            if z < 0 || z >= size_signed {
-                continue;
+        point
+            .neighbors(false)
+            .iter()
+            .filter(|p| self.contains(p))
+            .map(|p| self.value_at(p))
+            .collect()
+    }
+
+    /// Find every point reachable from a start point with straight moves,
+    /// only moving onto values where the predicate is true. The search stays
+    /// within min and max, inclusive. The reached points are true in the
+    /// returned grid.
+    pub fn flood_fill<F>(
+        &self,
+        from: &Point3d,
+        min: Point3d,
+        max: Point3d,
+        mut predicate: F,
+    ) -> Grid3d<bool>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut reached = Grid3d::new_bounds(min, max, false);
+        if !reached.contains(from) || !predicate(&self.value_at(from)) {
+            return reached;
+        }
+
+        reached.set_at(from, true);
+        let mut jobs = VecDeque::from([*from]);
+        while let Some(point) = jobs.pop_front() {
+            for near in point.neighbors(false) {
+                if !reached.contains(&near) || reached.value_at(&near) {
+                    continue;
+                }
+                if predicate(&self.value_at(&near)) {
+                    reached.set_at(&near, true);
+                    jobs.push_back(near);
+                }
             }
-            values.push(self.value_at(&Point3d {
-                x: x as usize,
-                y: y as usize,
-                z: z as usize,
-            }));
         }
 
-        values
+        reached
+    }
+
+    /// Find every point on the outside of the shapes in the grid. The search
+    /// starts in a one unit shell around the grid and moves onto values where
+    /// open is true. Points inside closed off pockets are not reached. The
+    /// reached points, shell included, are true in the returned grid.
+    pub fn exterior<F>(&self, open: F) -> Grid3d<bool>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let one = Point3d::splat(1);
+        let min = self.min - one;
+        let max = self.max() + one;
+        self.flood_fill(&min, min, max, open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_to_fit() {
+        let mut grid = Grid3d::new_empty('.');
+        assert!(grid.is_empty());
+        grid.set_at(&Point3d::new(1, 2, 3), 'a');
+        assert!(!grid.is_empty());
+        assert_eq!(
+            (grid.min(), grid.max()),
+            (Point3d::new(1, 2, 3), Point3d::new(1, 2, 3))
+        );
+        for i in 0..10 {
+            grid.set_at(&Point3d::new(-i, i, 3), 'b');
+        }
+        assert_eq!(grid.min(), Point3d::new(-9, 0, 3));
+        assert_eq!(grid.max(), Point3d::new(1, 9, 3));
+        assert_eq!(grid.value_at(&Point3d::new(1, 2, 3)), 'a');
+        assert_eq!(grid.value_at(&Point3d::new(-4, 4, 3)), 'b');
+        assert_eq!(grid.value_at(&Point3d::new(0, 9, 3)), '.');
+        assert_eq!(grid.value_at(&Point3d::new(50, 50, 50)), '.');
+        assert_eq!(grid.points().count(), 11 * 10);
+    }
+
+    #[test]
+    fn resize_keeps_values() {
+        let mut grid = Grid3d::new(3, 0);
+        grid.set_at(&Point3d::new(0, 0, 0), 1);
+        grid.set_at(&Point3d::new(2, 2, 2), 2);
+        assert_eq!(grid.nearby_values(&Point3d::new(0, 0, 0)), [0; 3]);
+        assert_eq!(grid.nearby_values(&Point3d::new(1, 1, 1)).len(), 6);
+
+        grid.resize(Point3d::new(-1, -1, -1), Point3d::new(1, 1, 1));
+        assert_eq!(grid.value_at(&Point3d::new(0, 0, 0)), 1);
+        assert_eq!(grid.value_at(&Point3d::new(2, 2, 2)), 0);
+        assert_eq!(grid.value_at(&Point3d::new(-1, -1, -1)), 0);
+
+        // The dropped value does not come back when the grid grows again
+        grid.resize(Point3d::new(0, 0, 0), Point3d::new(2, 2, 2));
+        assert_eq!(grid.value_at(&Point3d::new(2, 2, 2)), 0);
+    }
+
+    #[test]
+    fn exterior_skips_pockets() {
+        // A 3x3x3 cube with its center missing
+        let mut grid = Grid3d::new_empty(false);
+        for point in Grid3d::new(3, false).points() {
+            grid.set_at(&point, point != Point3d::splat(1));
+        }
+        let outside = grid.exterior(|solid| !solid);
+        assert_eq!(outside.min(), Point3d::splat(-1));
+        assert_eq!(
+            outside.points().filter(|p| outside.value_at(p)).count(),
+            125 - 27
+        );
+        assert!(!outside.value_at(&Point3d::splat(1)));
+
+        let inside = grid.flood_fill(&Point3d::splat(1), grid.min(), grid.max(), |solid| !solid);
+        assert_eq!(inside.points().filter(|p| inside.value_at(p)).count(), 1);
     }
 }
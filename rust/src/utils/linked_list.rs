@@ -1,190 +1,341 @@
-use std::cell::RefCell;
 use std::fmt::Debug;
-use std::ops::Deref;
-use std::rc::{Rc, Weak};
 
-/// A linked list. Is doubly linked, giving prev and next. Keeps a current
-/// pointer and can navigate with move next and prev. Can get value of current.
-/// Can pop current out of the list. Can insert at current.
-#[derive(Debug, Default, Clone)]
+/// A circular linked list. Is doubly linked, giving prev and next. Nodes live
+/// in a vector and link to each other by index, so there is no borrow
+/// checking at run time. A node keeps its id when it is removed and inserted
+/// again, so the original order is always nodes 0 to n - 1.
+/// Keeps a current node and can navigate with move next and prev.
+/// A list always has at least one node.
+#[derive(Debug, Clone)]
 pub struct LinkedList<T> {
+    /// All nodes, in the order they were added.
+    nodes: Vec<Node<T>>,
+
     /// First node added to the list.
-    head: Weak<RefCell<Node<T>>>,
+    head: NodeId,
 
     /// Current node with all the moves done.
-    current: Weak<RefCell<Node<T>>>,
+    current: NodeId,
 
-    /// A vector of all of the nodes. This keeps the Rc in scope so the memory
-    /// is not released.
-    pub values: Vec<Rc<RefCell<Node<T>>>>,
+    /// How many nodes are linked into the list.
+    len: usize,
 }
 
-/// Simple node struct. Stores a value and pointers to prev and next.
-#[derive(Debug, Default, Clone)]
-pub struct Node<T> {
-    pub value: T,
-    next: Weak<RefCell<Node<T>>>,
-    prev: Weak<RefCell<Node<T>>>,
-}
+/// Unique id for each node in the list
+pub type NodeId = usize;
 
-impl<T> Node<T>
-where
-    T: Default + Clone,
-{
-    /// Create a new node with a given value. Has no prev and next connections.
-    fn new(value: &T) -> Rc<RefCell<Node<T>>> {
-        Rc::new(RefCell::new(Node {
-            value: value.clone(),
-            ..Default::default()
-        }))
-    }
+/// Internal only.
+/// Simple node struct. Stores a value and links to prev and next.
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: T,
+    next: NodeId,
+    prev: NodeId,
+    linked: bool,
 }
 
 impl<T> LinkedList<T>
 where
-    T: Default + Clone + Debug,
+    T: Clone + Debug,
 {
-    /// Create a new linked list based on a list of values. This also stores
-    /// each node in a list so the data is kept alive.
-    pub fn new(values: &Vec<T>) -> LinkedList<T> {
-        // Default init, empty current and head pointers
-        let mut linked_list = LinkedList {
-            ..Default::default()
-        };
-
-        // Init nodes into vector so they are long lived
-        for value in values {
-            linked_list.values.push(Node::new(value));
-        }
-
-        // Create links
-        for i in 0..values.len() {
-            let mut current = linked_list.values[i].deref().borrow_mut();
-            let next = &linked_list.values[(i + 1) % values.len()];
-            let prev = &linked_list.values[i.checked_sub(1).unwrap_or(values.len() - 1)];
+    /// Create a new linked list based on a list of values. The last value
+    /// links back around to the first. There must be at least one value.
+    pub fn new(values: &[T]) -> LinkedList<T> {
+        assert!(!values.is_empty(), "A linked list needs a value");
+        let len = values.len();
+        let nodes = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Node {
+                value: value.clone(),
+                next: (i + 1) % len,
+                prev: (i + len - 1) % len,
+                linked: true,
+            })
+            .collect();
 
-            current.next = Rc::downgrade(next);
-            current.prev = Rc::downgrade(prev);
+        LinkedList {
+            nodes,
+            head: 0,
+            current: 0,
+            len,
         }
+    }
 
-        // Set current as head
-        linked_list.current = Rc::downgrade(&linked_list.values[0]);
-        linked_list.head = linked_list.current.clone();
+    /// Get how many nodes are in the list. Removed nodes do not count.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-        linked_list
+    /// Is the list empty? Never true, the last node can not be removed.
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
-    /// Set the current value to a specific node.
-    pub fn set_current(&mut self, node: &Weak<RefCell<Node<T>>>) {
-        self.current = node.clone();
+    /// Get how many nodes were ever made, including removed nodes.
+    /// Node ids go from 0 to this count - 1.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
     }
 
     /// Get the current node.
     #[allow(dead_code)]
-    pub fn current_node(&self) -> Weak<RefCell<Node<T>>> {
-        self.current.clone()
+    pub fn current(&self) -> NodeId {
+        self.current
     }
 
-    /// Get the length of the original list. This does not change with pop and insert.
-    pub fn len(&self) -> usize {
-        self.values.len()
+    /// Set the current node.
+    pub fn set_current(&mut self, node: NodeId) {
+        assert!(self.nodes[node].linked, "Node {node} is not in the list");
+        self.current = node;
+    }
+
+    /// Set the head node as the current node.
+    #[allow(dead_code)]
+    pub fn move_to_head(&mut self) {
+        self.current = self.head;
+    }
+
+    /// Get the value stored in a node.
+    pub fn value(&self, node: NodeId) -> &T {
+        &self.nodes[node].value
+    }
+
+    /// Change the value stored in a node.
+    pub fn value_mut(&mut self, node: NodeId) -> &mut T {
+        &mut self.nodes[node].value
     }
 
     /// Get the value stored in the current node.
     pub fn current_value(&self) -> T {
-        self.current
-            .upgrade()
-            .unwrap()
-            .deref()
-            .borrow()
-            .value
-            .clone()
+        self.nodes[self.current].value.clone()
     }
 
     /// Set the value of the current node.
-    pub fn set_current_value(&self, value: &T) {
-        self.current.upgrade().unwrap().deref().borrow_mut().value = value.clone();
+    #[allow(dead_code)]
+    pub fn set_current_value(&mut self, value: T) {
+        self.nodes[self.current].value = value;
     }
 
-    /// Se the current pointer as the head pointer
-    pub fn move_to_head(&mut self) {
-        self.current = self.head.clone();
+    /// Get the node after this one.
+    pub fn next(&self, node: NodeId) -> NodeId {
+        self.nodes[node].next
+    }
+
+    /// Get the node before this one.
+    pub fn prev(&self, node: NodeId) -> NodeId {
+        self.nodes[node].prev
+    }
+
+    /// Get the node some steps away. Negative steps go backwards. The steps
+    /// are done modulo the list length, going whichever way is shorter.
+    pub fn nth_from(&self, node: NodeId, steps: i64) -> NodeId {
+        let len = self.len as i64;
+        let forward = steps.rem_euclid(len);
+        let mut node = node;
+        if forward <= len / 2 {
+            for _ in 0..forward {
+                node = self.next(node);
+            }
+        } else {
+            for _ in 0..len - forward {
+                node = self.prev(node);
+            }
+        }
+        node
     }
 
     /// Move the current to the next node.
+    #[allow(dead_code)]
     pub fn move_next(&mut self) {
-        self.current = self
-            .current
-            .upgrade()
-            .unwrap()
-            .deref()
-            .borrow()
-            .next
-            .clone();
+        self.current = self.next(self.current);
     }
 
     /// Move the current to the prev node.
+    #[allow(dead_code)]
     pub fn move_prev(&mut self) {
-        self.current = self
-            .current
-            .upgrade()
-            .unwrap()
-            .deref()
-            .borrow()
-            .prev
-            .clone();
-    }
-
-    /// Remove the current node form the list and return it. Set current to the next node.
-    pub fn pop(&mut self) -> Weak<RefCell<Node<T>>> {
-        // Remove the current node
-        let popped = self.current.clone();
-
-        // Connect the prev and next together
-        let prev = popped.upgrade().unwrap().deref().borrow().prev.clone();
-        let next = popped.upgrade().unwrap().deref().borrow().next.clone();
-        prev.upgrade().unwrap().deref().borrow_mut().next = next.clone();
-        next.upgrade().unwrap().deref().borrow_mut().prev = prev;
-
-        // Update current
-        self.current = next;
-
-        popped
-    }
-
-    /// Insert this node into the list. The current will point to this new node as the next node.
-    /// Then update the current pointer to point to this new node.
-    pub fn insert(&mut self, node: &Weak<RefCell<Node<T>>>) {
-        // Connect prev and next to this new node
-        let prev = self.current.clone();
-        let next = self
-            .current
-            .upgrade()
-            .unwrap()
-            .deref()
-            .borrow()
-            .next
-            .clone();
-        next.upgrade().unwrap().deref().borrow_mut().prev = node.clone();
-        prev.upgrade().unwrap().deref().borrow_mut().next = node.clone();
-
-        // Connect this new node to prev and next
-        node.upgrade().unwrap().deref().borrow_mut().next = next;
-        node.upgrade().unwrap().deref().borrow_mut().prev = prev;
-
-        // Update current
-        self.current = node.clone();
-    }
-
-    /// Print all the values in the linked list
+        self.current = self.prev(self.current);
+    }
+
+    /// Move the current some steps. Negative steps go backwards.
+    pub fn move_by(&mut self, steps: i64) {
+        self.current = self.nth_from(self.current, steps);
+    }
+
+    /// Take a node out of the list. Its prev and next are joined together.
+    /// If it was the current or head node, the next node takes its place.
+    /// The node keeps its value and can be inserted again.
+    pub fn remove(&mut self, node: NodeId) {
+        assert!(self.nodes[node].linked, "Node {node} is not in the list");
+        assert!(self.len > 1, "Cannot remove the last node");
+        let (prev, next) = (self.prev(node), self.next(node));
+        self.nodes[prev].next = next;
+        self.nodes[next].prev = prev;
+        self.nodes[node].linked = false;
+        self.len -= 1;
+
+        if self.current == node {
+            self.current = next;
+        }
+        if self.head == node {
+            self.head = next;
+        }
+    }
+
+    /// Put a removed node back into the list, right after another node.
+    pub fn insert_after(&mut self, at: NodeId, node: NodeId) {
+        assert!(
+            !self.nodes[node].linked,
+            "Node {node} is already in the list"
+        );
+        let next = self.next(at);
+        self.nodes[node].prev = at;
+        self.nodes[node].next = next;
+        self.nodes[at].next = node;
+        self.nodes[next].prev = node;
+        self.nodes[node].linked = true;
+        self.len += 1;
+    }
+
+    /// Remove the current node from the list and return it. Set current to the next node.
+    #[allow(dead_code)]
+    pub fn pop(&mut self) -> NodeId {
+        let node = self.current;
+        self.remove(node);
+        node
+    }
+
+    /// Insert a removed node after the current node. Then the current is the new node.
+    #[allow(dead_code)]
+    pub fn insert(&mut self, node: NodeId) {
+        self.insert_after(self.current, node);
+        self.current = node;
+    }
+
+    /// Move a node some steps along the list. Negative steps go backwards.
+    /// While moving the node is not in the list, so one full loop is the
+    /// length minus one steps.
+    pub fn move_node(&mut self, node: NodeId, steps: i64) {
+        let loop_len = self.len as i64 - 1;
+        if loop_len == 0 || steps % loop_len == 0 {
+            return;
+        }
+
+        let prev = self.prev(node);
+        self.remove(node);
+        let at = self.nth_from(prev, steps);
+        self.insert_after(at, node);
+    }
+
+    /// Iterate through the values, starting at a node and going once around.
+    pub fn iter_from(&self, node: NodeId) -> Iter<'_, T> {
+        Iter {
+            list: self,
+            node,
+            remaining: self.len,
+        }
+    }
+
+    /// Iterate through the values, starting at the head.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_from(self.head)
+    }
+
+    /// Print all the values in the linked list, when debug logging is on.
     pub fn print(&self) {
-        // Walk the list and build a vector that we can print
-        let mut values = vec![];
-        let mut node = self.head.clone();
-        for _ in 0..self.values.len() {
-            values.push(node.upgrade().unwrap().deref().borrow().value.clone());
-            node = node.upgrade().unwrap().deref().borrow().next.clone();
+        // Collecting the values is slow, skip it unless it will be shown
+        if !log::log_enabled!(log::Level::Debug) {
+            return;
         }
+        let values: Vec<&T> = self.iter().map(|(_, v)| v).collect();
         log::debug!("{:?}", values);
     }
 }
+
+/// Iterate once around the list, giving each node id and value.
+pub struct Iter<'a, T> {
+    list: &'a LinkedList<T>,
+    node: NodeId,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where
+    T: Clone + Debug,
+{
+    type Item = (NodeId, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.node;
+        self.node = self.list.next(node);
+        Some((node, self.list.value(node)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(list: &LinkedList<i64>) -> Vec<i64> {
+        list.iter().map(|(_, v)| *v).collect()
+    }
+
+    #[test]
+    fn navigate() {
+        let mut list = LinkedList::new(&[1, 2, 3, 4, 5]);
+        assert_eq!(list.len(), 5);
+        list.move_prev();
+        assert_eq!(list.current_value(), 5);
+        list.move_next();
+        list.move_next();
+        assert_eq!(list.current(), 1);
+        list.move_by(-7);
+        assert_eq!(list.current_value(), 5);
+        assert_eq!(list.nth_from(0, 12), 2);
+        list.set_current_value(50);
+        list.move_to_head();
+        assert_eq!(values(&list), [1, 2, 3, 4, 50]);
+    }
+
+    #[test]
+    fn pop_and_insert() {
+        let mut list = LinkedList::new(&[1, 2, 3]);
+        let head = list.pop();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.current_value(), 2);
+        assert_eq!(values(&list), [2, 3]);
+        list.insert(head);
+        assert_eq!(list.current(), head);
+        assert_eq!(values(&list), [2, 1, 3]);
+        assert_eq!(
+            list.iter_from(2).map(|(n, _)| n).collect::<Vec<_>>(),
+            [2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn move_node_matches_mixing() {
+        // The mixing example from 2022 day 20
+        let mut list = LinkedList::new(&[1, 2, -3, 3, -2, 0, 4]);
+        for node in 0..list.node_count() {
+            let value = *list.value(node);
+            list.move_node(node, value);
+        }
+        let zero = list.iter().find(|(_, v)| **v == 0).unwrap().0;
+        let mixed: Vec<i64> = list.iter_from(zero).map(|(_, v)| *v).collect();
+        assert_eq!(mixed, [0, 3, -2, 1, 2, -3, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot remove the last node")]
+    fn keep_last_node() {
+        let mut list = LinkedList::new(&[1]);
+        list.pop();
+    }
+}
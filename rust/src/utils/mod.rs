@@ -1,13 +1,24 @@
+pub mod automaton;
 pub mod board;
 pub mod board3d;
 pub mod cpu;
+pub mod cpu_optimizer;
+pub mod cpu_trace;
 pub mod crt;
+pub mod cycle;
 pub mod file_system;
+pub mod geometry;
 pub mod grid3d;
 pub mod linked_list;
 pub mod molecule;
+pub mod molecule_grammar;
+pub mod point;
 pub mod primes;
+pub mod ranges;
+pub mod render;
+pub mod ring;
 pub mod rock_paper_scissors;
 pub mod screen;
 pub mod tetris;
+pub mod turtle;
 pub mod utils;
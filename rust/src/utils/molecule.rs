@@ -1,64 +1,49 @@
+use std::collections::HashMap;
+use std::fmt;
+
 /// A molecule is made up of multiple atoms. This molecule struct and the Atom
 /// struct support to and from string conversions. The molecule struct also
 /// supports building the molecule and makeing "random like" replacements to
 /// atoms inside the molecule.
+/// Atoms are not limited to real elements. Any capital letter followed by
+/// lower case letters is an atom, like "Rn" or "Xyz", and so is a lower case
+/// run on its own, like "e". Atom names are learned by an Interner, which is
+/// needed to parse and show molecules.
 #[derive(Debug, Clone, Hash, std::cmp::Eq, PartialEq)]
 pub struct Molecule {
     atoms: Vec<Atom>,
 }
 
-// Used for doing emun -> string
-use strum_macros::Display;
-
 impl Molecule {
     /// Create an empty Molecule.
     pub fn new() -> Molecule {
         Molecule { atoms: vec![] }
     }
 
-    /// Build a molecule from a string of atoms.
-    pub fn new_from_string(input: &str) -> Molecule {
+    /// Build a molecule from a string of atoms. New atom names are learned by
+    /// the interner.
+    pub fn parse(interner: &mut Interner, input: &str) -> Molecule {
         let mut molecule = Molecule::new();
 
-        // Build a slice of all capital letters, this is used to build atoms,
-        // as each new atom starts with a capital letter.
-        let mut capitals = ['?'; 26];
-        for (i, char) in capitals.iter_mut().enumerate() {
-            *char = (i as u8 + 'A' as u8) as char;
+        for token in Interner::tokenize(input) {
+            molecule.push_atom(interner.intern(token));
         }
-
-        // Parse atoms out of the string and add them to the molecule
-        let mut atom = String::new();
-        for char in input.chars() {
-            if capitals.contains(&char) {
-                // This is the start of a new atom
-
-                // Store the finished atom
-                if atom.chars().count() > 0 {
-                    molecule.push_atom(Atom::new(&atom));
-                }
-
-                // Start a new atom
-                atom = char.to_string();
-            } else {
-                // Keep building the string for the current atom
-                atom.push(char);
-            }
-        }
-        // Store the last atom of the molecule
-        molecule.push_atom(Atom::new(&atom));
-
         molecule
     }
 
+    /// Build a molecule from a list of atoms.
+    pub fn from_atoms(atoms: Vec<Atom>) -> Molecule {
+        Molecule { atoms }
+    }
+
     /// The number of atoms in the molecule.
     pub fn len(&self) -> usize {
         self.atoms.len()
     }
 
     /// The list of atoms currently in the molecule.
-    pub fn atoms(&self) -> Vec<Atom> {
-        self.atoms.clone()
+    pub fn atoms(&self) -> &[Atom] {
+        &self.atoms
     }
 
     /// Add a new atom to the molecule.
@@ -75,7 +60,7 @@ impl Molecule {
         }
 
         // Loop throught each atom at the start and ensure matches
-        for (i, atom) in target.atoms().iter().enumerate() {
+        for (i, atom) in target.atoms.iter().enumerate() {
             if *atom != self.atoms[i] {
                 return false;
             }
@@ -85,11 +70,36 @@ impl Molecule {
         true
     }
 
-    /// Convert this molecule to a string.
-    pub fn to_string(&self) -> String {
+    /// Find every index where the target molecule is inside this molecule.
+    pub fn find(&self, target: &Molecule) -> Vec<usize> {
+        if target.len() == 0 || target.len() > self.len() {
+            return vec![];
+        }
         self.atoms
-            .iter()
-            .fold(String::new(), |a, x| a + &x.to_string())
+            .windows(target.len())
+            .enumerate()
+            .filter(|(_, window)| *window == target.atoms.as_slice())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Replace count atoms starting at an index with another molecule.
+    pub fn replace_at(&self, index: usize, count: usize, with: &Molecule) -> Molecule {
+        let mut atoms = self.atoms.clone();
+        atoms.splice(index..index + count, with.atoms.iter().copied());
+        Molecule { atoms }
+    }
+
+    /// Show this molecule with the atom names from the interner, like
+    /// molecule.display(&interner).to_string().
+    pub fn display<'a>(&'a self, interner: &'a Interner) -> Named<'a, Molecule> {
+        Named::new(self, interner)
+    }
+
+    /// Get this molecule as a string, with the atom names from the interner.
+    /// Atoms only know their number, so the interner is needed for names.
+    pub fn to_string(&self, interner: &Interner) -> String {
+        self.display(interner).to_string()
     }
 
     /// For every from atom found in this module, change to the to pattern.
@@ -108,8 +118,8 @@ impl Molecule {
                 atoms.remove(i);
 
                 // Add in each of the replacement atoms
-                for (j, replace) in to.atoms().iter().enumerate() {
-                    atoms.insert(i + j, replace.clone());
+                for (j, replace) in to.atoms.iter().enumerate() {
+                    atoms.insert(i + j, *replace);
                 }
 
                 molecules.push(Molecule { atoms });
@@ -120,251 +130,128 @@ impl Molecule {
     }
 }
 
-/// An enum of all the known Atoms. Supports to and from string conversion.
-#[derive(Debug, Clone, PartialEq, Hash, std::cmp::Eq, Display)]
-pub enum Atom {
-    H,
-    He,
-    Li,
-    Be,
-    B,
-    C,
-    N,
-    O,
-    F,
-    Ne,
-    Na,
-    Mg,
-    Al,
-    Si,
-    P,
-    S,
-    Cl,
-    Ar,
-    K,
-    Ca,
-    Sc,
-    Ti,
-    V,
-    Cr,
-    Mn,
-    Fe,
-    Co,
-    Ni,
-    Cu,
-    Zn,
-    Ga,
-    Ge,
-    As,
-    Se,
-    Br,
-    Kr,
-    Rb,
-    Sr,
-    Y,
-    Zr,
-    Nb,
-    Mo,
-    Tc,
-    Ru,
-    Rh,
-    Pd,
-    Ag,
-    Cd,
-    In,
-    Sn,
-    Sb,
-    Te,
-    I,
-    Xe,
-    Cs,
-    Ba,
-    La,
-    Ce,
-    Pr,
-    Nd,
-    Pm,
-    Sm,
-    Eu,
-    Gd,
-    Tb,
-    Dy,
-    Ho,
-    Er,
-    Tm,
-    Yb,
-    Lu,
-    Hf,
-    Ta,
-    W,
-    Re,
-    Os,
-    Ir,
-    Pt,
-    Au,
-    Hg,
-    Tl,
-    Pb,
-    Bi,
-    Po,
-    At,
-    Rn,
-    Fr,
-    Ra,
-    Ac,
-    Th,
-    Pa,
-    U,
-    Np,
-    Pu,
-    Am,
-    Cm,
-    Bk,
-    Cf,
-    Es,
-    Fm,
-    Md,
-    No,
-    Lr,
-    Rf,
-    Db,
-    Sg,
-    Bh,
-    Hs,
-    Mt,
-    Ds,
-    Rg,
-    Cn,
-    Uut,
-    Fl,
-    Uup,
-    Lv,
-    Uus,
-    Uuo,
+/// An atom, like "H" or "Ca". Atoms are small handles to a name learned by an
+/// interner, so they are cheap to copy, compare and hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Atom(u32);
+
+/// Learns atom names, giving each new name the next number.
+#[derive(Debug, Default)]
+pub struct Interner {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
 }
 
-impl Atom {
-    pub fn new(input: &str) -> Atom {
-        match input {
-            "H" => Atom::H,
-            "He" => Atom::He,
-            "Li" => Atom::Li,
-            "Be" => Atom::Be,
-            "B" => Atom::B,
-            "C" => Atom::C,
-            "N" => Atom::N,
-            "O" => Atom::O,
-            "F" => Atom::F,
-            "Ne" => Atom::Ne,
-            "Na" => Atom::Na,
-            "Mg" => Atom::Mg,
-            "Al" => Atom::Al,
-            "Si" => Atom::Si,
-            "P" => Atom::P,
-            "S" => Atom::S,
-            "Cl" => Atom::Cl,
-            "Ar" => Atom::Ar,
-            "K" => Atom::K,
-            "Ca" => Atom::Ca,
-            "Sc" => Atom::Sc,
-            "Ti" => Atom::Ti,
-            "V" => Atom::V,
-            "Cr" => Atom::Cr,
-            "Mn" => Atom::Mn,
-            "Fe" => Atom::Fe,
-            "Co" => Atom::Co,
-            "Ni" => Atom::Ni,
-            "Cu" => Atom::Cu,
-            "Zn" => Atom::Zn,
-            "Ga" => Atom::Ga,
-            "Ge" => Atom::Ge,
-            "As" => Atom::As,
-            "Se" => Atom::Se,
-            "Br" => Atom::Br,
-            "Kr" => Atom::Kr,
-            "Rb" => Atom::Rb,
-            "Sr" => Atom::Sr,
-            "Y" => Atom::Y,
-            "Zr" => Atom::Zr,
-            "Nb" => Atom::Nb,
-            "Mo" => Atom::Mo,
-            "Tc" => Atom::Tc,
-            "Ru" => Atom::Ru,
-            "Rh" => Atom::Rh,
-            "Pd" => Atom::Pd,
-            "Ag" => Atom::Ag,
-            "Cd" => Atom::Cd,
-            "In" => Atom::In,
-            "Sn" => Atom::Sn,
-            "Sb" => Atom::Sb,
-            "Te" => Atom::Te,
-            "I" => Atom::I,
-            "Xe" => Atom::Xe,
-            "Cs" => Atom::Cs,
-            "Ba" => Atom::Ba,
-            "La" => Atom::La,
-            "Ce" => Atom::Ce,
-            "Pr" => Atom::Pr,
-            "Nd" => Atom::Nd,
-            "Pm" => Atom::Pm,
-            "Sm" => Atom::Sm,
-            "Eu" => Atom::Eu,
-            "Gd" => Atom::Gd,
-            "Tb" => Atom::Tb,
-            "Dy" => Atom::Dy,
-            "Ho" => Atom::Ho,
-            "Er" => Atom::Er,
-            "Tm" => Atom::Tm,
-            "Yb" => Atom::Yb,
-            "Lu" => Atom::Lu,
-            "Hf" => Atom::Hf,
-            "Ta" => Atom::Ta,
-            "W" => Atom::W,
-            "Re" => Atom::Re,
-            "Os" => Atom::Os,
-            "Ir" => Atom::Ir,
-            "Pt" => Atom::Pt,
-            "Au" => Atom::Au,
-            "Hg" => Atom::Hg,
-            "Tl" => Atom::Tl,
-            "Pb" => Atom::Pb,
-            "Bi" => Atom::Bi,
-            "Po" => Atom::Po,
-            "At" => Atom::At,
-            "Rn" => Atom::Rn,
-            "Fr" => Atom::Fr,
-            "Ra" => Atom::Ra,
-            "Ac" => Atom::Ac,
-            "Th" => Atom::Th,
-            "Pa" => Atom::Pa,
-            "U" => Atom::U,
-            "Np" => Atom::Np,
-            "Pu" => Atom::Pu,
-            "Am" => Atom::Am,
-            "Cm" => Atom::Cm,
-            "Bk" => Atom::Bk,
-            "Cf" => Atom::Cf,
-            "Es" => Atom::Es,
-            "Fm" => Atom::Fm,
-            "Md" => Atom::Md,
-            "No" => Atom::No,
-            "Lr" => Atom::Lr,
-            "Rf" => Atom::Rf,
-            "Db" => Atom::Db,
-            "Sg" => Atom::Sg,
-            "Bh" => Atom::Bh,
-            "Hs" => Atom::Hs,
-            "Mt" => Atom::Mt,
-            "Ds" => Atom::Ds,
-            "Rg" => Atom::Rg,
-            "Cn" => Atom::Cn,
-            "Uut" => Atom::Uut,
-            "Fl" => Atom::Fl,
-            "Uup" => Atom::Uup,
-            "Lv" => Atom::Lv,
-            "Uus" => Atom::Uus,
-            "Uuo" => Atom::Uuo,
-            _ => panic!("Unexpected Atom {input}"),
+impl Interner {
+    /// Create an interner that knows no names yet.
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// Get the atom for a name, learning it if new.
+    pub fn intern(&mut self, name: &str) -> Atom {
+        if let Some(id) = self.ids.get(name) {
+            return Atom(*id);
         }
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        Atom(id)
+    }
+
+    /// Get the name of an atom. Panics if the atom is from another interner.
+    pub fn name(&self, atom: Atom) -> &str {
+        &self.names[atom.0 as usize]
+    }
+
+    /// Split a string into atom names. Each capital letter starts a new atom,
+    /// which takes any lower case letters after it, like "HeLi" is "He" and
+    /// "Li". Lower case letters at the start are an atom on their own.
+    pub fn tokenize(input: &str) -> Vec<&str> {
+        let mut tokens = vec![];
+        let mut start = 0;
+        for (i, char) in input.char_indices() {
+            if char.is_ascii_uppercase() && i > start {
+                tokens.push(&input[start..i]);
+                start = i;
+            }
+        }
+        if start < input.len() {
+            tokens.push(&input[start..]);
+        }
+        tokens
+    }
+}
+
+/// Something shown with the atom names from an interner.
+pub struct Named<'a, T> {
+    pub value: &'a T,
+    pub interner: &'a Interner,
+}
+
+impl<'a, T> Named<'a, T> {
+    /// Show a value with names from this interner.
+    pub fn new(value: &'a T, interner: &'a Interner) -> Named<'a, T> {
+        Named { value, interner }
+    }
+}
+
+impl fmt::Display for Named<'_, Atom> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.interner.name(*self.value))
+    }
+}
+
+impl fmt::Display for Named<'_, Molecule> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for atom in self.value.atoms.iter() {
+            write!(f, "{}", self.interner.name(*atom))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize() {
+        assert_eq!(
+            Interner::tokenize("eHeLiXyzO"),
+            ["e", "He", "Li", "Xyz", "O"]
+        );
+        assert_eq!(Interner::tokenize(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn parse_and_show() {
+        let mut interner = Interner::new();
+        let molecule = Molecule::parse(&mut interner, "CaRnCaF");
+        assert_eq!(molecule.len(), 4);
+        assert_eq!(molecule.atoms()[0], molecule.atoms()[2]);
+        assert_eq!(molecule.to_string(&interner), "CaRnCaF");
+        assert_eq!(interner.name(molecule.atoms()[1]), "Rn");
+        assert_eq!(Named::new(&molecule.atoms()[3], &interner).to_string(), "F");
+    }
+
+    #[test]
+    fn find_and_replace() {
+        let mut interner = Interner::new();
+        let mut parse = |text| Molecule::parse(&mut interner, text);
+        let molecule = parse("HOHOH");
+        let (ho, h, oh, o) = (parse("HO"), parse("H"), parse("OH"), parse("O"));
+        assert!(molecule.starts_with(&ho));
+        assert!(!molecule.starts_with(&oh));
+        assert!(!ho.starts_with(&molecule));
+        assert_eq!(molecule.find(&ho), [0, 2]);
+        assert_eq!(molecule.find(&Molecule::new()), Vec::<usize>::new());
+
+        assert_eq!(molecule.replace_at(1, 2, &o), parse("HOOH"));
+        let replaced = molecule.replace(&h.atoms()[0], &oh);
+        assert_eq!(
+            replaced,
+            [parse("OHOHOH"), parse("HOOHOH"), parse("HOHOOH")]
+        );
     }
 }
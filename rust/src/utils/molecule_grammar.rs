@@ -0,0 +1,525 @@
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::utils::molecule::*;
+
+/// A set of replacements that build molecules, like "H => HO". Molecules are
+/// grown from a start symbol, like "e", and the replacements from the start
+/// symbol give the first molecule.
+/// Besides running replacements forwards, the grammar can search backwards
+/// from a target molecule to find how it was built. The greedy searches are
+/// fast but may not find the fewest steps, the chart search always does.
+/// The grammar learns atom names, so molecules used with it should be parsed
+/// by it too.
+pub struct Grammar {
+    /// Atom names in the replacements and molecules.
+    interner: Interner,
+
+    /// The start symbol.
+    start: Atom,
+
+    /// Molecules the start symbol can be replaced with.
+    starts: Vec<Molecule>,
+
+    /// Replacements of one atom with a molecule.
+    rules: Vec<Rule>,
+}
+
+/// Replace an atom with a molecule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub from: Atom,
+    pub to: Molecule,
+}
+
+/// How a molecule is built from the start symbol. Each molecule is one
+/// replacement from the one before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivation {
+    start: Atom,
+    molecules: Vec<Molecule>,
+}
+
+impl Derivation {
+    /// The molecules after each step, ending with the target.
+    #[allow(dead_code)]
+    pub fn molecules(&self) -> &[Molecule] {
+        &self.molecules
+    }
+
+    /// How many replacements are done, including the one from the start symbol.
+    pub fn steps(&self) -> usize {
+        self.molecules.len()
+    }
+
+    /// Show each step with the atom names from the interner.
+    pub fn display<'a>(&'a self, interner: &'a Interner) -> Named<'a, Derivation> {
+        Named::new(self, interner)
+    }
+}
+
+/// Show each step, like "e => HF => HCaF".
+impl fmt::Display for Named<'_, Derivation> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.interner.name(self.value.start))?;
+        for molecule in self.value.molecules.iter() {
+            write!(f, " => {}", molecule.display(self.interner))?;
+        }
+        Ok(())
+    }
+}
+
+/// A symbol in the chart search. The start symbol is 0, then each atom, then
+/// the made up symbols used to split long replacements into pairs.
+type Symbol = usize;
+
+/// The start symbol in the chart search.
+const START: Symbol = 0;
+
+/// A replacement as symbols. Replacements from the start symbol come first.
+struct Production {
+    from: Symbol,
+    to: Vec<Symbol>,
+}
+
+/// A replacement of a symbol with two symbols. Long replacements are split into
+/// a chain of pairs, where only the first pair counts as a step.
+struct Pair {
+    from: Symbol,
+    left: Symbol,
+    right: Symbol,
+
+    /// The production this pair starts, or None for the rest of a chain.
+    production: Option<usize>,
+}
+
+/// How a span of the target was built from a symbol.
+#[derive(Debug, Clone, Copy)]
+enum Back {
+    /// The span is just this atom.
+    Leaf,
+
+    /// A production that replaces the symbol with one other symbol.
+    Unit(Symbol),
+
+    /// A pair, with the left symbol covering the span up to the split.
+    Pair { split: usize, pair: usize },
+}
+
+/// The fewest steps to build each span of the target from each symbol.
+type Chart = Vec<Vec<HashMap<Symbol, (usize, Back)>>>;
+
+/// The chart search, with the grammar turned into symbols.
+struct ChartSearch {
+    productions: Vec<Production>,
+    pairs: Vec<Pair>,
+    atoms: Vec<Atom>,
+    symbols: HashMap<Atom, Symbol>,
+    chart: Chart,
+}
+
+impl Grammar {
+    /// Parse a grammar, one replacement per line like "H => HO". Replacements
+    /// from the start symbol, like "e => HF", give the first molecule. The
+    /// start symbol can only be on the left of a replacement, as it can only
+    /// be used once, at the start.
+    pub fn parse(text: &str, start: &str) -> Result<Grammar> {
+        let mut interner = Interner::new();
+        let mut grammar = Grammar {
+            start: interner.intern(start),
+            interner,
+            starts: vec![],
+            rules: vec![],
+        };
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let Some((from, to)) = line.split_once("=>") else {
+                bail!("Expected a replacement like 'H => HO', found '{line}'");
+            };
+            let from = grammar.interner.intern(from.trim());
+            let to = grammar.parse_molecule(to.trim());
+            if to.atoms().contains(&grammar.start) {
+                bail!("The start symbol {start} cannot be replaced into, found '{line}'");
+            }
+            if from == grammar.start {
+                grammar.starts.push(to);
+            } else {
+                grammar.rules.push(Rule { from, to });
+            }
+        }
+        Ok(grammar)
+    }
+
+    /// Parse a molecule, learning any new atom names.
+    pub fn parse_molecule(&mut self, text: &str) -> Molecule {
+        Molecule::parse(&mut self.interner, text)
+    }
+
+    /// Get the atom names, to show molecules.
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// Get the replacements of one atom with a molecule.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Get every distinct molecule made by doing a single replacement.
+    pub fn products(&self, molecule: &Molecule) -> HashSet<Molecule> {
+        self.rules
+            .iter()
+            .flat_map(|rule| molecule.replace(&rule.from, &rule.to))
+            .collect()
+    }
+
+    /// Shrink the target back to a start molecule by undoing replacements.
+    /// Always undo the replacement at the right most spot, and the longest one
+    /// if there are a few there. This is fast but may get stuck, or find more
+    /// steps than needed.
+    pub fn reduce_greedy(&self, target: &Molecule) -> Option<Derivation> {
+        let mut order: Vec<usize> = (0..self.rules.len()).collect();
+        order.sort_by_key(|r| std::cmp::Reverse(self.rules[*r].to.len()));
+        self.reduce(target, &order, true)
+    }
+
+    /// Shrink the target back to a start molecule by undoing replacements in
+    /// a random order. If it gets stuck, start over with a new order. The same
+    /// seed always gives the same result.
+    pub fn reduce_random(
+        &self,
+        target: &Molecule,
+        seed: u64,
+        attempts: usize,
+    ) -> Option<Derivation> {
+        let mut order: Vec<usize> = (0..self.rules.len()).collect();
+        let mut random = seed.max(1);
+        for attempt in 0..attempts {
+            // Fisher Yates shuffle with an xorshift random number
+            for i in (1..order.len()).rev() {
+                random ^= random << 13;
+                random ^= random >> 7;
+                random ^= random << 17;
+                order.swap(i, (random % (i as u64 + 1)) as usize);
+            }
+            if let Some(derivation) = self.reduce(target, &order, false) {
+                log::debug!("Reduced after {} attempts", attempt + 1);
+                return Some(derivation);
+            }
+        }
+        None
+    }
+
+    /// Undo replacements, trying rules in this order, until a start molecule
+    /// is left. Undo the first rule that fits, or with right most the rule that
+    /// fits furthest right. Replacements that do not grow are skipped so this
+    /// always ends.
+    fn reduce(&self, target: &Molecule, order: &[usize], right_most: bool) -> Option<Derivation> {
+        let mut molecules = vec![target.clone()];
+        loop {
+            let molecule = molecules.last().unwrap();
+            if self.starts.contains(molecule) {
+                molecules.reverse();
+                return Some(Derivation {
+                    start: self.start,
+                    molecules,
+                });
+            }
+
+            let mut fits = order
+                .iter()
+                .map(|r| &self.rules[*r])
+                .filter(|rule| rule.to.len() > 1)
+                .filter_map(|rule| Some((*molecule.find(&rule.to).last()?, rule)));
+            let (index, rule) = match right_most {
+                true => fits.min_by_key(|(index, _)| std::cmp::Reverse(*index))?,
+                false => fits.next()?,
+            };
+            let from = Molecule::from_atoms(vec![rule.from]);
+            molecules.push(molecule.replace_at(index, rule.to.len(), &from));
+        }
+    }
+
+    /// Find the fewest replacements that build the target from the start
+    /// symbol. This is a CYK style chart search, where every span of the target
+    /// gets the fewest steps to build it from each symbol. None if the target
+    /// cannot be built.
+    pub fn shortest_derivation(&self, target: &Molecule) -> Option<Derivation> {
+        let mut search = ChartSearch::new(self, target);
+        search.fill();
+        let (steps, _) = search.chart[0][target.len()].get(&START)?;
+        log::debug!("Fewest steps is {steps}");
+
+        let mut sentence = vec![START];
+        let mut molecules = vec![];
+        search.expand(START, 0, target.len(), &mut sentence, &mut molecules);
+        Some(Derivation {
+            start: self.start,
+            molecules,
+        })
+    }
+}
+
+impl ChartSearch {
+    /// Turn the grammar and target into symbols, with an empty chart.
+    fn new(grammar: &Grammar, target: &Molecule) -> ChartSearch {
+        let mut search = ChartSearch {
+            productions: vec![],
+            pairs: vec![],
+            atoms: vec![],
+            symbols: HashMap::new(),
+            chart: vec![],
+        };
+
+        let starts = grammar.starts.iter().map(|to| (START, to));
+        let rules = grammar
+            .rules
+            .iter()
+            .map(|r| (search.symbol(&r.from), &r.to));
+        let productions: Vec<(Symbol, &Molecule)> = starts.chain(rules).collect();
+        for (from, to) in productions {
+            let to = to.atoms().iter().map(|a| search.symbol(a)).collect();
+            search.productions.push(Production { from, to });
+        }
+        for atom in target.atoms().iter() {
+            search.symbol(atom);
+        }
+
+        // Split each long production into a chain of pairs, like A => BCD
+        // becomes A => B X and X => C D
+        let mut next = search.atoms.len() + 1;
+        for (p, production) in search.productions.iter().enumerate() {
+            let to = &production.to;
+            if to.len() < 2 {
+                continue;
+            }
+            let mut from = production.from;
+            for i in 0..to.len() - 1 {
+                let right = match i + 2 == to.len() {
+                    true => to[i + 1],
+                    false => next,
+                };
+                search.pairs.push(Pair {
+                    from,
+                    left: to[i],
+                    right,
+                    production: (i == 0).then_some(p),
+                });
+                from = next;
+                next += 1;
+            }
+        }
+
+        let n = target.len();
+        search.chart = (0..=n).map(|_| vec![HashMap::new(); n + 1]).collect();
+        for (i, atom) in target.atoms().iter().enumerate() {
+            search.chart[i][i + 1].insert(search.symbols[atom], (0, Back::Leaf));
+        }
+        search
+    }
+
+    /// Get the symbol for an atom, adding it if new.
+    fn symbol(&mut self, atom: &Atom) -> Symbol {
+        if let Some(symbol) = self.symbols.get(atom) {
+            return *symbol;
+        }
+        self.atoms.push(*atom);
+        self.symbols.insert(*atom, self.atoms.len());
+        self.atoms.len()
+    }
+
+    /// Fill the chart, from short spans to the whole target.
+    fn fill(&mut self) {
+        let n = self.chart.len() - 1;
+        let mut by_left: HashMap<Symbol, Vec<usize>> = HashMap::new();
+        for (i, pair) in self.pairs.iter().enumerate() {
+            by_left.entry(pair.left).or_default().push(i);
+        }
+
+        for length in 1..=n {
+            for i in 0..=n - length {
+                let j = i + length;
+                let mut cell = std::mem::take(&mut self.chart[i][j]);
+                for split in i + 1..j {
+                    for (left, (left_steps, _)) in self.chart[i][split].iter() {
+                        for index in by_left.get(left).into_iter().flatten() {
+                            let pair = &self.pairs[*index];
+                            let Some((right_steps, _)) = self.chart[split][j].get(&pair.right)
+                            else {
+                                continue;
+                            };
+                            let steps =
+                                left_steps + right_steps + pair.production.is_some() as usize;
+                            let back = Back::Pair {
+                                split,
+                                pair: *index,
+                            };
+                            improve(&mut cell, pair.from, steps, back);
+                        }
+                    }
+                }
+                self.close_units(&mut cell);
+                self.chart[i][j] = cell;
+            }
+        }
+    }
+
+    /// Add productions that replace a symbol with one other symbol, until
+    /// nothing improves.
+    fn close_units(&self, cell: &mut HashMap<Symbol, (usize, Back)>) {
+        loop {
+            let mut changed = false;
+            for production in self.productions.iter() {
+                if production.to.len() != 1 {
+                    continue;
+                }
+                let Some((steps, _)) = cell.get(&production.to[0]) else {
+                    continue;
+                };
+                let back = Back::Unit(production.to[0]);
+                changed |= improve(cell, production.from, steps + 1, back);
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    /// Replay how a span was built, leftmost symbol first. Everything left of
+    /// the span is already atoms, so the symbol is at the start of the span.
+    fn expand(
+        &self,
+        symbol: Symbol,
+        i: usize,
+        j: usize,
+        sentence: &mut Vec<Symbol>,
+        molecules: &mut Vec<Molecule>,
+    ) {
+        let (_, back) = self.chart[i][j][&symbol];
+        match back {
+            Back::Leaf => (),
+            Back::Unit(child) => {
+                sentence[i] = child;
+                molecules.push(self.molecule(sentence));
+                self.expand(child, i, j, sentence, molecules);
+            }
+            Back::Pair { pair, .. } => {
+                let p = self.pairs[pair].production.unwrap();
+                sentence.splice(i..i + 1, self.productions[p].to.iter().copied());
+                molecules.push(self.molecule(sentence));
+
+                // Walk the chain of pairs to find the span of each child
+                let mut children = vec![];
+                let (mut symbol, mut start) = (symbol, i);
+                while let Back::Pair { split, pair } = self.chart[start][j][&symbol].1 {
+                    let pair = &self.pairs[pair];
+                    children.push((pair.left, start, split));
+                    (symbol, start) = (pair.right, split);
+                    if !self.is_chain(symbol) {
+                        break;
+                    }
+                }
+                children.push((symbol, start, j));
+
+                for (child, start, end) in children {
+                    self.expand(child, start, end, sentence, molecules);
+                }
+            }
+        }
+    }
+
+    /// Is this a made up symbol from splitting a long production?
+    fn is_chain(&self, symbol: Symbol) -> bool {
+        symbol > self.atoms.len()
+    }
+
+    /// Turn a sentence of atom symbols into a molecule.
+    fn molecule(&self, sentence: &[Symbol]) -> Molecule {
+        Molecule::from_atoms(sentence.iter().map(|s| self.atoms[s - 1]).collect())
+    }
+}
+
+/// Keep the fewer steps to build a symbol. Return true if it improved.
+fn improve(
+    cell: &mut HashMap<Symbol, (usize, Back)>,
+    symbol: Symbol,
+    steps: usize,
+    back: Back,
+) -> bool {
+    match cell.get(&symbol) {
+        Some((best, _)) if *best <= steps => false,
+        _ => {
+            cell.insert(symbol, (steps, back));
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = "e => H\ne => O\nH => HO\nH => OH\nO => HH";
+
+    /// Check each molecule is one replacement from the one before it.
+    fn check(grammar: &Grammar, derivation: &Derivation, target: &Molecule) {
+        let molecules = derivation.molecules();
+        assert!(grammar.starts.contains(&molecules[0]));
+        for step in molecules.windows(2) {
+            assert!(grammar.products(&step[0]).contains(&step[1]));
+        }
+        assert_eq!(molecules.last(), Some(target));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Grammar::parse("H => HO\nO HH", "e").is_err());
+        assert!(Grammar::parse("H => eO", "e").is_err());
+        let grammar = Grammar::parse("\ne => H\nH => HO\n", "e").unwrap();
+        assert_eq!(grammar.rules().len(), 1);
+    }
+
+    #[test]
+    fn products() {
+        let mut grammar = Grammar::parse(RULES, "e").unwrap();
+        let molecule = grammar.parse_molecule("HOH");
+        assert_eq!(grammar.products(&molecule).len(), 4);
+        let molecule = grammar.parse_molecule("HOHOHO");
+        assert_eq!(grammar.products(&molecule).len(), 7);
+    }
+
+    #[test]
+    fn shortest_derivation() {
+        let mut grammar = Grammar::parse(RULES, "e").unwrap();
+        for (text, steps) in [("HOH", 3), ("HOHOHO", 6), ("O", 1)] {
+            let target = grammar.parse_molecule(text);
+            let derivation = grammar.shortest_derivation(&target).unwrap();
+            assert_eq!(derivation.steps(), steps);
+            check(&grammar, &derivation, &target);
+        }
+        let target = grammar.parse_molecule("HOX");
+        assert_eq!(grammar.shortest_derivation(&target), None);
+
+        let target = grammar.parse_molecule("HOH");
+        let derivation = grammar.shortest_derivation(&target).unwrap();
+        let shown = derivation.display(grammar.interner()).to_string();
+        assert!(shown.starts_with("e => ") && shown.ends_with(" => HOH"));
+    }
+
+    #[test]
+    fn reduce() {
+        // Rules that only grow, so undoing them always gets smaller
+        let mut grammar = Grammar::parse("e => NAl\nN => CaF\nAl => ThF\nCa => PB", "e").unwrap();
+        let target = grammar.parse_molecule("PBFThF");
+        let greedy = grammar.reduce_greedy(&target).unwrap();
+        assert_eq!(greedy.steps(), 4);
+        check(&grammar, &greedy, &target);
+        let random = grammar.reduce_random(&target, 7, 10).unwrap();
+        assert_eq!(random, grammar.reduce_random(&target, 7, 10).unwrap());
+        check(&grammar, &random, &target);
+
+        let stuck = grammar.parse_molecule("PBThF");
+        assert_eq!(grammar.reduce_greedy(&stuck), None);
+        assert_eq!(grammar.reduce_random(&stuck, 7, 10), None);
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+
+/// A point, or a vector, with N signed coordinates.
+/// 2D, 3D and 4D points have named getters, like point.x() or point.z(), and
+/// coordinates can be set by axis, like point[Z] = 3.
+/// Points can be added, subtracted, negated and scaled. They can also be
+/// used as an offset, for example: location + direction * 2
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point<const N: usize> {
+    pub coords: [i32; N],
+}
+
+/// Axis of the x coordinate, for indexing like point[X].
+pub const X: usize = 0;
+
+/// Axis of the y coordinate.
+pub const Y: usize = 1;
+
+/// Axis of the z coordinate.
+pub const Z: usize = 2;
+
+/// Axis of the w coordinate.
+pub const W: usize = 3;
+
+impl Point<2> {
+    /// Create a 2D point.
+    pub const fn new(x: i32, y: i32) -> Point<2> {
+        Point { coords: [x, y] }
+    }
+
+    /// Rotate 90 degrees clockwise around the origin. Board y goes down, so
+    /// right turns into down.
+    #[allow(dead_code)]
+    pub fn rotate_clockwise(&self) -> Point<2> {
+        Self::new(-self.y(), self.x())
+    }
+
+    /// Rotate 90 degrees counter clockwise around the origin. Board y goes
+    /// down, so right turns into up.
+    #[allow(dead_code)]
+    pub fn rotate_counter_clockwise(&self) -> Point<2> {
+        Self::new(self.y(), -self.x())
+    }
+}
+
+impl Point<3> {
+    /// Create a 3D point.
+    pub const fn new(x: i32, y: i32, z: i32) -> Point<3> {
+        Point { coords: [x, y, z] }
+    }
+}
+
+impl Point<4> {
+    /// Create a 4D point.
+    #[allow(dead_code)]
+    pub const fn new(x: i32, y: i32, z: i32, w: i32) -> Point<4> {
+        Point {
+            coords: [x, y, z, w],
+        }
+    }
+}
+
+impl<const N: usize> Point<N> {
+    /// The point with all coordinates set to 0.
+    pub const fn zero() -> Point<N> {
+        Point { coords: [0; N] }
+    }
+
+    /// The point with all coordinates set to this value.
+    pub const fn splat(value: i32) -> Point<N> {
+        Point { coords: [value; N] }
+    }
+
+    /// A point of length 1 along this axis.
+    #[allow(dead_code)]
+    pub fn unit(axis: usize) -> Point<N> {
+        let mut point = Point::zero();
+        point.coords[axis] = 1;
+        point
+    }
+
+    /// Apply a function to each coordinate.
+    pub fn map<F>(&self, f: F) -> Point<N>
+    where
+        F: FnMut(i32) -> i32,
+    {
+        Point {
+            coords: self.coords.map(f),
+        }
+    }
+
+    /// Combine two points one coordinate at a time.
+    pub fn zip<F>(&self, other: &Point<N>, mut f: F) -> Point<N>
+    where
+        F: FnMut(i32, i32) -> i32,
+    {
+        let mut coords = self.coords;
+        for (c, o) in coords.iter_mut().zip(other.coords) {
+            *c = f(*c, o);
+        }
+        Point { coords }
+    }
+
+    /// Make each coordinate positive.
+    #[allow(dead_code)]
+    pub fn abs(&self) -> Point<N> {
+        self.map(i32::abs)
+    }
+
+    /// Make each coordinate -1, 0 or 1 based on its sign. This turns an
+    /// offset into a single step in the same direction.
+    pub fn signum(&self) -> Point<N> {
+        self.map(i32::signum)
+    }
+
+    /// The smallest value of each coordinate.
+    pub fn component_min(&self, other: &Point<N>) -> Point<N> {
+        self.zip(other, std::cmp::min)
+    }
+
+    /// The largest value of each coordinate.
+    pub fn component_max(&self, other: &Point<N>) -> Point<N> {
+        self.zip(other, std::cmp::max)
+    }
+
+    /// Get the taxi cab distance to another point. Sum of all axis distances.
+    pub fn manhattan(&self, other: &Point<N>) -> i32 {
+        self.zip(other, |a, b| (a - b).abs()).coords.iter().sum()
+    }
+
+    /// Get the chess king distance to another point. The largest axis distance.
+    pub fn chebyshev(&self, other: &Point<N>) -> i32 {
+        self.zip(other, |a, b| (a - b).abs())
+            .coords
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get the straight line distance to another point.
+    #[allow(dead_code)]
+    pub fn euclidean(&self, other: &Point<N>) -> f64 {
+        self.coords
+            .iter()
+            .zip(other.coords)
+            .map(|(a, b)| ((a - b) as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Get the points next to this point. Without diagonals there are 2 * N
+    /// neighbors, each one step along a single axis. With diagonals there are
+    /// 3^N - 1 neighbors, every point with a chebyshev distance of 1.
+    pub fn neighbors(&self, diagonals: bool) -> Vec<Point<N>> {
+        let mut points = vec![];
+        for i in 0..3usize.pow(N as u32) {
+            let mut offset = Point::<N>::zero();
+            let mut digits = i;
+            for axis in (0..N).rev() {
+                offset.coords[axis] = (digits % 3) as i32 - 1;
+                digits /= 3;
+            }
+            let moves = offset.manhattan(&Point::zero());
+            if moves == 0 || (!diagonals && moves > 1) {
+                continue;
+            }
+            points.push(*self + offset);
+        }
+        points
+    }
+
+    /// Rotate 90 degrees around the origin, in the plane of two axes. The
+    /// from axis turns into the to axis. For example, with from x and to y,
+    /// (1, 0) becomes (0, 1) and (0, 1) becomes (-1, 0). Swap the axes to
+    /// rotate the other way.
+    #[allow(dead_code)]
+    pub fn rotate(&self, from_axis: usize, to_axis: usize) -> Point<N> {
+        let mut point = *self;
+        point.coords[to_axis] = self.coords[from_axis];
+        point.coords[from_axis] = -self.coords[to_axis];
+        point
+    }
+}
+
+impl<const N: usize> Default for Point<N> {
+    fn default() -> Self {
+        Point::zero()
+    }
+}
+
+impl<const N: usize> Debug for Point<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut tuple = f.debug_tuple("Point");
+        for c in self.coords.iter() {
+            tuple.field(c);
+        }
+        tuple.finish()
+    }
+}
+
+impl<const N: usize> From<[i32; N]> for Point<N> {
+    fn from(coords: [i32; N]) -> Self {
+        Point { coords }
+    }
+}
+
+impl<const N: usize> Index<usize> for Point<N> {
+    type Output = i32;
+
+    fn index(&self, axis: usize) -> &i32 {
+        &self.coords[axis]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for Point<N> {
+    fn index_mut(&mut self, axis: usize) -> &mut i32 {
+        &mut self.coords[axis]
+    }
+}
+
+impl<const N: usize> Add for Point<N> {
+    type Output = Point<N>;
+
+    fn add(self, other: Point<N>) -> Point<N> {
+        self.zip(&other, |a, b| a + b)
+    }
+}
+
+impl<const N: usize> Sub for Point<N> {
+    type Output = Point<N>;
+
+    fn sub(self, other: Point<N>) -> Point<N> {
+        self.zip(&other, |a, b| a - b)
+    }
+}
+
+impl<const N: usize> Neg for Point<N> {
+    type Output = Point<N>;
+
+    fn neg(self) -> Point<N> {
+        self.map(|c| -c)
+    }
+}
+
+impl<const N: usize> Mul<i32> for Point<N> {
+    type Output = Point<N>;
+
+    fn mul(self, scale: i32) -> Point<N> {
+        self.map(|c| c * scale)
+    }
+}
+
+impl<const N: usize> AddAssign for Point<N> {
+    fn add_assign(&mut self, other: Point<N>) {
+        *self = *self + other;
+    }
+}
+
+impl<const N: usize> SubAssign for Point<N> {
+    fn sub_assign(&mut self, other: Point<N>) {
+        *self = *self - other;
+    }
+}
+
+/// Named getters for each coordinate a point has, like point.x() or point.z().
+macro_rules! named_getters {
+    ($n:literal, $($name:ident => $axis:ident),*) => {
+        impl Point<$n> {
+            $(
+                /// Get this coordinate.
+                #[allow(dead_code)]
+                pub const fn $name(&self) -> i32 {
+                    self.coords[$axis]
+                }
+            )*
+        }
+    };
+}
+
+named_getters!(2, x => X, y => Y);
+named_getters!(3, x => X, y => Y, z => Z);
+named_getters!(4, x => X, y => Y, z => Z, w => W);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let a = Point::<3>::new(1, -2, 3);
+        let b = Point::<3>::new(4, 0, -1);
+        assert_eq!(a + b, Point::<3>::new(5, -2, 2));
+        assert_eq!(a - b, Point::<3>::new(-3, -2, 4));
+        assert_eq!(-a, Point::<3>::new(-1, 2, -3));
+        assert_eq!(a * 2, Point::<3>::new(2, -4, 6));
+        assert_eq!(a.abs(), Point::<3>::new(1, 2, 3));
+        assert_eq!((b - a).signum(), Point::<3>::new(1, 1, -1));
+        assert_eq!(a.component_min(&b), Point::<3>::new(1, -2, -1));
+        assert_eq!(a.component_max(&b), Point::<3>::new(4, 0, 3));
+
+        let mut c = Point::<4>::new(1, 2, 3, 4);
+        c[W] = 0;
+        c += Point::<4>::unit(X);
+        assert_eq!(c, Point::<4>::new(2, 2, 3, 0));
+        assert_eq!((c.x(), c.y(), c.z(), c.w()), (2, 2, 3, 0));
+    }
+
+    #[test]
+    fn distances() {
+        let a = Point::<2>::new(1, 1);
+        let b = Point::<2>::new(4, -3);
+        assert_eq!(a.manhattan(&b), 7);
+        assert_eq!(a.chebyshev(&b), 4);
+        assert_eq!(a.euclidean(&b), 5.0);
+        assert_eq!(Point::<0>::zero().chebyshev(&Point::zero()), 0);
+    }
+
+    #[test]
+    fn neighbors() {
+        let origin = Point::<3>::zero();
+        assert_eq!(origin.neighbors(false).len(), 6);
+        assert_eq!(origin.neighbors(true).len(), 26);
+        assert!(origin.neighbors(false).contains(&Point::<3>::new(0, 0, -1)));
+        assert_eq!(Point::<4>::zero().neighbors(true).len(), 80);
+    }
+
+    #[test]
+    fn rotations() {
+        let right = Point::<2>::new(1, 0);
+        assert_eq!(right.rotate_clockwise(), Point::<2>::new(0, 1));
+        assert_eq!(right.rotate_counter_clockwise(), Point::<2>::new(0, -1));
+        assert_eq!(right.rotate(X, Y), right.rotate_clockwise());
+        let point = Point::<3>::new(1, 2, 3);
+        assert_eq!(point.rotate(Y, Z), Point::<3>::new(1, -3, 2));
+        assert_eq!(point.rotate(Y, Z).rotate(Z, Y), point);
+    }
+}
@@ -1,15 +1,16 @@
 /// Find prime numbers! This uses the "Sieve of Eratosthenes" to find primes.
 /// Once you have primes, then you can do fun things like factorization :)
+/// Numbers bigger than the sieve still work, they are tested with
+/// Miller-Rabin and factored with Pollard's rho.
 pub struct Primes {
     is_prime: Vec<bool>,
     up_to: usize,
     primes: Vec<usize>,
 }
 
-use itertools::Itertools;
-
 impl Primes {
     /// Find all prime numbers up to a certain number.
+    #[allow(dead_code)]
     pub fn new(up_to: usize) -> Primes {
         let mut primes = Primes {
             is_prime: vec![true; up_to],
@@ -41,99 +42,344 @@ impl Primes {
         }
     }
 
-    /// Get if a number is prime.
+    /// Get all the primes found by the sieve, in order.
+    #[allow(dead_code)]
+    pub fn primes(&self) -> &[usize] {
+        &self.primes
+    }
+
+    /// Get if a number is prime. Numbers past the sieve use Miller-Rabin.
     pub fn is_prime(&self, number: usize) -> bool {
-        self.is_prime[number]
+        if number < self.up_to {
+            self.is_prime[number]
+        } else {
+            miller_rabin(number as u64)
+        }
     }
 
-    /// Get the prime factorization of this number. This uses recursion.
+    /// Use a "Segmented Sieve" to find primes in low..high. Only the primes up
+    /// to the square root of high are needed, so this works for ranges far past
+    /// the sieve without allocating everything below them.
+    #[allow(dead_code)]
+    pub fn primes_between(&self, low: usize, high: usize) -> Vec<usize> {
+        assert!(
+            self.up_to * self.up_to >= high,
+            "Sieve up to {} is too small for {high}",
+            self.up_to
+        );
+        if low >= high {
+            return vec![];
+        }
+
+        let mut is_prime = vec![true; high - low];
+        for prime in self.primes.iter().take_while(|p| *p * *p < high) {
+            // Start at the first multiple in the segment, skipping the prime itself
+            let first = std::cmp::max(prime * prime, low.div_ceil(*prime) * prime);
+            for multiple in (first..high).step_by(*prime) {
+                is_prime[multiple - low] = false;
+            }
+        }
+
+        (low..high)
+            .filter(|n| *n >= 2 && is_prime[n - low])
+            .collect()
+    }
+
+    /// Get the prime factorization of this number. Small factors come from
+    /// trial division by the sieved primes, anything left uses Pollard's rho.
     pub fn prime_factors(&self, number: usize) -> Vec<Power> {
         assert_ne!(number, 0, "Cannot factorize 0");
         assert_ne!(number, 1, "1 has no prime factors");
 
-        if self.is_prime(number) {
-            // All prime factors have been found
-            return vec![Power {
-                base: number,
-                exponent: 1,
-            }];
-        }
-
-        // Find the first prime factor for this number
-        let mut prime_factors = vec![];
+        // Divide out the small primes
+        let mut bases = vec![];
+        let mut remaining = number;
         for prime in self.primes.iter() {
-            if number % prime == 0 {
-                // This prime is a factor
-                prime_factors.push(Power {
-                    base: *prime,
-                    exponent: 1,
-                });
-
-                // Use recursion to find the other primes inside the multiple
-                let multiple = number / prime;
-                prime_factors.extend(self.prime_factors(multiple));
-
-                // Stop after one prime is found as this uses recursion to find
-                // the other prime factors
+            if prime * prime > remaining {
                 break;
             }
+            while remaining.is_multiple_of(*prime) {
+                bases.push(*prime);
+                remaining /= prime;
+            }
+        }
+
+        // What is left is prime, or a product of primes too big for the sieve
+        if remaining > 1 {
+            self.split_factors(remaining, &mut bases);
         }
 
         // Conbine exponents for matching bases, so 2^1*2^1*3^1 --> 2^2*3^1
-        prime_factors.sort_by(|a, b| a.base.cmp(&b.base));
-        let mut output = vec![prime_factors[0].clone()];
-        for factor in prime_factors.iter().skip(1) {
-            // Inspect previous number to see if matching base
-            if factor.base == output.last().unwrap().base {
-                // Base matches, combine exponents
-                output.last_mut().unwrap().exponent += factor.exponent;
-            } else {
-                // This is a new base, add it to the list
-                output.push(factor.clone());
+        bases.sort();
+        let mut output: Vec<Power> = vec![];
+        for base in bases {
+            match output.last_mut() {
+                Some(last) if last.base == base => last.exponent += 1,
+                _ => output.push(Power { base, exponent: 1 }),
             }
         }
 
         output
     }
 
+    /// Split a number into prime factors using Pollard's rho. This uses recursion.
+    fn split_factors(&self, number: usize, bases: &mut Vec<usize>) {
+        if number == 1 {
+            return;
+        }
+        if self.is_prime(number) {
+            bases.push(number);
+            return;
+        }
+
+        let factor = pollard_rho(number as u64) as usize;
+        self.split_factors(factor, bases);
+        self.split_factors(number / factor, bases);
+    }
+
     /// Get all factors for a number.
     /// For example 12's factors are [1, 2, 3, 4, 6, 12].
+    #[allow(dead_code)]
     pub fn all_factors(&self, number: usize) -> Vec<usize> {
         if number == 1 {
             // 1 is a special case, it's not prime factorable so just do it manually
             return vec![1];
         }
 
-        let mut output = vec![];
+        // Multiply every factor found so far by each power of the next prime
+        // So 2^2*3^1 --> [1] --> [1, 2, 4] --> [1, 2, 4, 3, 6, 12]
+        let mut output = vec![1];
+        for power in self.prime_factors(number) {
+            let mut multiples = vec![];
+            for factor in output.iter() {
+                let mut multiple = *factor;
+                for _ in 0..power.exponent {
+                    multiple *= power.base;
+                    multiples.push(multiple);
+                }
+            }
+            output.extend(multiples);
+        }
+
+        output.sort();
+        output
+    }
 
-        // Find the prime factors
-        let prime_factors = self.prime_factors(number);
+    /// Get how many factors a number has. For example 12 has 6 factors.
+    #[allow(dead_code)]
+    pub fn divisor_count(&self, number: usize) -> usize {
+        if number == 1 {
+            return 1;
+        }
+        self.prime_factors(number)
+            .iter()
+            .map(|p| p.exponent + 1)
+            .product()
+    }
 
-        // Remove exponents, convert 2^2*3^1 --> 2*2*3
-        let mut prime_factor_bases = vec![];
-        for prime_factor in prime_factors.iter() {
-            for _ in 0..prime_factor.exponent {
-                prime_factor_bases.push(prime_factor.base);
-            }
+    /// Get the sum of all factors of a number, also called sigma.
+    /// For example 12 is 1+2+3+4+6+12 = 28.
+    #[allow(dead_code)]
+    pub fn divisor_sum(&self, number: usize) -> usize {
+        if number == 1 {
+            return 1;
+        }
+        self.prime_factors(number)
+            .iter()
+            .map(|p| {
+                (0..=p.exponent)
+                    .map(|e| p.base.pow(e as u32))
+                    .sum::<usize>()
+            })
+            .product()
+    }
+}
+
+/// Get how many factors each number below up_to has. This walks the multiples
+/// of every number, so it is much faster than factoring each number.
+#[allow(dead_code)]
+pub fn divisor_count_sieve(up_to: usize) -> Vec<usize> {
+    let mut counts = vec![0; up_to];
+    for factor in 1..up_to {
+        for multiple in (factor..up_to).step_by(factor) {
+            counts[multiple] += 1;
+        }
+    }
+    counts
+}
+
+/// Get the sum of the factors of each number below up_to. This walks the
+/// multiples of every number, so it is much faster than factoring each number.
+pub fn divisor_sum_sieve(up_to: usize) -> Vec<usize> {
+    let mut sums = vec![0; up_to];
+    for factor in 1..up_to {
+        for multiple in (factor..up_to).step_by(factor) {
+            sums[multiple] += factor;
+        }
+    }
+    sums
+}
+
+/// Do a * b % modulus without overflowing.
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+/// Do base ^ exponent % modulus without overflowing.
+fn pow_mod(base: u64, exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    let mut base = base % modulus;
+    let mut exponent = exponent;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+    result
+}
 
-            // Each prime factor is also a factor, ensure they are in the output
-            output.push(prime_factor.base);
+/// Test if a number is prime with Miller-Rabin. These bases are enough to
+/// give the right answer for every u64.
+fn miller_rabin(number: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if number < 2 {
+        return false;
+    }
+    for base in BASES {
+        if number.is_multiple_of(base) {
+            return number == base;
         }
+    }
 
-        // Find all factors by multiple all combinations of prime factors
-        // So 1*2*2*3 --> 1*2, 1*3, 2*2, 2*3, and 2*2*3 --> 2, 3, 4, 6, 12
-        for len in 2..=prime_factor_bases.len() {
-            for combo in prime_factor_bases.iter().combinations(len) {
-                output.push(combo.iter().fold(1, |a, &x| a * x));
+    // Write number - 1 as odd * 2^twos
+    let twos = (number - 1).trailing_zeros();
+    let odd = (number - 1) >> twos;
+    'bases: for base in BASES {
+        let mut x = pow_mod(base, odd, number);
+        if x == 1 || x == number - 1 {
+            continue;
+        }
+        for _ in 1..twos {
+            x = mul_mod(x, x, number);
+            if x == number - 1 {
+                continue 'bases;
             }
         }
+        return false;
+    }
+    true
+}
 
-        output.push(1); // Ensure 1 is in the list
-        output.sort();
-        output.dedup(); // There could be duplicates from the combinations
+/// Find a factor of a composite number with Pollard's rho. The factor is not
+/// always prime. Numbers that are not composite never finish.
+fn pollard_rho(number: u64) -> u64 {
+    if number.is_multiple_of(2) {
+        return 2;
+    }
 
-        output
+    // Walk x -> x^2 + c with a tortoise and hare until they meet modulo a
+    // factor. If they meet modulo the number itself, try again with a new c.
+    for c in 1.. {
+        let step = |x: u64| ((mul_mod(x, x, number) as u128 + c as u128) % number as u128) as u64;
+        let (mut tortoise, mut hare, mut factor) = (2, 2, 1);
+        while factor == 1 {
+            tortoise = step(tortoise);
+            hare = step(step(hare));
+            factor = gcd_u64(tortoise.abs_diff(hare), number);
+        }
+        if factor != number {
+            return factor;
+        }
+    }
+    unreachable!()
+}
+
+/// Get the greatest common divisor of two numbers. It is never negative.
+pub fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Get the greatest common divisor of two unsigned numbers, for numbers too
+/// big for an i64.
+pub fn gcd_u64(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd_u64(b, a % b)
+    }
+}
+
+/// Get the least common multiple of two numbers.
+#[allow(dead_code)]
+pub fn lcm(a: i64, b: i64) -> i64 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    (a / gcd(a, b) * b).abs()
+}
+
+/// Get the gcd of two numbers, plus x and y where a*x + b*y = gcd.
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        if a < 0 {
+            (-a, -1, 0)
+        } else {
+            (a, 1, 0)
+        }
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+/// Get x where a*x = 1 modulo the modulus. There is no inverse if a and the
+/// modulus share a factor.
+#[allow(dead_code)]
+pub fn mod_inverse(a: i64, modulus: i64) -> Option<i64> {
+    let (g, x, _) = extended_gcd(a.rem_euclid(modulus), modulus);
+    if g != 1 {
+        return None;
     }
+    Some(x.rem_euclid(modulus))
+}
+
+/// Get base ^ exponent modulo the modulus. The answer is never negative.
+#[allow(dead_code)]
+pub fn mod_pow(base: i64, exponent: u64, modulus: i64) -> i64 {
+    assert!(modulus > 0, "Modulus must be positive");
+    pow_mod(base.rem_euclid(modulus) as u64, exponent, modulus as u64) as i64
+}
+
+/// Solve a list of x = remainder modulo modulus with the Chinese Remainder
+/// Theorem. The moduli do not need to be coprime. Return the remainder and
+/// modulus that all the answers share, or None if there is no answer or it
+/// does not fit in an i64.
+#[allow(dead_code)]
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    let mut answer = (0i64, 1i64);
+    for &(remainder, modulus) in congruences {
+        // Solve answer.0 + answer.1 * k = remainder modulo modulus
+        let (g, x, _) = extended_gcd(answer.1, modulus);
+        let (g, x) = (g as i128, x as i128);
+        let diff = remainder as i128 - answer.0 as i128;
+        if diff % g != 0 {
+            return None;
+        }
+
+        // The combined modulus may not fit in an i64
+        let step = modulus as i128 / g;
+        let k = diff / g * x % step;
+        let combined = answer.1 as i128 * step;
+        let value = (answer.0 as i128 + answer.1 as i128 * k).rem_euclid(combined);
+        answer = (i64::try_from(value).ok()?, i64::try_from(combined).ok()?);
+    }
+    Some(answer)
 }
 
 /// Power Struct. This is base^exponent data type.
@@ -142,3 +388,98 @@ pub struct Power {
     pub base: usize,
     pub exponent: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sieve() {
+        let primes = Primes::new(30);
+        assert_eq!(primes.primes(), [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert!(!primes.is_prime(0));
+        assert!(!primes.is_prime(1));
+        assert!(primes.is_prime(29));
+        assert_eq!(
+            primes.primes_between(800, 900).len(),
+            (800..900).filter(|n| miller_rabin(*n)).count()
+        );
+        assert_eq!(primes.primes_between(0, 12), [2, 3, 5, 7, 11]);
+    }
+
+    #[test]
+    fn miller_rabin_past_the_sieve() {
+        let primes = Primes::new(30);
+        assert!(primes.is_prime(1_000_000_007));
+        assert!(primes.is_prime(18_446_744_073_709_551_557));
+        // A Carmichael number, and a strong pseudoprime to bases 2, 3, 5 and 7
+        assert!(!primes.is_prime(561));
+        assert!(!primes.is_prime(3_215_031_751));
+        assert!(!primes.is_prime(1_000_000_007 * 998_244_353));
+    }
+
+    #[test]
+    fn pollard_rho_factors() {
+        let primes = Primes::new(100);
+        let powers = |number| {
+            primes
+                .prime_factors(number)
+                .iter()
+                .map(|p| (p.base, p.exponent))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(powers(360), [(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(
+            powers(12 * 998_244_353 * 1_000_000_007),
+            [(2, 2), (3, 1), (998_244_353, 1), (1_000_000_007, 1)]
+        );
+        assert_eq!(powers(1_000_003 * 1_000_003), [(1_000_003, 2)]);
+        assert_eq!(powers(1_000_000_007), [(1_000_000_007, 1)]);
+    }
+
+    #[test]
+    fn divisors() {
+        let primes = Primes::new(10);
+        assert_eq!(primes.all_factors(1), [1]);
+        assert_eq!(primes.all_factors(12), [1, 2, 3, 4, 6, 12]);
+        let counts = divisor_count_sieve(200);
+        let sums = divisor_sum_sieve(200);
+        for number in 1..200 {
+            assert_eq!(primes.divisor_count(number), counts[number]);
+            assert_eq!(primes.divisor_sum(number), sums[number]);
+        }
+        assert_eq!(sums[12], 28);
+    }
+
+    #[test]
+    fn gcd_and_friends() {
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(gcd_u64(u64::MAX, 5), 5);
+        assert_eq!(lcm(4, -6), 12);
+        assert_eq!(lcm(0, 6), 0);
+        let (g, x, y) = extended_gcd(240, 46);
+        assert_eq!(g, 2);
+        assert_eq!(240 * x + 46 * y, 2);
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(-3, 7), Some(2));
+        assert_eq!(mod_inverse(2, 4), None);
+        assert_eq!(mod_pow(-2, 3, 5), 2);
+        assert_eq!(mod_pow(7, 0, 1), 0);
+    }
+
+    #[test]
+    fn chinese_remainders() {
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(-1, 3), (7, 5)]), Some((2, 15)));
+
+        // Moduli that share a factor
+        assert_eq!(crt(&[(2, 4), (4, 6)]), Some((10, 12)));
+        assert_eq!(crt(&[(3, 6), (3, 6)]), Some((3, 6)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+
+        // The answer does not fit in an i64
+        assert_eq!(crt(&[(0, 1 << 40), (1, (1 << 40) + 1)]), None);
+    }
+}
@@ -0,0 +1,309 @@
+use std::fmt::Debug;
+use std::ops::{Add, RangeInclusive, Sub};
+
+/// A set of whole numbers, stored as sorted inclusive ranges.
+/// Ranges never overlap or touch, touching ranges are merged into one.
+/// This makes it cheap to work with huge ranges, like covering a row
+/// of a map that is millions of squares wide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeSet<T> {
+    ranges: Vec<RangeInclusive<T>>,
+}
+
+/// The number types that can be used in a range set. Ranges can go all the
+/// way to the smallest or largest value of the type.
+pub trait RangeValue:
+    Copy + Ord + Debug + Add<Output = Self> + Sub<Output = Self> + From<u8>
+{
+    /// The next number up, or None for the largest value.
+    fn successor(self) -> Option<Self>;
+
+    /// The next number down, or None for the smallest value.
+    fn predecessor(self) -> Option<Self>;
+}
+
+macro_rules! range_value {
+    ($($t:ty),*) => {
+        $(
+            impl RangeValue for $t {
+                fn successor(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn predecessor(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+            }
+        )*
+    };
+}
+
+range_value!(u8, u16, u32, u64, u128, usize, i16, i32, i64, i128, isize);
+
+impl<T> RangeSet<T>
+where
+    T: RangeValue,
+{
+    /// Create an empty set.
+    pub fn new() -> RangeSet<T> {
+        RangeSet { ranges: vec![] }
+    }
+
+    /// The sorted ranges in the set.
+    #[allow(dead_code)]
+    pub fn ranges(&self) -> &[RangeInclusive<T>] {
+        &self.ranges
+    }
+
+    /// Is the set empty?
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// How many numbers are in the set. The count must fit in T, so a set of
+    /// every u32 cannot be counted as a u32.
+    pub fn len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::from(0), |a, r| a + *r.end() - *r.start() + T::from(1))
+    }
+
+    /// Is this number in the set?
+    #[allow(dead_code)]
+    pub fn contains(&self, value: T) -> bool {
+        let i = self.ranges.partition_point(|r| *r.end() < value);
+        i < self.ranges.len() && *self.ranges[i].start() <= value
+    }
+
+    /// Is every number of this range in the set?
+    pub fn contains_range(&self, range: &RangeInclusive<T>) -> bool {
+        let i = self.ranges.partition_point(|r| r.end() < range.start());
+        i < self.ranges.len()
+            && self.ranges[i].start() <= range.start()
+            && self.ranges[i].end() >= range.end()
+    }
+
+    /// Does any number of this range appear in the set?
+    pub fn overlaps(&self, range: &RangeInclusive<T>) -> bool {
+        let i = self.ranges.partition_point(|r| r.end() < range.start());
+        i < self.ranges.len() && self.ranges[i].start() <= range.end()
+    }
+
+    /// Add a range to the set. Empty ranges are ignored.
+    pub fn insert(&mut self, range: RangeInclusive<T>) {
+        if range.is_empty() {
+            return;
+        }
+
+        // Find all ranges that overlap or touch the new range, and merge them
+        let (mut start, mut end) = range.into_inner();
+        // There is no number after the largest value, so nothing can touch it
+        let first = self
+            .ranges
+            .partition_point(|r| r.end().successor().is_some_and(|next| next < start));
+        let last = self
+            .ranges
+            .partition_point(|r| end.successor().is_none_or(|next| *r.start() <= next));
+        if first < last {
+            start = std::cmp::min(start, *self.ranges[first].start());
+            end = std::cmp::max(end, *self.ranges[last - 1].end());
+        }
+        self.ranges.splice(first..last, [start..=end]);
+    }
+
+    /// Take a range out of the set.
+    pub fn remove(&mut self, range: RangeInclusive<T>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let (start, end) = range.into_inner();
+        let first = self.ranges.partition_point(|r| *r.end() < start);
+        let last = self.ranges.partition_point(|r| *r.start() <= end);
+        if first == last {
+            return;
+        }
+
+        // Keep the parts sticking out on either side. A part sticking out
+        // below start means start is not the smallest value, and the same for
+        // end, so there is always a number before start and after end.
+        let mut keep = vec![];
+        let (before, after) = (start.predecessor(), end.successor());
+        if let (true, Some(before)) = (*self.ranges[first].start() < start, before) {
+            keep.push(*self.ranges[first].start()..=before);
+        }
+        if let (true, Some(after)) = (*self.ranges[last - 1].end() > end, after) {
+            keep.push(after..=*self.ranges[last - 1].end());
+        }
+        self.ranges.splice(first..last, keep);
+    }
+
+    /// All numbers in either set.
+    #[allow(dead_code)]
+    pub fn union(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        self.ranges
+            .iter()
+            .chain(other.ranges.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Numbers in both sets.
+    #[allow(dead_code)]
+    pub fn intersection(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        // Walk both sorted lists together
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = std::cmp::max(*a.start(), *b.start());
+            let end = std::cmp::min(*a.end(), *b.end());
+            if start <= end {
+                ranges.push(start..=end);
+            }
+            if a.end() < b.end() {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        RangeSet { ranges }
+    }
+
+    /// Numbers in this set, but not the other set.
+    pub fn difference(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        let mut set = self.clone();
+        for range in other.ranges.iter() {
+            set.remove(range.clone());
+        }
+        set
+    }
+
+    /// The numbers inside this range that are not in the set.
+    #[allow(dead_code)]
+    pub fn gaps(&self, within: RangeInclusive<T>) -> RangeSet<T> {
+        RangeSet::from(within).difference(self)
+    }
+}
+
+impl<T> Default for RangeSet<T>
+where
+    T: RangeValue,
+{
+    fn default() -> Self {
+        RangeSet::new()
+    }
+}
+
+impl<T> From<RangeInclusive<T>> for RangeSet<T>
+where
+    T: RangeValue,
+{
+    fn from(range: RangeInclusive<T>) -> Self {
+        let mut set = RangeSet::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl<T> FromIterator<RangeInclusive<T>> for RangeSet<T>
+where
+    T: RangeValue,
+{
+    /// Build a set from many ranges. The ranges are sorted first, so they
+    /// can be merged in one pass.
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut sorted: Vec<RangeInclusive<T>> =
+            iter.into_iter().filter(|r| !r.is_empty()).collect();
+        sorted.sort_by_key(|r| *r.start());
+
+        let mut ranges: Vec<RangeInclusive<T>> = vec![];
+        for range in sorted {
+            match ranges.last_mut() {
+                Some(last)
+                    if last
+                        .end()
+                        .successor()
+                        .is_none_or(|next| *range.start() <= next) =>
+                {
+                    if range.end() > last.end() {
+                        *last = *last.start()..=*range.end();
+                    }
+                }
+                _ => ranges.push(range),
+            }
+        }
+        RangeSet { ranges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_merges_touching_ranges() {
+        let mut set = RangeSet::new();
+        set.insert(5..=7);
+        set.insert(1..=2);
+        set.insert(3..=4);
+        assert_eq!(set.ranges(), &[1..=7]);
+        set.insert(10..=12);
+        set.insert(0..=0);
+        #[allow(clippy::reversed_empty_ranges)]
+        set.insert(20..=19);
+        assert_eq!(set.ranges(), &[0..=7, 10..=12]);
+        assert_eq!(set.len(), 11);
+        assert!(set.contains(10));
+        assert!(!set.contains(8));
+        assert!(set.contains_range(&(2..=6)));
+        assert!(!set.contains_range(&(6..=10)));
+        assert!(set.overlaps(&(8..=10)));
+        assert!(!set.overlaps(&(8..=9)));
+    }
+
+    #[test]
+    fn remove_splits_ranges() {
+        let mut set = RangeSet::from(1..=10);
+        set.remove(4..=5);
+        assert_eq!(set.ranges(), &[1..=3, 6..=10]);
+        set.remove(0..=1);
+        set.remove(10..=20);
+        assert_eq!(set.ranges(), &[2..=3, 6..=9]);
+        set.remove(3..=6);
+        assert_eq!(set.ranges(), &[2..=2, 7..=9]);
+        set.remove(0..=100);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn edges_of_the_type() {
+        let mut set = RangeSet::from(250u8..=u8::MAX);
+        set.insert(0..=5);
+        set.insert(6..=6);
+        assert_eq!(set.ranges(), &[0..=6, 250..=255]);
+        set.insert(100..=249);
+        assert_eq!(set.ranges(), &[0..=6, 100..=255]);
+        set.remove(u8::MAX..=u8::MAX);
+        set.remove(0..=0);
+        assert_eq!(set.ranges(), &[1..=6, 100..=254]);
+
+        let full = RangeSet::from(i64::MIN..=i64::MAX);
+        let mut set = full.clone();
+        set.remove(0..=0);
+        assert_eq!(set.ranges(), &[i64::MIN..=-1, 1..=i64::MAX]);
+        assert_eq!(full.difference(&set).ranges(), &[0..=0]);
+    }
+
+    #[test]
+    fn set_operations() {
+        let a: RangeSet<i32> = [8..=12, 1..=3, 2..=5].into_iter().collect();
+        let b: RangeSet<i32> = [4..=9, 11..=20].into_iter().collect();
+        assert_eq!(a.ranges(), &[1..=5, 8..=12]);
+        assert_eq!(a.union(&b).ranges(), &[1..=20]);
+        assert_eq!(a.intersection(&b).ranges(), &[4..=5, 8..=9, 11..=12]);
+        assert_eq!(a.difference(&b).ranges(), &[1..=3, 10..=10]);
+        assert_eq!(a.gaps(0..=14).ranges(), &[0..=0, 6..=7, 13..=14]);
+    }
+}
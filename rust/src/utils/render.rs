@@ -0,0 +1,322 @@
+use anyhow::{Context, Result};
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::utils::board::*;
+
+/// Where puzzles save debug pictures, set once from the command line.
+static PICTURE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Have puzzles save their debug pictures in this directory. Only the first
+/// directory set is used.
+pub fn set_picture_dir(directory: PathBuf) {
+    let _ = PICTURE_DIR.set(directory);
+}
+
+/// Get the directory to save debug pictures in, or None to not save them.
+pub fn picture_dir() -> Option<&'static Path> {
+    PICTURE_DIR.get().map(|directory| directory.as_path())
+}
+
+/// Render boards to images for debugging. Each board value maps to a color,
+/// and players can have their own colors so they stand out from the board.
+/// A single board can be saved as a PNG. Or frames can be recorded while a
+/// simulation runs, then saved as PNGs or as one animated GIF.
+pub struct Renderer<T>
+where
+    T: Clone + Copy + Debug + PartialEq + std::fmt::Display,
+{
+    /// Colors for board values. Index into this list is the palette index.
+    colors: Vec<(T, Color)>,
+
+    /// Colors for players, based on the player value.
+    player_colors: Vec<(T, Color)>,
+
+    /// Color for any value without a color.
+    default_color: Color,
+
+    /// How many pixels wide and tall each board square is.
+    scale: usize,
+
+    /// Recorded frames.
+    frames: Vec<Frame>,
+}
+
+/// A color as red, green and blue.
+pub type Color = [u8; 3];
+
+/// Internal only.
+/// A recorded frame. Pixels are palette indexes, already scaled up.
+struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl<T> Renderer<T>
+where
+    T: Clone + Copy + Debug + PartialEq + std::fmt::Display,
+{
+    /// Create a new renderer. Each board square is scale pixels wide and tall.
+    /// Values without a color are drawn in black.
+    pub fn new(scale: usize) -> Renderer<T> {
+        assert!(scale > 0);
+        Renderer {
+            colors: vec![],
+            player_colors: vec![],
+            default_color: [0, 0, 0],
+            scale,
+            frames: vec![],
+        }
+    }
+
+    /// Set the color used for this board value. Players with this value use
+    /// it too, unless they have a player color.
+    pub fn set_color(&mut self, value: T, color: Color) {
+        match self.colors.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, c)) => *c = color,
+            None => self.colors.push((value, color)),
+        }
+    }
+
+    /// Set the color used for players with this value.
+    #[allow(dead_code)]
+    pub fn set_player_color(&mut self, value: T, color: Color) {
+        match self.player_colors.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, c)) => *c = color,
+            None => self.player_colors.push((value, color)),
+        }
+    }
+
+    /// Set the color used for values without a color.
+    pub fn set_default_color(&mut self, color: Color) {
+        self.default_color = color;
+    }
+
+    /// The palette has the default color first, then board colors, then player colors.
+    fn palette(&self) -> Vec<Color> {
+        let mut palette = vec![self.default_color];
+        palette.extend(self.colors.iter().map(|(_, c)| *c));
+        palette.extend(self.player_colors.iter().map(|(_, c)| *c));
+        assert!(palette.len() <= 256, "Too many colors for a palette");
+        palette
+    }
+
+    /// Find the palette index of a board square. Visible players are drawn
+    /// on top of the board.
+    fn palette_index(&self, board: &Board<T>, point: BoardPoint) -> u8 {
+        let value = match board.visible_player_value_at(point) {
+            Some(player_value) => {
+                let player_index = self
+                    .player_colors
+                    .iter()
+                    .position(|(v, _)| *v == player_value);
+                if let Some(i) = player_index {
+                    return (1 + self.colors.len() + i) as u8;
+                }
+                player_value
+            }
+            None => board.value_at(point),
+        };
+
+        match self.colors.iter().position(|(v, _)| *v == value) {
+            Some(i) => (1 + i) as u8,
+            None => 0,
+        }
+    }
+
+    /// Convert the board into a frame of palette indexes.
+    fn frame(&self, board: &Board<T>) -> Frame {
+        let width = board.width() as usize * self.scale;
+        let height = board.height() as usize * self.scale;
+        let mut pixels = vec![0; width * height];
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                let index = self.palette_index(board, BoardPoint::new(x, y));
+                let x_ = x as usize * self.scale;
+                let y_ = y as usize * self.scale;
+                for row in y_..y_ + self.scale {
+                    pixels[row * width + x_..row * width + x_ + self.scale].fill(index);
+                }
+            }
+        }
+
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Save a frame as a PNG file.
+    fn write_frame_png(&self, frame: &Frame, path: &Path) -> Result<()> {
+        let palette = self.palette();
+        let mut rgb = Vec::with_capacity(frame.pixels.len() * 3);
+        for index in frame.pixels.iter() {
+            rgb.extend(palette[*index as usize]);
+        }
+
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(file),
+            frame.width as u32,
+            frame.height as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&rgb)?;
+        Ok(())
+    }
+
+    /// Save the board as a PNG file.
+    pub fn write_png(&self, board: &Board<T>, path: &Path) -> Result<()> {
+        self.write_frame_png(&self.frame(board), path)
+    }
+
+    /// Record the board as the next frame of the animation.
+    #[allow(dead_code)]
+    pub fn add_frame(&mut self, board: &Board<T>) {
+        let frame = self.frame(board);
+        self.frames.push(frame);
+    }
+
+    /// How many frames have been recorded.
+    #[allow(dead_code)]
+    pub fn frames_len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Save each recorded frame as a PNG in this directory. Files are named
+    /// frame_0000.png, frame_0001.png and so on.
+    #[allow(dead_code)]
+    pub fn write_png_frames(&self, directory: &Path) -> Result<()> {
+        std::fs::create_dir_all(directory)?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            self.write_frame_png(frame, &directory.join(format!("frame_{i:04}.png")))?;
+        }
+        Ok(())
+    }
+
+    /// Save all recorded frames as an animated GIF that loops forever. Each
+    /// frame is shown for the delay in milliseconds. Boards can change size
+    /// between frames, the GIF is as big as the biggest frame. GIFs can be at
+    /// most 65535 pixels wide and tall.
+    #[allow(dead_code)]
+    pub fn write_gif(&self, path: &Path, delay_ms: u16) -> Result<()> {
+        let size = |pixels: usize| u16::try_from(pixels).context("Frame is too big for a GIF");
+        let width = self.frames.iter().map(|f| f.width).max().unwrap_or(0);
+        let height = self.frames.iter().map(|f| f.height).max().unwrap_or(0);
+        let palette: Vec<u8> = self.palette().into_iter().flatten().collect();
+
+        let file = File::create(path)?;
+        let mut encoder =
+            gif::Encoder::new(BufWriter::new(file), size(width)?, size(height)?, &palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for frame in self.frames.iter() {
+            let mut gif_frame = gif::Frame::from_indexed_pixels(
+                size(frame.width)?,
+                size(frame.height)?,
+                &frame.pixels,
+                None,
+            );
+            gif_frame.delay = delay_ms / 10; // GIF delay is in 10ms units
+            gif_frame.dispose = gif::DisposalMethod::Background;
+            encoder.write_frame(&gif_frame)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board() -> Board<char> {
+        let mut board = Board::new();
+        board.push_row(vec!['#', '.']);
+        board.push_row(vec!['.', '?']);
+        board
+    }
+
+    fn renderer() -> Renderer<char> {
+        let mut renderer = Renderer::new(2);
+        renderer.set_color('#', [255, 255, 255]);
+        renderer.set_color('.', [0, 0, 255]);
+        renderer.set_color('.', [0, 255, 0]);
+        renderer.set_default_color([9, 9, 9]);
+        renderer
+    }
+
+    #[test]
+    fn palette_indexes() {
+        let mut board = board();
+        let mut renderer = renderer();
+        assert_eq!(
+            renderer.palette(),
+            [[9, 9, 9], [255, 255, 255], [0, 255, 0]]
+        );
+
+        // Players use the board color for their value, or their own color
+        let player = board.add_player(BoardPoint::new(1, 0), '#');
+        assert_eq!(renderer.palette_index(&board, BoardPoint::new(1, 0)), 1);
+        renderer.set_player_color('#', [255, 0, 0]);
+        assert_eq!(renderer.palette_index(&board, BoardPoint::new(1, 0)), 3);
+        assert_eq!(renderer.palette_index(&board, BoardPoint::new(0, 0)), 1);
+        assert_eq!(renderer.palette_index(&board, BoardPoint::new(1, 1)), 0);
+        board.set_player_visible(player, false);
+        assert_eq!(renderer.palette_index(&board, BoardPoint::new(1, 0)), 2);
+
+        let frame = renderer.frame(&board);
+        assert_eq!((frame.width, frame.height), (4, 4));
+        assert_eq!(frame.pixels[..8], [1, 1, 2, 2, 1, 1, 2, 2]);
+        assert_eq!(frame.pixels[8..], [2, 2, 0, 0, 2, 2, 0, 0]);
+    }
+
+    #[test]
+    fn write_pictures() {
+        let directory = std::env::temp_dir().join("aoc_render_test");
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut renderer = renderer();
+
+        let path = directory.join("board.png");
+        renderer.write_png(&board(), &path).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut rgb = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut rgb).unwrap();
+        assert_eq!(rgb[..6], [255, 255, 255, 255, 255, 255]);
+        assert_eq!(rgb[rgb.len() - 3..], [9, 9, 9]);
+
+        let mut wide = Board::new();
+        wide.push_row(vec!['#'; 3]);
+        renderer.add_frame(&board());
+        renderer.add_frame(&wide);
+        assert_eq!(renderer.frames_len(), 2);
+        renderer.write_png_frames(&directory).unwrap();
+        assert!(directory.join("frame_0001.png").exists());
+
+        let path = directory.join("frames.gif");
+        renderer.write_gif(&path, 100).unwrap();
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (6, 4));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+            frames += 1;
+        }
+        assert_eq!(frames, 2);
+
+        // Too big for a GIF
+        let mut huge = Board::new();
+        huge.push_row(vec!['#'; 40000]);
+        renderer.add_frame(&huge);
+        assert!(renderer.write_gif(&path, 100).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
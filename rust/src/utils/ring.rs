@@ -0,0 +1,129 @@
+use std::fmt::Debug;
+use std::ops::{Index, IndexMut};
+
+/// A ring of values, where the last value is next to the first value.
+/// Any index is allowed, it wraps around the ring. Negative indexes count
+/// back from the end, like -1 is the last value.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Ring<T> {
+    values: Vec<T>,
+}
+
+impl<T> Ring<T>
+where
+    T: Clone + Debug,
+{
+    /// Create a new ring from a list of values.
+    pub fn new(values: Vec<T>) -> Ring<T> {
+        Ring { values }
+    }
+
+    /// Get how many values are in the ring.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Is the ring empty?
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Convert any index into an index of the underlying list.
+    pub fn wrap(&self, index: i64) -> usize {
+        assert!(!self.values.is_empty(), "Cannot index an empty ring");
+        index.rem_euclid(self.values.len() as i64) as usize
+    }
+
+    /// Get the value at any index.
+    pub fn get(&self, index: i64) -> &T {
+        &self.values[self.wrap(index)]
+    }
+
+    /// Get the value at any index to change it.
+    pub fn get_mut(&mut self, index: i64) -> &mut T {
+        let index = self.wrap(index);
+        &mut self.values[index]
+    }
+
+    /// Rotate the values to the left, so the value at `count` is now at 0.
+    pub fn rotate_left(&mut self, count: i64) {
+        if self.values.is_empty() {
+            return;
+        }
+        let count = self.wrap(count);
+        self.values.rotate_left(count);
+    }
+
+    /// Rotate the values to the right, so the value at 0 is now at `count`.
+    pub fn rotate_right(&mut self, count: i64) {
+        self.rotate_left(-count);
+    }
+
+    /// Iterate once around the ring, starting at index 0.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+}
+
+impl<T> Index<i64> for Ring<T>
+where
+    T: Clone + Debug,
+{
+    type Output = T;
+
+    fn index(&self, index: i64) -> &T {
+        self.get(index)
+    }
+}
+
+impl<T> IndexMut<i64> for Ring<T>
+where
+    T: Clone + Debug,
+{
+    fn index_mut(&mut self, index: i64) -> &mut T {
+        self.get_mut(index)
+    }
+}
+
+impl<T> FromIterator<T> for Ring<T>
+where
+    T: Clone + Debug,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Ring<T> {
+        Ring::new(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_wraps_both_ways() {
+        let mut ring = Ring::new(vec!['a', 'b', 'c']);
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring[0], 'a');
+        assert_eq!(ring[4], 'b');
+        assert_eq!(ring[-1], 'c');
+        assert_eq!(ring[-7], 'c');
+        ring[-3] = 'z';
+        assert_eq!(ring[3], 'z');
+    }
+
+    #[test]
+    fn rotate() {
+        let mut ring: Ring<i32> = (0..5).collect();
+        ring.rotate_right(2);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [3, 4, 0, 1, 2]);
+        ring.rotate_left(7);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        ring.rotate_left(-1);
+        assert_eq!(ring[0], 4);
+
+        let mut empty: Ring<i32> = Ring::default();
+        empty.rotate_left(3);
+        assert!(empty.is_empty());
+    }
+}
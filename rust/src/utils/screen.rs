@@ -1,10 +1,84 @@
+use anyhow::{bail, Context, Result};
 use grid::*;
 use rusttype::Point;
+use std::fmt;
+
+use crate::utils::ring::Ring;
 
 // A screen to display pixels. Supports setting blocks of pixels and shifting
-// pixeles by rows or columns. And can print them to the console for easy debug.
+// pixeles by rows or columns. Can run a script of these commands, forwards or
+// backwards, and can render the pixels with any chars.
 pub struct Screen {
     grid: Grid<char>,
+
+    /// Every command run, with the pixels a rect covered, so it can be undone.
+    history: Vec<(ScreenCommand, Vec<char>)>,
+}
+
+/// The commands a screen script supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenCommand {
+    /// Turn on a rectangle of pixels in the top left, like "rect 3x2".
+    Rect { width: usize, height: usize },
+
+    /// Rotate a row to the right, like "rotate row y=0 by 4".
+    RotateRow { row: usize, count: usize },
+
+    /// Rotate a column down, like "rotate column x=1 by 1".
+    RotateColumn { column: usize, count: usize },
+}
+
+impl ScreenCommand {
+    /// Parse one command from text.
+    pub fn parse(text: &str) -> Result<ScreenCommand> {
+        let number = |s: &str| -> Result<usize> {
+            s.parse()
+                .with_context(|| format!("Expected a number, found '{s}'"))
+        };
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let command = match words[..] {
+            ["rect", size] => {
+                let Some((width, height)) = size.split_once('x') else {
+                    bail!("Expected rect WIDTHxHEIGHT, found '{size}'");
+                };
+                ScreenCommand::Rect {
+                    width: number(width)?,
+                    height: number(height)?,
+                }
+            }
+            ["rotate", "row", target, "by", count] => {
+                let Some(row) = target.strip_prefix("y=") else {
+                    bail!("Expected y=ROW, found '{target}'");
+                };
+                ScreenCommand::RotateRow {
+                    row: number(row)?,
+                    count: number(count)?,
+                }
+            }
+            ["rotate", "column", target, "by", count] => {
+                let Some(column) = target.strip_prefix("x=") else {
+                    bail!("Expected x=COLUMN, found '{target}'");
+                };
+                ScreenCommand::RotateColumn {
+                    column: number(column)?,
+                    count: number(count)?,
+                }
+            }
+            _ => bail!("Unknown command '{text}'"),
+        };
+        Ok(command)
+    }
+
+    /// Parse a script, one command per line. Blank lines are skipped.
+    pub fn parse_script(script: &str) -> Result<Vec<ScreenCommand>> {
+        script
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| ScreenCommand::parse(line).with_context(|| format!("Line {}", i + 1)))
+            .collect()
+    }
 }
 
 impl Screen {
@@ -12,6 +86,7 @@ impl Screen {
     pub fn new(width: usize, height: usize) -> Screen {
         let mut screen = Screen {
             grid: Grid::new(height, width),
+            history: vec![],
         };
 
         // Init all pixels to off as '.', where '#' where be on
@@ -31,49 +106,124 @@ impl Screen {
                 self.grid[y][x] = '#';
             }
         }
-        self.debug_print();
     }
 
     // Rotate the pixels in a row a fixed number of times. Pixels rotate back
     // to the begining of the row.
     pub fn rotate_row(&mut self, row: usize, count: usize) {
         log::debug!("Rotate row {} by {} pixels", row, count);
-        let orig: Vec<char> = self.grid.iter_row(row).map(|x| *x).collect();
-        let width = self.grid.cols();
-        let offset = count % width;
-        for i in 0..width {
-            self.grid[row][(i + offset) % width] = orig[i];
+        let mut pixels: Ring<char> = self.grid.iter_row(row).copied().collect();
+        pixels.rotate_right(count as i64);
+        for (x, pixel) in pixels.iter().enumerate() {
+            self.grid[row][x] = *pixel;
         }
-        self.debug_print();
     }
 
     // Rotate the pixels in a column a fixed number of times. Pixels rotate back
     // to the begining of the column.
     pub fn rotate_column(&mut self, column: usize, count: usize) {
         log::debug!("Rotate column {} by {} pixels", column, count);
-        let orig: Vec<char> = self.grid.iter_col(column).map(|x| *x).collect();
-        let height = self.grid.rows();
-        let offset = count % height;
-        for i in 0..height {
-            self.grid[(i + offset) % height][column] = orig[i];
+        let mut pixels: Ring<char> = self.grid.iter_col(column).copied().collect();
+        pixels.rotate_right(count as i64);
+        for (y, pixel) in pixels.iter().enumerate() {
+            self.grid[y][column] = *pixel;
+        }
+    }
+
+    /// Check a command fits on this screen.
+    fn check(&self, command: &ScreenCommand) -> Result<()> {
+        let (width, height) = (self.grid.cols(), self.grid.rows());
+        match *command {
+            ScreenCommand::Rect {
+                width: w,
+                height: h,
+            } if w > width || h > height => {
+                bail!("Rect {w}x{h} does not fit on a {width}x{height} screen")
+            }
+            ScreenCommand::RotateRow { row, .. } if row >= height => {
+                bail!("Row {row} is not on a screen {height} tall")
+            }
+            ScreenCommand::RotateColumn { column, .. } if column >= width => {
+                bail!("Column {column} is not on a screen {width} wide")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Run one command. It is remembered, so it can be undone.
+    pub fn run(&mut self, command: &ScreenCommand) -> Result<()> {
+        self.check(command)?;
+        let mut covered = vec![];
+        match *command {
+            ScreenCommand::Rect { width, height } => {
+                for y in 0..height {
+                    covered.extend(self.grid.iter_row(y).take(width));
+                }
+                self.set_pixels(Point { x: 0, y: 0 }, width, height)
+            }
+            ScreenCommand::RotateRow { row, count } => self.rotate_row(row, count),
+            ScreenCommand::RotateColumn { column, count } => self.rotate_column(column, count),
+        }
+        self.history.push((*command, covered));
+        Ok(())
+    }
+
+    /// Run a list of commands in order.
+    pub fn run_all(&mut self, commands: &[ScreenCommand]) -> Result<()> {
+        for command in commands.iter() {
+            self.run(command)?;
         }
-        self.debug_print();
+        Ok(())
     }
 
-    /// Convert pixels to a string for easy display
-    pub fn to_string(&self) -> String {
-        let mut string = "\n".to_string();
+    /// Parse and run a script, one command per line.
+    #[allow(dead_code)]
+    pub fn run_script(&mut self, script: &str) -> Result<()> {
+        self.run_all(&ScreenCommand::parse_script(script)?)
+    }
+
+    /// Undo the last command run. Rotations are undone by rotating back, and
+    /// a rect puts back the pixels it covered. Return the command undone, or
+    /// None if there is nothing left to undo.
+    pub fn undo(&mut self) -> Option<ScreenCommand> {
+        let (command, covered) = self.history.pop()?;
+        let (width, height) = (self.grid.cols(), self.grid.rows());
+        match command {
+            ScreenCommand::Rect { width, .. } => {
+                for (i, pixel) in covered.into_iter().enumerate() {
+                    self.grid[i / width][i % width] = pixel;
+                }
+            }
+            ScreenCommand::RotateRow { row, count } => self.rotate_row(row, width - count % width),
+            ScreenCommand::RotateColumn { column, count } => {
+                self.rotate_column(column, height - count % height)
+            }
+        }
+        Some(command)
+    }
+
+    /// Undo every command run, last command first. This unscrambles the
+    /// screen back to how it was before any commands.
+    pub fn undo_all(&mut self) {
+        while self.undo().is_some() {}
+    }
+
+    /// Draw the pixels as rows of text, using any chars for on and off.
+    pub fn render(&self, on: char, off: char) -> String {
+        let mut string = String::new();
         for row in 0..self.grid.rows() {
-            string += &self.grid.iter_row(row).map(|x| *x).collect::<String>();
-            string += "\n";
+            for pixel in self.grid.iter_row(row) {
+                string.push(if *pixel == '#' { on } else { off });
+            }
+            string.push('\n');
         }
         string
     }
 
     /// Print the grid values.
+    #[allow(dead_code)]
     pub fn debug_print(&self) {
-        let string = self.to_string();
-        log::debug!("{}", string);
+        log::debug!("{}", self);
     }
 
     // Count how many pixels are on
@@ -89,3 +239,59 @@ impl Screen {
         count
     }
 }
+
+/// Convert pixels to a string for easy display, starting on a new line.
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\n{}", self.render('#', '.'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_all_unscrambles() {
+        let script = "rect 3x2\nrotate column x=1 by 1\nrect 2x1\nrotate row y=0 by 4\nrect 1x3";
+        let mut screen = Screen::new(7, 3);
+        screen.run_script(script).unwrap();
+        let end = screen.to_string();
+        screen.undo_all();
+        assert_eq!(screen.count_set_pixels(), 0);
+        screen.run_script(script).unwrap();
+        assert_eq!(screen.to_string(), end);
+    }
+
+    #[test]
+    fn undo_puts_back_covered_pixels() {
+        let mut screen = Screen::new(4, 2);
+        screen.run_script("rect 1x1\nrotate row y=0 by 2").unwrap();
+        let before = screen.render('#', '.');
+        assert_eq!(before, "..#.\n....\n");
+
+        screen
+            .run_script("rect 3x2\nrotate column x=0 by 1")
+            .unwrap();
+        assert_eq!(screen.render('#', '.'), "###.\n###.\n");
+        let rect = ScreenCommand::Rect {
+            width: 3,
+            height: 2,
+        };
+        assert!(screen.undo().is_some());
+        assert_eq!(screen.undo(), Some(rect));
+        assert_eq!(screen.render('#', '.'), before);
+
+        screen.undo_all();
+        assert_eq!(screen.undo(), None);
+        assert_eq!(screen.count_set_pixels(), 0);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(ScreenCommand::parse("rect 3by2").is_err());
+        assert!(ScreenCommand::parse("rotate row x=1 by 2").is_err());
+        assert!(ScreenCommand::parse("flip row y=1").is_err());
+        assert!(Screen::new(2, 2).run_script("rect 3x1").is_err());
+    }
+}
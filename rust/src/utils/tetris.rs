@@ -1,29 +1,46 @@
-use std::vec;
+use anyhow::{ensure, Result};
 use strum_macros::EnumIter;
 
-use crate::utils::board::*;
+use crate::utils::board::Direction;
+use crate::utils::cycle::*;
 
-/// A game of tetris. It is played on a grid with a couple of shape types.
-/// Moves are down, left, right. Rotation is not supported.
-/// A collison will prevent shape from moving.
-/// The board grows infinitely tall.
-/// Shapes start a configurable number of spaces above the highest current shape.
-/// Shapes start 2 spaces from the left.
+/// A game of tetris. It is played on a grid with any shapes, from a library
+/// of shape types or made from ASCII art.
+/// Moves are down, left, right, and shapes can rotate with wall kicks.
+/// A collison will prevent shape from moving. A shape that cannot move down
+/// is locked into the tower, and full lines can be cleared.
+/// The board grows infinitely tall. Each row is stored as a bitmask, bit x is
+/// set when column x is filled, so boards can be at most 32 wide.
+/// Shapes start a configurable number of spaces above the highest current shape,
+/// and a configurable number of spaces from the left.
+#[derive(Debug, Clone)]
 pub struct Tetris {
-    /// The game board.
-    grid: Board<char>,
+    /// The locked rows, from the floor up.
+    rows: Vec<u32>,
 
-    /// The shapes currently on the grid.
-    shapes: Vec<Shape>,
+    /// The shape that is still falling.
+    falling: Option<Falling>,
+
+    /// How many shapes have been added.
+    shape_count: usize,
 
     /// The width of the board.
     width: i32,
 
+    /// Columns between the left wall and a new shape.
+    spawn_left: i32,
+
     /// Rows of air between top of tower and new shape
     new_shape_air_gap: i32,
+
+    /// Remove full rows when a shape locks.
+    clear_lines: bool,
+
+    /// How many full rows have been removed.
+    rows_cleared: u64,
 }
 
-/// The supported Tetris shape types.
+/// The library of Tetris shape types.
 #[derive(Debug, Clone, Copy, EnumIter, PartialEq)]
 pub enum Shapes {
     // ####
@@ -50,241 +67,529 @@ pub enum Shapes {
     Square,
 }
 
+impl Shapes {
+    /// The ASCII art for this shape type.
+    pub fn art(&self) -> &'static str {
+        match self {
+            Shapes::Flat => "####",
+            Shapes::Plus => ".#.\n###\n.#.",
+            Shapes::L => "..#\n..#\n###",
+            Shapes::Tall => "#\n#\n#\n#",
+            Shapes::Square => "##\n##",
+        }
+    }
+
+    /// Build the shape for this shape type.
+    pub fn shape(&self) -> Shape {
+        Shape::from_ascii(self.art())
+    }
+}
+
+/// A shape, as a bitmask for each row from the bottom up. Bit 0 is the left
+/// most column of the shape.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shape {
+    rows: Vec<u32>,
+    width: i32,
+}
+
+impl Shape {
+    /// Create a shape from ASCII art, where '#' is a block and anything else
+    /// is empty. The top line of the art is the top of the shape.
+    pub fn from_ascii(art: &str) -> Shape {
+        let lines: Vec<&str> = art.lines().filter(|l| !l.trim().is_empty()).collect();
+        let rows: Vec<u32> = lines
+            .iter()
+            .rev()
+            .map(|line| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .fold(0, |mask, (x, _)| mask | 1 << x)
+            })
+            .collect();
+        Shape::from_rows(rows)
+    }
+
+    /// Create a shape from row bitmasks, from the bottom up. Empty rows and
+    /// columns around the shape are removed.
+    fn from_rows(rows: Vec<u32>) -> Shape {
+        let mut rows: Vec<u32> = rows.into_iter().skip_while(|r| *r == 0).collect();
+        while rows.last() == Some(&0) {
+            rows.pop();
+        }
+        assert!(!rows.is_empty(), "A shape needs at least one block");
+
+        let all = rows.iter().fold(0, |a, r| a | r);
+        let shift = all.trailing_zeros();
+        let width = (32 - all.leading_zeros() - shift) as i32;
+        let rows = rows.iter().map(|r| r >> shift).collect();
+        Shape { rows, width }
+    }
+
+    /// How many columns wide the shape is.
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    /// How many rows tall the shape is.
+    pub fn height(&self) -> i32 {
+        self.rows.len() as i32
+    }
+
+    /// Is there a block at this column and row, from the bottom left?
+    fn is_block(&self, x: i32, y: i32) -> bool {
+        self.rows[y as usize] & 1 << x != 0
+    }
+
+    /// Get this shape rotated a quarter turn clockwise.
+    pub fn rotate_clockwise(&self) -> Shape {
+        // Column x from the left becomes row x from the top
+        let height = self.height();
+        let rows = (0..self.width)
+            .rev()
+            .map(|x| {
+                (0..height)
+                    .filter(|y| self.is_block(x, *y))
+                    .fold(0, |mask, y| mask | 1 << y)
+            })
+            .collect();
+        Shape::from_rows(rows)
+    }
+
+    /// Get this shape rotated a quarter turn counter clockwise.
+    pub fn rotate_counter_clockwise(&self) -> Shape {
+        self.rotate_clockwise()
+            .rotate_clockwise()
+            .rotate_clockwise()
+    }
+}
+
+/// A shape that is still falling, with the bottom left corner of the shape
+/// at x, y. Row 0 is the floor.
+#[derive(Debug, Clone)]
+struct Falling {
+    id: ShapeId,
+    shape: Shape,
+    x: i32,
+    y: i32,
+}
+
 /// A unique ID for a shape on the board.
 pub type ShapeId = usize;
 
+/// The state of a game, used to find where a game starts to repeat. The
+/// surface profile is how far below the top of the tower the highest block in
+/// each column is. With the same surface, next shape and next jet, the game
+/// plays out the same way from here.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub profile: Vec<u32>,
+    pub shape: usize,
+    pub jet: usize,
+}
+
+/// A copy of a game between rounds, used to find where the game repeats with
+/// Brent's algorithm. Rounds are the same when their fingerprints are.
+#[derive(Debug, Clone)]
+struct Round {
+    game: Tetris,
+    round: usize,
+    jet: usize,
+    fingerprint: Fingerprint,
+}
+
+impl PartialEq for Round {
+    fn eq(&self, other: &Round) -> bool {
+        self.fingerprint == other.fingerprint
+    }
+}
+
+/// Offsets to try when a rotation is blocked, as x, y.
+const WALL_KICKS: [(i32, i32); 6] = [(0, 0), (-1, 0), (1, 0), (-2, 0), (2, 0), (0, 1)];
+
 impl Tetris {
-    /// Create a new tetris board with width 7 and air cap for new shapes 3.
+    /// Create a new tetris board with width 7, new shapes 2 from the left and
+    /// air cap for new shapes 3.
     pub fn new() -> Tetris {
-        let mut me = Tetris {
-            grid: Board::new(),
-            shapes: vec![],
-            width: 7,
-            new_shape_air_gap: 3,
-        };
-        me.grid.push_row(vec!['.'; me.width as usize]);
-        me.grid.set_players_as_walls();
-        me
+        Tetris::with_size(7, 2, 3).expect("The default size is valid")
+    }
+
+    /// Create a new tetris board with any width up to 32, how far from the
+    /// left new shapes start, and the air gap above the tower for new shapes.
+    /// The start and air gap can not be negative.
+    pub fn with_size(width: i32, spawn_left: i32, new_shape_air_gap: i32) -> Result<Tetris> {
+        ensure!(
+            (1..=32).contains(&width),
+            "Width must be 1 to 32, not {width}"
+        );
+        ensure!(
+            spawn_left >= 0,
+            "Spawn left can not be negative, not {spawn_left}"
+        );
+        ensure!(
+            new_shape_air_gap >= 0,
+            "Air gap can not be negative, not {new_shape_air_gap}"
+        );
+        Ok(Tetris {
+            rows: vec![],
+            falling: None,
+            shape_count: 0,
+            width,
+            spawn_left,
+            new_shape_air_gap,
+            clear_lines: false,
+            rows_cleared: 0,
+        })
+    }
+
+    /// Remove full rows when a shape locks, like real tetris.
+    #[allow(dead_code)]
+    pub fn set_clear_lines(&mut self, clear_lines: bool) {
+        self.clear_lines = clear_lines;
+    }
+
+    /// How many full rows have been removed.
+    #[allow(dead_code)]
+    pub fn rows_cleared(&self) -> u64 {
+        self.rows_cleared
     }
 
-    /// Print what the game looks like.
+    /// A row with every column filled.
+    fn full_row(&self) -> u32 {
+        u32::MAX >> (32 - self.width)
+    }
+
+    /// Print what the game looks like. The falling shape is shown as '@'.
     #[allow(dead_code)]
-    pub fn print(&mut self) {
-        self.grid.print_board_with_players_pretty();
-        println!("");
+    pub fn print(&self) {
+        let top = match &self.falling {
+            Some(f) => std::cmp::max(self.rows.len() as i32, f.y + f.shape.height()),
+            None => self.rows.len() as i32,
+        };
+        for y in (0..top).rev() {
+            let row: String = (0..self.width)
+                .map(|x| match self.is_falling_block(x, y) {
+                    true => '@',
+                    false if self.row(y) & 1 << x != 0 => '#',
+                    false => '.',
+                })
+                .collect();
+            println!("|{row}|");
+        }
+        println!("+{}+", "-".repeat(self.width as usize));
+    }
+
+    /// Is the falling shape at this location?
+    fn is_falling_block(&self, x: i32, y: i32) -> bool {
+        self.falling.as_ref().is_some_and(|f| {
+            let (x, y) = (x - f.x, y - f.y);
+            x >= 0
+                && y >= 0
+                && x < f.shape.width()
+                && y < f.shape.height()
+                && f.shape.is_block(x, y)
+        })
+    }
+
+    /// Get a locked row, rows above the tower are empty.
+    fn row(&self, y: i32) -> u32 {
+        self.rows.get(y as usize).copied().unwrap_or(0)
     }
 
     /// Add a new shape to the board at the default start location.
+    #[allow(dead_code)]
     pub fn add_shape(&mut self, shape_type: Shapes) -> ShapeId {
-        let shape = Shape::new(shape_type, &mut self.grid, self.new_shape_air_gap);
-        self.shapes.push(shape);
-        self.shapes.len() - 1
+        self.add_custom_shape(&shape_type.shape())
+    }
+
+    /// Add any shape to the board at the default start location. A shape that
+    /// is still falling is locked where it is.
+    pub fn add_custom_shape(&mut self, shape: &Shape) -> ShapeId {
+        self.lock();
+        assert!(shape.width() <= self.width, "Shape is wider than the board");
+        let id = self.shape_count;
+        self.shape_count += 1;
+        let x = std::cmp::min(self.spawn_left, self.width - shape.width());
+        let y = self.rows.len() as i32 + self.new_shape_air_gap;
+        log::debug!("Adding shape {id} at {},{}", x, y);
+        self.falling = Some(Falling {
+            id,
+            shape: shape.clone(),
+            x,
+            y,
+        });
+        id
+    }
+
+    /// Can this shape be at this location without hitting a wall or block?
+    fn fits(&self, shape: &Shape, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x + shape.width() > self.width {
+            return false;
+        }
+        shape
+            .rows
+            .iter()
+            .enumerate()
+            .all(|(i, row)| self.row(y + i as i32) & row << x == 0)
+    }
+
+    /// Get the falling shape, if it has this id.
+    fn take_falling(&mut self, id: ShapeId) -> Option<Falling> {
+        match &self.falling {
+            Some(f) if f.id == id => self.falling.take(),
+            _ => None,
+        }
     }
 
     /// Move the shape, return true if moved or false if it could not move.
+    /// A shape that cannot move down is locked into the tower.
     pub fn move_shape(&mut self, id: ShapeId, direction: Direction) -> bool {
-        self.shapes[id].move_shape(direction, &mut self.grid)
+        let Some(mut falling) = self.take_falling(id) else {
+            return false;
+        };
+        let (x, y) = match direction {
+            Direction::Left => (falling.x - 1, falling.y),
+            Direction::Right => (falling.x + 1, falling.y),
+            Direction::Down => (falling.x, falling.y - 1),
+            _ => panic!("Unexpected move direction"),
+        };
+
+        let moved = self.fits(&falling.shape, x, y);
+        if moved {
+            (falling.x, falling.y) = (x, y);
+        } else {
+            log::debug!("Cannot move {id} {:?}", direction);
+        }
+        self.falling = Some(falling);
+        if !moved && direction == Direction::Down {
+            self.lock();
+        }
+        moved
+    }
+
+    /// Rotate the shape a quarter turn, return true if it rotated. If the
+    /// rotated shape is blocked, try nudging it left, right or up.
+    #[allow(dead_code)]
+    pub fn rotate_shape(&mut self, id: ShapeId, clockwise: bool) -> bool {
+        let Some(mut falling) = self.take_falling(id) else {
+            return false;
+        };
+        let shape = match clockwise {
+            true => falling.shape.rotate_clockwise(),
+            false => falling.shape.rotate_counter_clockwise(),
+        };
+
+        let kick = WALL_KICKS
+            .iter()
+            .find(|(dx, dy)| self.fits(&shape, falling.x + dx, falling.y + dy));
+        if let Some((dx, dy)) = kick {
+            falling.shape = shape;
+            falling.x += dx;
+            falling.y += dy;
+        }
+        self.falling = Some(falling);
+        kick.is_some()
+    }
+
+    /// Lock the falling shape into the tower, and clear full rows.
+    fn lock(&mut self) {
+        let Some(falling) = self.falling.take() else {
+            return;
+        };
+        for (i, row) in falling.shape.rows.iter().enumerate() {
+            let y = (falling.y + i as i32) as usize;
+            if y >= self.rows.len() {
+                self.rows.resize(y + 1, 0);
+            }
+            self.rows[y] |= row << falling.x;
+        }
+
+        if self.clear_lines {
+            let full = self.full_row();
+            let before = self.rows.len();
+            self.rows.retain(|r| *r != full);
+            self.rows_cleared += (before - self.rows.len()) as u64;
+        }
     }
 
     /// Get how tall the shape tower is.
     pub fn stack_height(&self) -> u32 {
-        let min_player_y = self.grid.player_minimum_height();
-        let stack_height = (self.grid.height() - min_player_y) as u32;
-        stack_height
+        let falling_top = self.falling.as_ref().map_or(0, |f| f.y + f.shape.height());
+        std::cmp::max(self.rows.len() as i32, falling_top) as u32
+    }
+
+    /// Get how tall the tower would be if no rows were cleared.
+    pub fn total_height(&self) -> u64 {
+        self.stack_height() as u64 + self.rows_cleared
     }
 
     /// Does the top most line have a full row of blocks.
     #[allow(dead_code)]
     pub fn is_top_line_full(&self) -> bool {
-        let min_player_y = self.grid.player_minimum_height();
-        for x in 0..self.width {
-            let value = self.grid.value_at(BoardPoint { x, y: min_player_y });
-            if value != '#' {
-                return false;
-            }
-        }
+        self.rows.last() == Some(&self.full_row())
+    }
 
-        true
+    /// Get how far below the top of the tower the highest block in each
+    /// column is. An empty column is the full height of the tower.
+    pub fn surface_profile(&self) -> Vec<u32> {
+        let height = self.rows.len();
+        (0..self.width)
+            .map(|x| {
+                let depth = self.rows.iter().rev().position(|r| r & 1 << x != 0);
+                depth.unwrap_or(height) as u32
+            })
+            .collect()
     }
 
-    /// Output a string version of the specified rows.
-    pub fn rows_as_string(&self, rows: u32) -> String {
-        let min_player_y = self.grid.player_minimum_height();
-        let mut output = String::new();
-        for x in 0..self.width {
-            for y in min_player_y..min_player_y + rows as i32 {
-                output.push(self.grid.value_at_with_player(BoardPoint { x, y }));
-            }
+    /// Get the state of the game, with the index of the next shape and jet.
+    pub fn fingerprint(&self, shape: usize, jet: usize) -> Fingerprint {
+        Fingerprint {
+            profile: self.surface_profile(),
+            shape,
+            jet,
         }
-        output
     }
-}
 
-/// A shape consists of a couple of players on the grid.
-struct Shape {
-    shape_type: Shapes,
-    players: Vec<PlayerId>,
-}
+    /// Drop a shape from the start location until it locks. Before each fall
+    /// the next jet pushes the shape, jets wrap around to the start.
+    pub fn drop_shape(&mut self, shape: &Shape, jets: &[Direction], jet: &mut usize) {
+        let id = self.add_custom_shape(shape);
+        loop {
+            // A push that is blocked fails silently
+            self.move_shape(id, jets[*jet]);
+            *jet = (*jet + 1) % jets.len();
 
-/// A private helper struct to manage shape logic. Create and move shapes.
-impl Shape {
-    /// Create a new shape and add it to the grid
-    fn new(shape_type: Shapes, grid: &mut Board<char>, new_shape_air_gap: i32) -> Shape {
-        let locations = Shape::shape_locations(shape_type);
-        let rows_for_shape = match shape_type {
-            Shapes::Flat => 1,
-            Shapes::Plus => 3,
-            Shapes::L => 3,
-            Shapes::Tall => 4,
-            Shapes::Square => 2,
-        };
+            if !self.move_shape(id, Direction::Down) {
+                break;
+            }
+        }
+    }
 
-        // Add new rows to the grid based of the current highest block, the
-        // rows this shape needs, and configurable rows between new block and
-        // current highest block
-        let min_player_y = if grid.height() > 1 {
-            grid.player_minimum_height()
-        } else {
-            // Special case for first shape where there are no other shapes
-            1
+    /// Find where the game repeats, taking shapes and jets in turn from here.
+    /// Copies of the game are played with Brent's algorithm, so this game is
+    /// not changed. The game must repeat, or this never returns.
+    pub fn find_cycle(&self, shapes: &[Shape], jets: &[Direction]) -> Cycle {
+        let start = Round {
+            game: self.clone(),
+            round: 0,
+            jet: 0,
+            fingerprint: self.fingerprint(0, 0),
         };
-        let air_rows_to_add = new_shape_air_gap - min_player_y;
-        let new_rows = rows_for_shape + air_rows_to_add;
-        let width = grid.width() as usize;
-        let mut y_offset = 0;
-        log::debug!("Adding {} rows of air", new_rows);
-        if new_rows > 0 {
-            for _ in 0..new_rows {
-                grid.push_front_row(vec!['.'; width]);
+        brent(start, |round| {
+            let mut next = round.clone();
+            let shape = &shapes[next.round % shapes.len()];
+            next.game.drop_shape(shape, jets, &mut next.jet);
+            next.round += 1;
+            next.fingerprint = next.game.fingerprint(next.round % shapes.len(), next.jet);
+            next
+        })
+    }
+
+    /// Get the tower height after dropping any number of shapes, taking shapes
+    /// and jets in turn. Cleared rows still count towards the height. Once the game repeats, whole cycles are skipped, so
+    /// huge counts are fast. The board is only played until one full cycle is
+    /// done. The game must repeat, see find_cycle.
+    pub fn simulate(&mut self, shapes: &[Shape], jets: &[Direction], count: u64) -> u64 {
+        let cycle = self.find_cycle(shapes, jets);
+        log::debug!("Game repeats. {cycle:?}");
+
+        let mut jet = 0;
+        let mut heights: Vec<i64> = vec![];
+        for round in 0.. {
+            let height = self.total_height();
+            heights.push(height as i64);
+            if round as u64 == count {
+                return height;
+            }
+            if round == cycle.start + cycle.length {
+                return cycle.extrapolate(&heights, count) as u64;
             }
-        } else {
-            // Remove rows! Instead of modifying the grid, just place the new
-            // shape a little lower
-            log::debug!("Special case where we need to 'remove' rows");
-            y_offset = new_rows.abs();
-        }
 
-        // Add the new shape to the board
-        log::debug!("Adding {:?} at {},{}", shape_type, 2, y_offset);
-        let mut players = vec![];
-        for location in locations {
-            let player = grid.add_player(
-                BoardPoint {
-                    x: location.x + 2,
-                    y: location.y + y_offset,
-                },
-                '#',
-            );
-            players.push(player);
+            self.drop_shape(&shapes[round % shapes.len()], jets, &mut jet);
         }
+        unreachable!()
+    }
+}
 
-        Shape {
-            shape_type,
-            players,
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    fn parse_jets(text: &str) -> Vec<Direction> {
+        text.chars()
+            .map(|c| match c {
+                '<' => Direction::Left,
+                _ => Direction::Right,
+            })
+            .collect()
     }
 
-    /// Return a vector of location points for a new shape
-    /// The top left of the shape area will be at 0,0
-    /// The locations are from left->right, top->bottom
-    fn shape_locations(shape_type: Shapes) -> Vec<BoardPoint> {
-        match shape_type {
-            Shapes::Flat => vec![
-                BoardPoint { x: 0, y: 0 },
-                BoardPoint { x: 1, y: 0 },
-                BoardPoint { x: 2, y: 0 },
-                BoardPoint { x: 3, y: 0 },
-            ],
-            Shapes::Plus => vec![
-                BoardPoint { x: 1, y: 0 },
-                BoardPoint { x: 0, y: 1 },
-                BoardPoint { x: 1, y: 1 },
-                BoardPoint { x: 2, y: 1 },
-                BoardPoint { x: 1, y: 2 },
-            ],
-            Shapes::L => vec![
-                BoardPoint { x: 2, y: 0 },
-                BoardPoint { x: 2, y: 1 },
-                BoardPoint { x: 0, y: 2 },
-                BoardPoint { x: 1, y: 2 },
-                BoardPoint { x: 2, y: 2 },
-            ],
-            Shapes::Tall => vec![
-                BoardPoint { x: 0, y: 0 },
-                BoardPoint { x: 0, y: 1 },
-                BoardPoint { x: 0, y: 2 },
-                BoardPoint { x: 0, y: 3 },
-            ],
-            Shapes::Square => vec![
-                BoardPoint { x: 0, y: 0 },
-                BoardPoint { x: 1, y: 0 },
-                BoardPoint { x: 0, y: 1 },
-                BoardPoint { x: 1, y: 1 },
-            ],
+    /// Drop every shape one at a time, without skipping cycles.
+    fn play(mut tetris: Tetris, shapes: &[Shape], jets: &[Direction], count: usize) -> u64 {
+        let mut jet = 0;
+        for round in 0..count {
+            tetris.drop_shape(&shapes[round % shapes.len()], jets, &mut jet);
         }
+        tetris.total_height()
     }
 
-    /// Move the shape the desired direction, returning true if the move was successfull
-    /// On failure to move, none of the shape was actually moved
-    fn move_shape(&mut self, direction: Direction, grid: &mut Board<char>) -> bool {
-        // Find the order to move parts of the shape in so they do not collide with each other
-        let indexes = match direction {
-            Direction::Left => self.move_left_indexes(),
-            Direction::Right => self.move_right_indexes(),
-            Direction::Down => self.move_down_indexes(),
-            _ => panic!("Unexpected move direction"),
-        };
-        assert_eq!(indexes.len(), self.players.len());
-
-        // Move the players one at a time
-        let mut players_moved = vec![];
-        for i in indexes.iter() {
-            let player = self.players[*i];
-            if grid.step_player(self.players[*i], direction).is_some() {
-                players_moved.push(player);
-            } else {
-                // Cannot move! Need to unmove any moved parts in reverse order
-                log::debug!("Cannot move {:?} {:?}", self.shape_type, direction);
-                let opposite_direction = Direction::opposite_direction(direction);
-                while players_moved.len() > 0 {
-                    log::debug!("Unmoving part of piece {:?}", opposite_direction);
-                    grid.step_player(players_moved.pop().unwrap(), opposite_direction);
-                }
-                return false;
-            }
+    #[test]
+    fn with_size_checks() {
+        assert!(Tetris::with_size(0, 2, 3).is_err());
+        assert!(Tetris::with_size(33, 2, 3).is_err());
+        assert!(Tetris::with_size(7, -1, 3).is_err());
+        assert!(Tetris::with_size(7, 2, -3).is_err());
+        assert!(Tetris::with_size(32, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn simulate_matches_playing() {
+        let shapes: Vec<Shape> = Shapes::iter().map(|s| s.shape()).collect();
+        let jets = parse_jets(">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>");
+        for count in [0, 1, 10, 2022, 3000] {
+            let expected = play(Tetris::new(), &shapes, &jets, count);
+            assert_eq!(
+                Tetris::new().simulate(&shapes, &jets, count as u64),
+                expected
+            );
         }
-        log::debug!("Moved {:?} {:?}", self.shape_type, direction);
-        true
-    }
-
-    /// Move left helper
-    fn move_left_indexes(&mut self) -> Vec<usize> {
-        let indexes: Vec<usize> = match self.shape_type {
-            Shapes::Flat => vec![0, 1, 2, 3],
-            Shapes::Plus => vec![1, 0, 2, 4, 3],
-            Shapes::L => vec![2, 3, 4, 0, 1],
-            Shapes::Tall => vec![0, 1, 2, 3],
-            Shapes::Square => vec![0, 2, 1, 3],
-        };
-        indexes
     }
 
-    /// Move right helper (the reverse of move left)
-    fn move_right_indexes(&mut self) -> Vec<usize> {
-        let mut indexes = self.move_left_indexes();
-        indexes.reverse();
-        indexes
+    #[test]
+    fn simulate_counts_cleared_rows() {
+        // Flat shapes fill a 4 wide board one row at a time
+        let shapes = [Shapes::Flat.shape()];
+        let jets = parse_jets("<>");
+        let mut tetris = Tetris::with_size(4, 0, 3).unwrap();
+        tetris.set_clear_lines(true);
+        assert_eq!(tetris.simulate(&shapes, &jets, 1000), 1000);
+        assert!(tetris.stack_height() <= 1);
+
+        // Other shapes clear some rows, playing must give the same height
+        let shapes: Vec<Shape> = Shapes::iter().map(|s| s.shape()).collect();
+        let jets = parse_jets("<<>><>>><<<>");
+        let mut clearing = Tetris::with_size(5, 1, 3).unwrap();
+        clearing.set_clear_lines(true);
+        let expected = play(clearing.clone(), &shapes, &jets, 4000);
+        assert_eq!(clearing.simulate(&shapes, &jets, 4000), expected);
+        assert!(clearing.rows_cleared() > 0);
     }
 
-    /// Move down helper
-    fn move_down_indexes(&mut self) -> Vec<usize> {
-        let indexes: Vec<usize> = match self.shape_type {
-            Shapes::Flat => vec![0, 1, 2, 3],
-            Shapes::Plus => vec![4, 3, 2, 1, 0],
-            Shapes::L => vec![4, 3, 2, 1, 0],
-            Shapes::Tall => vec![3, 2, 1, 0],
-            Shapes::Square => vec![3, 2, 1, 0],
-        };
-        indexes
+    #[test]
+    fn rotate_with_wall_kick() {
+        let mut tetris = Tetris::with_size(4, 0, 3).unwrap();
+        let id = tetris.add_shape(Shapes::Tall);
+        assert!(tetris.rotate_shape(id, true));
+        assert!(!tetris.move_shape(id, Direction::Left));
+        assert!(!tetris.move_shape(id, Direction::Right));
+        assert_eq!(
+            Shapes::L
+                .shape()
+                .rotate_clockwise()
+                .rotate_counter_clockwise(),
+            Shapes::L.shape()
+        );
     }
 }
@@ -0,0 +1,201 @@
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+use crate::utils::board::{BoardPoint, Direction};
+
+/// Which way something is facing, in degrees clockwise from up.
+/// Headings are always a multiple of 45 degrees, so they match a Direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Heading {
+    degrees: i32,
+}
+
+impl Heading {
+    /// Create a heading from degrees clockwise from up.
+    #[allow(dead_code)]
+    pub fn new(degrees: i32) -> Heading {
+        Heading::default().rotate(degrees)
+    }
+
+    /// The heading in degrees clockwise from up, from 0 to 315.
+    #[allow(dead_code)]
+    pub fn degrees(&self) -> i32 {
+        self.degrees
+    }
+
+    /// Rotate clockwise by some degrees, a multiple of 45. Negative degrees
+    /// are counter clockwise.
+    pub fn rotate(&self, degrees: i32) -> Heading {
+        assert!(degrees % 45 == 0, "Can only rotate by 45 degrees");
+        Heading {
+            degrees: (self.degrees + degrees).rem_euclid(360),
+        }
+    }
+
+    /// The direction of this heading.
+    pub fn direction(&self) -> Direction {
+        Direction::Up.rotate(self.degrees)
+    }
+}
+
+impl From<Direction> for Heading {
+    fn from(direction: Direction) -> Self {
+        let degrees = (0..360)
+            .step_by(45)
+            .find(|d| Direction::Up.rotate(*d) == direction)
+            .unwrap();
+        Heading { degrees }
+    }
+}
+
+/// Walk around an endless grid with simple commands. Every location walked
+/// through is remembered, so the path and the first location that is
+/// visited twice can be found later.
+/// Commands look like L3, R5 or F10:
+/// L and R turn left or right, then walk forward the number of steps.
+/// F walks forward without turning.
+/// L and R without a number only turn.
+pub struct Turtle {
+    location: BoardPoint,
+    heading: Heading,
+
+    /// Every location, in order, the start included
+    visited: Vec<BoardPoint>,
+
+    /// Every location, for fast look up
+    seen: HashSet<BoardPoint>,
+
+    /// The first location that was visited twice
+    first_revisit: Option<BoardPoint>,
+}
+
+impl Turtle {
+    /// Create a turtle at this location, facing this way.
+    pub fn new(location: BoardPoint, heading: Heading) -> Turtle {
+        Turtle {
+            location,
+            heading,
+            visited: vec![location],
+            seen: HashSet::from([location]),
+            first_revisit: None,
+        }
+    }
+
+    /// Where is the turtle now?
+    pub fn location(&self) -> BoardPoint {
+        self.location
+    }
+
+    /// Which way is the turtle facing?
+    #[allow(dead_code)]
+    pub fn heading(&self) -> Heading {
+        self.heading
+    }
+
+    /// Every location walked through, in order, the start included.
+    #[allow(dead_code)]
+    pub fn visited(&self) -> &Vec<BoardPoint> {
+        &self.visited
+    }
+
+    /// The first location that was visited a second time.
+    pub fn first_revisit(&self) -> Option<BoardPoint> {
+        self.first_revisit
+    }
+
+    /// Turn clockwise by some degrees, a multiple of 45.
+    pub fn turn(&mut self, degrees: i32) {
+        self.heading = self.heading.rotate(degrees);
+    }
+
+    /// Walk forward one step at a time, remembering each location.
+    pub fn forward(&mut self, steps: i32) {
+        let direction = self.heading.direction();
+        self.walk(direction, steps);
+    }
+
+    /// Walk in a direction without changing the heading.
+    pub fn walk(&mut self, direction: Direction, steps: i32) {
+        for _ in 0..steps {
+            self.location += direction.offset();
+            self.visited.push(self.location);
+            if !self.seen.insert(self.location) && self.first_revisit.is_none() {
+                log::debug!("First revisit at {:?}", self.location);
+                self.first_revisit = Some(self.location);
+            }
+        }
+    }
+
+    /// Run one command, like L3, R5 or F10.
+    pub fn execute(&mut self, command: &str) -> Result<()> {
+        let mut chars = command.chars();
+        let Some(action) = chars.next() else {
+            bail!("Empty command");
+        };
+        let steps: i32 = match chars.as_str() {
+            "" => 0,
+            s => s.parse()?,
+        };
+        match action {
+            'L' => self.turn(-90),
+            'R' => self.turn(90),
+            'F' => (),
+            _ => bail!("Unknown command {command}"),
+        }
+        self.forward(steps);
+        Ok(())
+    }
+
+    /// Run a list of commands split by commas or white space.
+    pub fn run(&mut self, commands: &str) -> Result<()> {
+        for command in commands
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|c| !c.is_empty())
+        {
+            self.execute(command)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_splits_on_any_white_space() {
+        let mut turtle = Turtle::new(BoardPoint::zero(), Heading::from(Direction::Up));
+        turtle.run("R8,\tR4,\r\nR4, R8\r\n").unwrap();
+        assert_eq!(turtle.location(), BoardPoint::new(4, -4));
+        assert_eq!(turtle.first_revisit(), Some(BoardPoint::new(4, 0)));
+        assert_eq!(turtle.heading().direction(), Direction::Up);
+    }
+
+    #[test]
+    fn bad_commands() {
+        let mut turtle = Turtle::new(BoardPoint::zero(), Heading::new(0));
+        assert!(turtle.run("R2, X3").is_err());
+        assert!(turtle.execute("Lx").is_err());
+    }
+
+    #[test]
+    fn turn_and_walk() {
+        let mut turtle = Turtle::new(BoardPoint::zero(), Heading::new(-45));
+        assert_eq!(turtle.heading().degrees(), 315);
+        assert_eq!(turtle.heading().direction(), Direction::UpLeft);
+        turtle.run("F2 R L").unwrap();
+        turtle.turn(-45);
+        turtle.walk(Direction::Right, 1);
+        assert_eq!(turtle.heading(), Heading::from(Direction::Left));
+        assert_eq!(
+            turtle.visited(),
+            &[
+                BoardPoint::new(0, 0),
+                BoardPoint::new(-1, -1),
+                BoardPoint::new(-2, -2),
+                BoardPoint::new(-1, -2)
+            ]
+        );
+        assert_eq!(turtle.first_revisit(), None);
+    }
+}
@@ -1,8 +1,8 @@
-use rusttype::Point;
+use crate::utils::point::Point;
 use std::str::FromStr;
 
 /// A simple way to import and use Point type.
-pub type UtilsPoint = Point<i32>;
+pub type UtilsPoint = Point<2>;
 
 /// Tests if this character is in the string.
 pub fn char_in_string(c: &char, s: &String) -> bool {
@@ -49,9 +49,7 @@ where
 
 /// Get the taxi cab distance between two points.
 pub fn manhattan_distance(p1: UtilsPoint, p2: UtilsPoint) -> i32 {
-    let x = (p1.x - p2.x).abs();
-    let y = (p1.y - p2.y).abs();
-    x + y
+    p1.manhattan(&p2)
 }
 
 /// An enum of math operators.
@@ -67,10 +67,7 @@ impl Puzzle for Day03 {
     fn solve_part1(&mut self) -> Result<String> {
         // How many unique houses does santa visit?
         let player_id = self.board.add_player(
-            BoardPoint {
-                x: (self.board.width() / 2) as i32,
-                y: (self.board.height() / 2) as i32,
-            },
+            BoardPoint::new(self.board.width() / 2, self.board.height() / 2),
             0,
         );
 
@@ -95,17 +92,11 @@ impl Puzzle for Day03 {
         // How many unique houses do santa and robot santa visit?
         // They start at the same spot and alternate commands
         let santa = self.board.add_player(
-            BoardPoint {
-                x: (self.board.width() / 2) as i32,
-                y: (self.board.height() / 2) as i32,
-            },
+            BoardPoint::new(self.board.width() / 2, self.board.height() / 2),
             0,
         );
         let robot = self.board.add_player(
-            BoardPoint {
-                x: (self.board.width() / 2) as i32,
-                y: (self.board.height() / 2) as i32,
-            },
+            BoardPoint::new(self.board.width() / 2, self.board.height() / 2),
             0,
         );
 
@@ -55,14 +55,8 @@ impl Puzzle for Day06 {
             // Get start and end coordinates for area that command applies to.
             // These coordinates are inclusive.
             let vals: Vec<i32> = find_vals(line);
-            let start = UtilsPoint {
-                x: vals[0],
-                y: vals[1],
-            };
-            let end = UtilsPoint {
-                x: vals[2],
-                y: vals[3],
-            };
+            let start = UtilsPoint::new(vals[0], vals[1]);
+            let end = UtilsPoint::new(vals[2], vals[3]);
             day.instructions.push(Instruction {
                 command,
                 start,
@@ -71,8 +65,8 @@ impl Puzzle for Day06 {
         }
 
         for instruction in day.instructions.iter() {
-            for x in instruction.start.x..=instruction.end.x {
-                for y in instruction.start.y..=instruction.end.y {
+            for x in instruction.start.x()..=instruction.end.x() {
+                for y in instruction.start.y()..=instruction.end.y() {
                     // Do work for part 1, turning lights on and off
                     let x_ = x as usize;
                     let y_ = y as usize;
@@ -14,53 +14,40 @@ pub struct Day13 {
     people: HashMap<String, HashMap<String, i32>>,
 }
 
+/// Score a full seating. Each person is next to the person on either side,
+/// and the last person is next to the first person.
+fn score_seating(day: &Day13, seated: &[&String]) -> i32 {
+    let len = seated.len();
+    (0..len)
+        .map(|i| (seated[i], seated[(i + 1) % len]))
+        .map(|(a, b)| day.people[a][b] + day.people[b][a])
+        .sum()
+}
+
 /// Try all permuations of seating and return the best overall score
 fn find_best_seating(day: &Day13) -> i32 {
-    struct Work<'a> {
-        person: &'a String,
-        seated: Vec<&'a String>,
-        score: i32,
-    }
-
     // Start with Alice
     let first_person = "Alice".to_string();
-    let mut jobs = vec![Work {
-        person: &first_person,
-        seated: vec![&first_person],
-        score: 0,
-    }];
+    let mut jobs = vec![vec![&first_person]];
     let mut high_score = i32::MIN;
 
-    while jobs.len() > 0 {
-        let job = jobs.pop().unwrap();
-
+    while let Some(seated) = jobs.pop() {
         let mut started_work = false;
         for person in day.people.keys() {
             // Tryout seating this person next to anyone who is not yet seated
-            if job.seated.contains(&person) {
+            if seated.contains(&person) {
                 continue;
             }
 
-            // Seat these two together, modify score
-            let score = job.score + day.people[job.person][person] + day.people[person][job.person];
-            let mut seated = job.seated.clone();
+            let mut seated = seated.clone();
             seated.push(person);
-            jobs.push(Work {
-                person,
-                seated,
-                score,
-            });
+            jobs.push(seated);
             started_work = true;
         }
 
         if !started_work {
-            // All done, need to get score for last person seated next to first person
-            let score = job.score
-                + day.people[job.person][&first_person]
-                + day.people[&first_person][job.person];
-
-            // Save score if a new best score
-            high_score = std::cmp::max(high_score, score);
+            // All done, score the table and save score if a new best score
+            high_score = std::cmp::max(high_score, score_seating(day, &seated));
         }
     }
 
@@ -4,110 +4,62 @@
 // Toggle lights on a 100x100 grid
 
 use anyhow::Result;
-use std::collections::VecDeque;
 
 use crate::puzzle::Puzzle;
+use crate::utils::automaton::*;
 use crate::utils::board::*;
 
 pub struct Day18 {
-    board: Board<char>,
+    lights: Automaton<char>,
 }
 
 /// Toggle the lights in the grid.
 /// A light which is on stays on when 2 or 3 neighbors are on, and turns off otherwise.
 /// A light which is off turns on if exactly 3 neighbors are on, and stays off otherwise.
-fn reset_lights(day: &mut Day18, corners_always_on: bool) {
-    // Store the next state of the lights
-    let mut next_values = VecDeque::new();
-
-    // Look at all the lights and calculate the next state
-    for y in 0..day.board.height() {
-        for x in 0..day.board.width() {
-            let point = BoardPoint { x, y };
-            let value = day.board.value_at(point);
-            let values = day.board.values_near_point(point);
-            let neighbors_on = values
-                .iter()
-                .fold(0, |a, v| if *v == '#' { a + 1 } else { a });
-            if value == '#' {
-                // Currently on, need 2 or 3 neighbors to stay on
-                if neighbors_on == 2 || neighbors_on == 3 {
-                    next_values.push_back('#');
-                } else {
-                    next_values.push_back('.');
-                }
-            } else {
-                // Currently off, need 3 neighbors on to turn on
-                if neighbors_on == 3 {
-                    next_values.push_back('#');
-                } else {
-                    next_values.push_back('.');
-                }
-            }
-        }
-    }
-
-    // Now set the updated lights value
-    for y in 0..day.board.height() {
-        for x in 0..day.board.width() {
-            let point = BoardPoint { x, y };
-            day.board.set_at(point, next_values.pop_front().unwrap());
-        }
-    }
-
-    if corners_always_on {
-        turn_on_corners(day);
+fn light_rule(value: char, neighbors: &[char]) -> char {
+    let neighbors_on = neighbors.iter().filter(|&&v| v == '#').count();
+    match (value, neighbors_on) {
+        ('#', 2) | (_, 3) => '#',
+        _ => '.',
     }
 }
 
 /// Force the corner lights to be on
 fn turn_on_corners(day: &mut Day18) {
-    let dim = day.board.width() - 1; // Width and height are the same
-    day.board.set_at(BoardPoint { x: 0, y: 0 }, '#');
-    day.board.set_at(BoardPoint { x: dim, y: 0 }, '#');
-    day.board.set_at(BoardPoint { x: 0, y: dim }, '#');
-    day.board.set_at(BoardPoint { x: dim, y: dim }, '#');
-}
-
-/// Count how many lights are turned on
-fn count_lights_on(day: &Day18) -> u32 {
-    let mut count = 0;
-    for y in 0..day.board.height() {
-        for x in 0..day.board.width() {
-            let point = BoardPoint { x, y };
-            if day.board.value_at(point) == '#' {
-                count += 1;
-            }
-        }
-    }
-    count
+    let dim = day.lights.board().width() - 1; // Width and height are the same
+    day.lights.fix_cell(BoardPoint::new(0, 0), '#');
+    day.lights.fix_cell(BoardPoint::new(dim, 0), '#');
+    day.lights.fix_cell(BoardPoint::new(0, dim), '#');
+    day.lights.fix_cell(BoardPoint::new(dim, dim), '#');
 }
 
 impl Puzzle for Day18 {
     #[allow(unused_variables)]
     fn from_input(input: &str) -> Result<Self> {
-        #[allow(unused_mut)]
-        let mut day = Day18 {
-            board: Board::new(),
-        };
-
+        let mut board = Board::new();
         for line in input.lines() {
             let chars: Vec<char> = line.chars().collect();
-            day.board.push_row(chars);
+            board.push_row(chars);
         }
 
+        let day = Day18 {
+            lights: Automaton::new(board, Neighborhood::Moore),
+        };
+
         Ok(day)
     }
 
     fn solve_part1(&mut self) -> Result<String> {
         // Toggle the lights 100 times
-        let steps = if self.board.width() > 50 { 100 } else { 4 };
-        for _ in 0..steps {
-            reset_lights(self, false);
-        }
+        let steps = if self.lights.board().width() > 50 {
+            100
+        } else {
+            4
+        };
+        self.lights.run(steps, light_rule);
 
         // Count how many lights are on
-        let count = count_lights_on(self);
+        let count = self.lights.count('#');
         Ok(count.to_string())
     }
 
@@ -123,13 +75,15 @@ impl Puzzle for Day18 {
         turn_on_corners(self);
 
         // Toggle the lights 100 times
-        let steps = if self.board.width() > 50 { 100 } else { 5 };
-        for _ in 0..steps {
-            reset_lights(self, true);
-        }
+        let steps = if self.lights.board().width() > 50 {
+            100
+        } else {
+            5
+        };
+        self.lights.run(steps, light_rule);
 
         // Count how many lights are on
-        let count = count_lights_on(self);
+        let count = self.lights.count('#');
         Ok(count.to_string())
     }
 
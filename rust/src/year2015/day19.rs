@@ -5,113 +5,18 @@
 // to make the molecule.
 
 use anyhow::Result;
-use std::cmp::Ordering;
-use std::collections::HashSet;
 
 use crate::puzzle::Puzzle;
 use crate::utils::molecule::*;
+use crate::utils::molecule_grammar::*;
 
 pub struct Day19 {
     /// The target molecule, made up of many atoms.
     molecule: Molecule,
 
     /// Replacements convert from one atom to multiple atoms (a molecule).
-    replacements: Vec<Replacement>,
-
-    /// Also have a string version of replacements for easy string matching.
-    replacement_strings: Vec<ReplacementString>,
-
-    /// Molecules can start with a couple of options.
-    starts: Vec<String>,
-}
-
-#[derive(Debug)]
-struct Replacement {
-    from: Atom,
-    to: Molecule,
-}
-
-struct ReplacementString {
-    from: String,
-    to: String,
-}
-
-/// Count how many distinct molecules can be created by doing a single
-/// replacement on the target molecule.
-fn count_replacements(day: &Day19) -> usize {
-    // Use hash set to prevent duplicates
-    let mut count: HashSet<String> = HashSet::new();
-
-    // Run each replacement once
-    for replacement in day.replacements.iter() {
-        let molecules = day.molecule.replace(&replacement.from, &replacement.to);
-
-        // A replacement could happen at multiple locations, resulting in multiple molecules
-        for molecule in molecules {
-            count.insert(molecule.to_string());
-        }
-    }
-
-    count.len()
-}
-
-/// Find the fewest number of replacements needed to go from a start molecule
-/// to the target molecule by using replacements.
-fn find_best_replacement_path(day: &Day19) -> usize {
-    // Instead of brute force path finding by starting form an initial molecule
-    // and trying to build the target molecule, go from the target molecule and
-    // shrink it to a start molecule.
-    let mut steps = 0;
-
-    // Start with the target and shrink it
-    let mut molecules = vec![day.molecule.to_string()];
-
-    loop {
-        let mut new_molecules = vec![];
-
-        // We keep state of multiple paths simultaneously, check each path at
-        // this step instance.
-        for molecule in molecules.iter() {
-            // Check if done
-            if day.starts.contains(&molecule) {
-                steps += 1; // Need to count the initial e -> molecule step
-                log::info!("Found path after {} steps", steps);
-                return steps;
-            }
-
-            // Find all new molecules by doing a single reverse replacement
-            for replacement in day.replacement_strings.iter() {
-                let matches: Vec<_> = molecule.match_indices(&replacement.to).collect();
-                for (matches_i, _) in matches {
-                    let mut new_molecule = molecule.clone();
-                    new_molecule.replace_range(
-                        matches_i..matches_i + replacement.to.len(),
-                        &replacement.from,
-                    );
-                    new_molecules.push(new_molecule)
-                }
-            }
-        }
-
-        // Keep only the best paths to prevent exponential growth. Aggressively
-        // trim down the size, so keep only the 100 shortest paths.
-        molecules = new_molecules;
-        molecules.sort_by(|a, b| {
-            if a.len() == b.len() {
-                Ordering::Equal
-            } else if a.len() > b.len() {
-                Ordering::Greater
-            } else {
-                Ordering::Less
-            }
-        });
-        molecules.dedup();
-        if molecules.len() > 100 {
-            molecules = molecules.split_at(100).0.to_vec();
-        }
-        log::debug!("Round {steps} and molecules {}", molecules.len());
-        steps += 1;
-    }
+    /// Molecules start from "e".
+    grammar: Grammar,
 }
 
 impl Puzzle for Day19 {
@@ -120,43 +25,22 @@ impl Puzzle for Day19 {
         // Input has the replacements listed first, then target molecule at the bottom
         let split: Vec<&str> = input.trim().split("\n\n").collect();
 
-        #[allow(unused_mut)]
-        let mut day = Day19 {
-            molecule: Molecule::new_from_string(split[1].trim()),
-            replacements: vec![],
-            replacement_strings: vec![],
-            starts: vec![],
-        };
+        let mut grammar = Grammar::parse(split[0], "e")?;
+        let molecule = grammar.parse_molecule(split[1].trim());
 
-        for line in split[0].lines() {
-            // Replacement lines look like
-            // Ti => TiTi
-            // e => HF
-            let molecules: Vec<&str> = line.trim().split(" ").collect();
-            if line.starts_with("e") {
-                // This is a start
-                day.starts.push(molecules[2].to_string());
-            } else {
-                // This is a replacement, store as a Replacement struct and basic strings
-                day.replacements.push(Replacement {
-                    from: Atom::new(molecules[0]),
-                    to: Molecule::new_from_string(molecules[2]),
-                });
-                day.replacement_strings.push(ReplacementString {
-                    from: molecules[0].to_string(),
-                    to: molecules[2].to_string(),
-                })
-            }
-        }
+        #[allow(unused_mut)]
+        let mut day = Day19 { molecule, grammar };
 
-        log::debug!("{}, {}", day.molecule.to_string(), day.molecule.len());
-        log::trace!("{:#?}", day.replacements);
+        let names = day.grammar.interner();
+        log::debug!("{}, {}", day.molecule.to_string(names), day.molecule.len());
+        log::trace!("{:#?}", day.grammar.rules());
 
         Ok(day)
     }
 
     fn solve_part1(&mut self) -> Result<String> {
-        let answer = count_replacements(self);
+        // Count how many distinct molecules a single replacement can make
+        let answer = self.grammar.products(&self.molecule).len();
         Ok(answer.to_string())
     }
 
@@ -168,8 +52,28 @@ impl Puzzle for Day19 {
     }
 
     fn solve_part2(&mut self) -> Result<String> {
-        let answer = find_best_replacement_path(self);
-        Ok(answer.to_string())
+        // Find the fewest replacements needed to go from e to the molecule
+        let derivation = self
+            .grammar
+            .shortest_derivation(&self.molecule)
+            .expect("The molecule should be buildable");
+        log::debug!("{}", derivation.display(self.grammar.interner()));
+
+        // Compare with the quick searches, which can get stuck or take more
+        // steps than needed, so they are only logged
+        if log::log_enabled!(log::Level::Debug) {
+            let greedy = self.grammar.reduce_greedy(&self.molecule);
+            let random = self.grammar.reduce_random(&self.molecule, 2015, 100);
+            for (name, found) in [("Greedy", greedy), ("Random", random)] {
+                let steps = found.map(|d| d.steps());
+                log::debug!(
+                    "{name} found {steps:?} steps, fewest is {}",
+                    derivation.steps()
+                );
+            }
+        }
+
+        Ok(derivation.steps().to_string())
     }
 
     fn answer_part2(&mut self, test: bool) -> Option<String> {
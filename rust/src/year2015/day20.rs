@@ -11,50 +11,32 @@ use crate::utils::primes::*;
 use crate::utils::utils::*;
 
 pub struct Day20 {
-    primes: Primes,
     target: usize,
 }
 
-/// NOTE this is unused as it's a little slow, 5 seconds to answer.
-/// But I asume the idea was to use factorization, so I'll keep the logic.
-/// Find the house that receives at least the target number of presents.
-/// A house recieves presents = sum_of_factors * scaler
-/// Execpt with stop_after_50_houses, a factor is removed from the sum if it
-/// has already been used at 50 other houses.
-#[allow(dead_code)]
-fn find_target_house(day: &mut Day20, scaler: usize, stop_after_50_houses: bool) -> usize {
-    // House present calculation is sum_of_factors * scaler
-    // So house 8 is (1+2+4+8)*10 = 150
-
-    // Iterate over all houses until the target present count is found
-    for house in 1..day.target {
-        // Get the factors for this house number
-        let factors = day.primes.all_factors(house);
-
-        // Sum up the factors
-        let mut sum_factors = 0;
-        for factor in factors.iter() {
-            if stop_after_50_houses && house / factor > 50 {
-                continue;
-            }
-            sum_factors += factor;
-        }
-
-        // Check if this is the target house
-        let presents = sum_factors * scaler;
-        log::debug!("House {house} got {}", presents);
-        if presents >= day.target {
+/// Find the house that receives at least the target number of presents, when
+/// every elf visits every house. Each house gets sum_of_factors * scaler, so
+/// sieve all the factor sums at once.
+fn find_target_house_via_sigma(day: &Day20, scaler: usize) -> usize {
+    // A house is one of its own factors, so house target / scaler always gets
+    // enough presents. Start with a small sieve and grow it up to that house.
+    let limit = day.target / scaler + 2;
+    let mut max = std::cmp::min(1024, limit);
+    loop {
+        let sums = divisor_sum_sieve(max);
+        if let Some(house) = sums.iter().position(|sum| sum * scaler >= day.target) {
             return house;
         }
+        assert!(max < limit, "Did not find target house");
+        max = std::cmp::min(max * 2, limit);
     }
-
-    panic!("Did not find target house");
 }
 
 /// Find the house that receives at least the target number of presents.
 fn find_target_house_via_elf(day: &mut Day20, scaler: usize, stop_after_50_houses: bool) -> usize {
-    // A little bit of optimization magic, but max = day.target is still pretty fast
-    let max = 1_000_000;
+    // Elf number house always visits that house, so house target / scaler
+    // always gets enough presents
+    let max = day.target / scaler + 2;
 
     let mut presents_at_house = vec![0; max];
 
@@ -88,18 +70,14 @@ impl Puzzle for Day20 {
         let target: usize = find_val(input.trim());
 
         #[allow(unused_mut)]
-        let mut day = Day20 {
-            primes: Primes::new(target),
-            target,
-        };
+        let mut day = Day20 { target };
 
         Ok(day)
     }
 
     fn solve_part1(&mut self) -> Result<String> {
         // Find target house, using scaler 10 and infinite elf present delivery
-        // let answer = find_target_house(self, 10, false);
-        let answer = find_target_house_via_elf(self, 10, false);
+        let answer = find_target_house_via_sigma(self, 10);
         Ok(answer.to_string())
     }
 
@@ -112,7 +90,6 @@ impl Puzzle for Day20 {
 
     fn solve_part2(&mut self) -> Result<String> {
         // Find target house, using scaler 11 and elfs stop after 50 houses
-        // let answer = find_target_house(self, 11, true);
         let answer = find_target_house_via_elf(self, 11, true);
         Ok(answer.to_string())
     }
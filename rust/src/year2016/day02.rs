@@ -27,17 +27,10 @@ impl Puzzle for Day02 {
         // Input is which direction to move. Each line will end on a key, that
         // part of the code.
         for line in input.trim().split('\n') {
-            let mut command = vec![];
-            for direction_char in line.chars() {
-                let direction = match direction_char {
-                    'U' => Direction::Up,
-                    'D' => Direction::Down,
-                    'L' => Direction::Left,
-                    'R' => Direction::Right,
-                    _ => panic!("Unknown direction char"),
-                };
-                command.push(direction)
-            }
+            let command = line
+                .chars()
+                .map(|c| Direction::from_letters(&c.to_string()).expect("Unknown direction char"))
+                .collect();
             day.commands.push(command);
         }
 
@@ -51,7 +44,7 @@ impl Puzzle for Day02 {
         grid.push_row(vec!['1', '2', '3']);
         grid.push_row(vec!['4', '5', '6']);
         grid.push_row(vec!['7', '8', '9']);
-        let middle = BoardPoint { x: 1, y: 1 };
+        let middle = BoardPoint::new(1, 1);
         let p1 = grid.add_player(middle, 'x');
 
         // Now move around this grid and find the key for each command sequence
@@ -83,7 +76,7 @@ impl Puzzle for Day02 {
         grid.push_row(vec![' ', 'A', 'B', 'C', ' ']);
         grid.push_row(vec![' ', ' ', 'D', ' ', ' ']);
         grid.add_wall(' ');
-        let middle = BoardPoint { x: 0, y: 2 };
+        let middle = BoardPoint::new(0, 2);
         let p1 = grid.add_player(middle, 'x');
 
         // Now move around this funky grid and find the code
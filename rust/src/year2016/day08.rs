@@ -3,44 +3,14 @@
 // --- Day 8: Two-Factor Authentication ---
 // Run some simple commands to display pixels on a screen
 
-use anyhow::Result;
-use regex::Regex;
-use rusttype::Point;
+use anyhow::{ensure, Result};
 
-use crate::{puzzle::Puzzle, utils::screen::Screen};
-
-#[allow(unused_imports)]
-use crate::utils::utils::*;
-
-#[allow(unused_imports)]
-use std::collections::VecDeque;
-
-struct Rotate {
-    target: usize,
-    count: usize,
-}
-
-// There are just three commands
-enum Command {
-    DrawRect(Point<usize>),
-    RotateRow(Rotate),
-    RotateColumn(Rotate),
-}
+use crate::puzzle::Puzzle;
+use crate::utils::screen::*;
 
 pub struct Day08 {
     screen: Screen,
-    commands: Vec<Command>,
-}
-
-fn run_commands(commands: &Vec<Command>, screen: &mut Screen) {
-    for command in commands.iter() {
-        // Run the commands on the screen
-        match command {
-            Command::DrawRect(point) => screen.set_pixels(Point { x: 0, y: 0 }, point.x, point.y),
-            Command::RotateRow(rotate) => screen.rotate_row(rotate.target, rotate.count),
-            Command::RotateColumn(rotate) => screen.rotate_column(rotate.target, rotate.count),
-        }
-    }
+    commands: Vec<ScreenCommand>,
 }
 
 impl Puzzle for Day08 {
@@ -52,43 +22,30 @@ impl Puzzle for Day08 {
             commands: vec![],
         };
 
-        for line in input.lines() {
-            log::debug!("Line {}", line);
-            // Parse the three command types to command enum
-            if line.starts_with("rect") {
-                let re = Regex::new(r"rect (\d*)x(\d*)").unwrap();
-                let matches = re.captures(line).unwrap();
-                let point = Point {
-                    x: find_val(matches.get(1).unwrap().as_str()),
-                    y: find_val(matches.get(2).unwrap().as_str()),
-                };
-                day.commands.push(Command::DrawRect(point));
-            } else if line.starts_with("rotate row") {
-                let re = Regex::new(r"rotate row y=(\d*) by (\d*)").unwrap();
-                let matches = re.captures(line).unwrap();
-                let rotate = Rotate {
-                    target: find_val(matches.get(1).unwrap().as_str()),
-                    count: find_val(matches.get(2).unwrap().as_str()),
-                };
-                day.commands.push(Command::RotateRow(rotate));
-            } else if line.starts_with("rotate column") {
-                let re = Regex::new(r"rotate column x=(\d*) by (\d*)").unwrap();
-                let matches = re.captures(line).unwrap();
-                let rotate = Rotate {
-                    target: find_val(matches.get(1).unwrap().as_str()),
-                    count: find_val(matches.get(2).unwrap().as_str()),
-                };
-                day.commands.push(Command::RotateColumn(rotate));
-            }
-        }
+        // Parse the three command types to commands
+        day.commands = ScreenCommand::parse_script(input)?;
 
         Ok(day)
     }
 
     fn solve_part1(&mut self) -> Result<String> {
         // Find out how many pixels are set after all commands are run
-        run_commands(&self.commands, &mut self.screen);
+        self.screen.run_all(&self.commands)?;
         let answer = self.screen.count_set_pixels();
+
+        // Check undoing every command unscrambles back to a blank screen, and
+        // running them again gets the same screen
+        if log::log_enabled!(log::Level::Trace) {
+            let end = self.screen.to_string();
+            self.screen.undo_all();
+            ensure!(self.screen.count_set_pixels() == 0, "Undo left pixels on");
+            self.screen.run_all(&self.commands)?;
+            ensure!(
+                self.screen.to_string() == end,
+                "Redo gave a different screen"
+            );
+        }
+
         Ok(answer.to_string())
     }
 
@@ -101,7 +58,8 @@ impl Puzzle for Day08 {
 
     fn solve_part2(&mut self) -> Result<String> {
         // Look at final screen debug print from part 1 and physically read in the display
-        run_commands(&self.commands, &mut self.screen);
+        self.screen.run_all(&self.commands)?;
+        log::debug!("{}", self.screen.render('█', ' '));
         let answer = self.screen.to_string();
         Ok(answer.to_string())
     }
@@ -7,6 +7,7 @@ use anyhow::Result;
 use std::ops::RangeInclusive;
 
 use crate::puzzle::Puzzle;
+use crate::utils::ranges::RangeSet;
 use crate::utils::utils::*;
 
 pub struct Day04 {
@@ -24,7 +25,8 @@ impl Puzzle for Day04 {
             // 5-7,7-9
             let line2 = line.replace("-", " "); // Remove '-' which could be a negative number
             let values = find_vals(&line2);
-            day.groups.push((values[0]..=values[1], values[2]..=values[3]));
+            day.groups
+                .push((values[0]..=values[1], values[2]..=values[3]));
         }
 
         Ok(day)
@@ -34,8 +36,8 @@ impl Puzzle for Day04 {
         let mut count = 0;
         // Count when one range fully contains the other range
         for (elf1, elf2) in self.groups.iter_mut() {
-            if (elf1.contains(elf2.start()) && elf1.contains(elf2.end()))
-                || (elf2.contains(elf1.start()) && elf2.contains(elf1.end()))
+            if RangeSet::from(elf1.clone()).contains_range(elf2)
+                || RangeSet::from(elf2.clone()).contains_range(elf1)
             {
                 count += 1;
             }
@@ -55,11 +57,7 @@ impl Puzzle for Day04 {
         let mut count = 0;
         // Count number of overlaps
         for (elf1, elf2) in self.groups.iter_mut() {
-            if elf1.contains(elf2.start())
-                || elf1.contains(elf2.end())
-                || elf2.contains(elf1.start())
-                || elf2.contains(elf1.end())
-            {
+            if RangeSet::from(elf1.clone()).overlaps(elf2) {
                 count += 1;
             }
         }
@@ -33,8 +33,6 @@ impl Puzzle for Day08 {
             day.score.push_row(vec![0; len]);
         }
 
-        day.board.add_player(BoardPoint { x: 0, y: 0 }, 0);
-
         Ok(day)
     }
 
@@ -43,30 +41,26 @@ impl Puzzle for Day08 {
 
         // Can see all of the trees at the edge
         let mut visible_trees = self.board.width() * 2 + self.board.height() * 2 - 4;
-        let player_id = 0; // Only one player
 
         // Walk all other trees in the grid, check if we can reach outside. If so, increment count.
         for y in 1..(self.board.height() - 1) {
             for x in 1..(self.board.width() - 1) {
-                // Move in each direction
+                // Look in each direction
                 for direction in Direction::straight_iterator() {
-                    self.board
-                        .set_player_location(player_id, BoardPoint { x, y });
-                    let tree_height = self.board.player_value(player_id).clone();
+                    let point = BoardPoint::new(x, y);
+                    let tree_height = self.board.value_at(point);
 
                     // Get all tree heights in this direction
-                    let mut tree_heights = vec![];
-                    while let Some(tree_height2) = self.board.step_player(player_id, direction) {
-                        tree_heights.push(tree_height2.clone());
-                    }
-                    let tree_height_max = tree_heights.iter().max().unwrap().clone();
+                    let tree_heights: Vec<u32> =
+                        self.board.ray(point, direction).map(|(_, h)| h).collect();
+                    let tree_height_max = *tree_heights.iter().max().unwrap();
 
                     // If our tree height is taller than outer trees, then this tree is visible from outside
                     let visible = tree_height > tree_height_max;
                     log::debug!("At {x},{y} going {direction:?}: {tree_height} vs {tree_height_max} = {visible}, {tree_heights:?}");
                     if visible {
                         visible_trees += 1;
-                        self.visible.set_at(BoardPoint { x, y }, 'v');
+                        self.visible.set_at(point, 'v');
                         break;
                     }
                 }
@@ -89,34 +83,27 @@ impl Puzzle for Day08 {
 
     fn solve_part2(&mut self) -> Result<String> {
         // Find how many trees we can see from within the forest
-        let player_id = 0; // Only one player
-
         // Search all non edge trees
         for y in 1..(self.board.height() - 1) {
             for x in 1..(self.board.width() - 1) {
-                let mut count_trees = vec![];
-                // Walk each direction
-                for direction in Direction::straight_iterator() {
-                    count_trees.push(0);
-                    self.board
-                        .set_player_location(player_id, BoardPoint { x, y });
-                    let tree_height = self.board.player_value(player_id).clone();
-
-                    // Keep walking while this tree is shorter than our tree
-                    while let Some(tree_height2) = self.board.step_player(player_id, direction) {
-                        let s = count_trees.pop().unwrap().clone();
-                        count_trees.push(s + 1);
-                        if tree_height2 >= tree_height {
-                            // This tree is taller, we cannot see beyond, so stop
-                            break;
-                        }
-                    }
-                }
+                let point = BoardPoint::new(x, y);
+                let tree_height = self.board.value_at(point);
+
+                // Look each direction, counting trees until one is as tall as our tree.
+                // We cannot see beyond that tree, but it is counted.
+                let count_trees: Vec<usize> = Direction::straight_iterator()
+                    .into_iter()
+                    .map(|direction| {
+                        self.board
+                            .ray_until(point, direction, |h| h >= tree_height)
+                            .count()
+                    })
+                    .collect();
 
                 // Calculate score as multiple of count in each direction
-                let mega_score = count_trees.iter().fold(1, |a, x| a * x);
+                let mega_score = count_trees.iter().product::<usize>() as u32;
                 log::debug!("At {x},{y} score {count_trees:?} --> {mega_score}");
-                self.score.set_at(BoardPoint { x, y }, mega_score);
+                self.score.set_at(point, mega_score);
             }
         }
 
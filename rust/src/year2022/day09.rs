@@ -25,11 +25,8 @@ fn move_it(day: &mut Day09, player_count: usize) -> u32 {
 
     // Initial location in the middle of the board so we do not overflow
     let init = match debug {
-        true => BoardPoint { x: 0, y: 5 },
-        false => BoardPoint {
-            x: dim as i32 / 2,
-            y: dim as i32 / 2,
-        },
+        true => BoardPoint::new(0, 5),
+        false => BoardPoint::new(dim as i32 / 2, dim as i32 / 2),
     };
 
     // Build the game board and history board.
@@ -19,30 +19,16 @@ impl Puzzle for Day10 {
     fn from_input(input: &str) -> Result<Self> {
         #[allow(unused_mut)]
         let mut day = Day10 {
-            cpu: Cpu::new(),
+            cpu: Cpu::new(CpuConfig::handheld()),
             crt: Crt::new(40, 6),
         };
 
-        // Read in the program and run it immediately
-        for line in input.lines() {
-            let instruction = match line {
-                "noop" => Instruction::Noop,
-                _ => {
-                    let parts: Vec<&str> = line.split(" ").collect();
-                    let i: i32 = parts[1].parse()?;
-                    Instruction::Add(Register::X, i)
-                }
-            };
-
-            // Run this instruction and drive CRT
-            let reg_x = day.cpu.reg(Register::X);
-            day.crt.print_sprite(reg_x as usize);
-            let count = day.cpu.cycle_count(&instruction);
-            for _ in 0..count {
-                day.crt.step(reg_x);
-            }
-            day.cpu.run_instruction(&instruction);
-        }
+        // Read in the program and run it immediately, driving the CRT. Trace
+        // it, to look back at the registers for part 1.
+        day.cpu.load(input)?;
+        day.cpu.set_trace(true);
+        day.crt.attach(day.cpu.register("x"));
+        day.crt.run(&mut day.cpu)?;
 
         Ok(day)
     }
@@ -50,10 +36,11 @@ impl Puzzle for Day10 {
     fn solve_part1(&mut self) -> Result<String> {
         let mut count = 0;
         // Sum reg_x at certain times in history
-        for x in (19..220).step_by(40) {
-            let state = self.cpu.state_history[x].clone();
-            log::debug!("{:#?}", state.registers[Register::X as usize]);
-            count += (1 + x as i32) * state.registers[Register::X as usize];
+        let reg_x = self.cpu.register("x");
+        let trace = self.cpu.trace().expect("Tracing is on");
+        for state in trace.sample((20..=220).step_by(40)) {
+            log::debug!("{:#?}", state.registers[reg_x.0]);
+            count += state.time() as i32 * state.registers[reg_x.0];
         }
 
         Ok(count.to_string())
@@ -41,17 +41,11 @@ impl Puzzle for Day12 {
             line = line3;
             day.grid.push_row(line.chars().collect());
             if char_in_string(&'S', &line_orig.to_string()) {
-                let start = BoardPoint {
-                    x: line_orig.find("S").unwrap() as i32,
-                    y: i as i32,
-                };
+                let start = BoardPoint::new(line_orig.find("S").unwrap() as i32, i as i32);
                 day.start_player = day.grid.add_player(start, 'S');
             }
             if char_in_string(&'E', &line_orig.to_string()) {
-                let end = BoardPoint {
-                    x: line_orig.find("E").unwrap() as i32,
-                    y: i as i32,
-                };
+                let end = BoardPoint::new(line_orig.find("E").unwrap() as i32, i as i32);
                 day.end_player = day.grid.add_player(end, 'E');
             }
         }
@@ -83,10 +77,7 @@ impl Puzzle for Day12 {
             for x in 0..self.grid.grid().cols() {
                 let chr = self.grid.grid()[y][x];
                 if chr == 'a' {
-                    let point = BoardPoint {
-                        x: x as i32,
-                        y: y as i32,
-                    };
+                    let point = BoardPoint::new(x as i32, y as i32);
                     let new_player = self.grid.add_player(point, '?');
                     answers.push(self.grid.find_shortest_path(
                         new_player,
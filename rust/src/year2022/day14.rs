@@ -7,6 +7,8 @@ use anyhow::Result;
 
 use crate::puzzle::Puzzle;
 use crate::utils::board::*;
+use crate::utils::point::{X, Y};
+use crate::utils::render::*;
 use crate::utils::utils::*;
 
 pub struct Day14 {
@@ -17,10 +19,7 @@ pub struct Day14 {
 }
 
 fn drop_sand(day: &mut Day14) -> u32 {
-    let origin = BoardPoint {
-        x: 500 - day.offset.x,
-        y: 0,
-    };
+    let origin = BoardPoint::new(500 - day.offset.x(), 0);
     let mut next_location = origin;
     let directions = vec![Direction::Down, Direction::DownLeft, Direction::DownRight];
     let mut count = 0;
@@ -39,7 +38,7 @@ fn drop_sand(day: &mut Day14) -> u32 {
                     if *direction == Direction::Down {
                         // Cache this as a new origin
                         next_location = day.grid.player_location(id);
-                        next_location.y -= 1;
+                        next_location[Y] -= 1;
                     }
 
                     break;
@@ -55,10 +54,10 @@ fn drop_sand(day: &mut Day14) -> u32 {
 
         // Check if player fell off or grid is full
         let location = day.grid.player_location(id);
-        if location.x == 0
-            || location.x == day.grid.width() - 1
-            || location.y == day.grid.height() - 1
-            || (location.x == origin.x && location.y == origin.y)
+        if location.x() == 0
+            || location.x() == day.grid.width() - 1
+            || location.y() == day.grid.height() - 1
+            || (location.x() == origin.x() && location.y() == origin.y())
         {
             break;
         }
@@ -74,29 +73,46 @@ fn drop_sand(day: &mut Day14) -> u32 {
     count
 }
 
+/// When a picture directory is set, save a picture of the sand pile there for
+/// debugging.
+fn save_picture(day: &Day14, name: &str) -> Result<()> {
+    let Some(directory) = picture_dir() else {
+        return Ok(());
+    };
+
+    let path = directory.join(name);
+    let mut renderer = Renderer::new(4);
+    renderer.set_default_color([255, 255, 255]);
+    renderer.set_color('#', [90, 90, 90]);
+    renderer.set_color('o', [230, 190, 90]);
+    renderer.write_png(&day.grid, &path)?;
+    log::debug!("Saved picture to {}", path.display());
+    Ok(())
+}
+
 impl Puzzle for Day14 {
     #[allow(unused_variables)]
     fn from_input(input: &str) -> Result<Self> {
         #[allow(unused_mut)]
         let mut day = Day14 {
             grid: Board::new(),
-            offset: BoardPoint { x: 0, y: 0 },
-            min: BoardPoint { x: 1000, y: 1000 },
-            max: BoardPoint { x: 0, y: 0 },
+            offset: BoardPoint::new(0, 0),
+            min: BoardPoint::new(1000, 1000),
+            max: BoardPoint::new(0, 0),
         };
 
         let test = input.lines().count() < 10;
         if test {
             // 11x11 grid
             // min, max: Point { x: 494, y: 4 }, Point { x: 503, y: 9 }
-            day.offset.x = 400;
+            day.offset[X] = 400;
             let row = vec!['.'; 200];
             for _ in 0..13 {
                 day.grid.push_row(row.clone());
             }
         } else {
             // min, max: Point { x: 483, y: 16 }, Point { x: 544, y: 164 }
-            day.offset.x = 330;
+            day.offset[X] = 330;
             let row = vec!['.'; 340];
             for _ in 0..167 {
                 day.grid.push_row(row.clone());
@@ -110,70 +126,33 @@ impl Puzzle for Day14 {
 
         for line in input.lines() {
             let mut started = false;
-            let mut first = BoardPoint { x: 0, y: 0 };
+            let mut first = BoardPoint::new(0, 0);
             for point_str in line.split("->") {
                 let values: Vec<i32> = find_vals(point_str);
-                let second = BoardPoint {
-                    x: values[0],
-                    y: values[1],
-                };
-                if second.x > day.max.x {
-                    day.max.x = second.x;
+                let second = BoardPoint::new(values[0], values[1]);
+                if second.x() > day.max.x() {
+                    day.max[X] = second.x();
                 }
-                if second.x < day.min.x {
-                    day.min.x = second.x;
+                if second.x() < day.min.x() {
+                    day.min[X] = second.x();
                 }
-                if second.y > day.max.y {
-                    day.max.y = second.y;
+                if second.y() > day.max.y() {
+                    day.max[Y] = second.y();
                 }
-                if second.y < day.min.y {
-                    day.min.y = second.y;
+                if second.y() < day.min.y() {
+                    day.min[Y] = second.y();
                 }
                 if started {
                     // connect lines
-                    let mut begin = BoardPoint {
-                        x: first.x,
-                        y: first.y,
-                    };
-                    let mut end = BoardPoint {
-                        x: second.x,
-                        y: second.y,
-                    };
-                    if first.x == second.x {
-                        if first.y > second.y {
-                            begin.y = second.y;
-                            end.y = first.y;
-                        }
-                        for y in begin.y..=end.y {
-                            day.grid.set_at(
-                                BoardPoint {
-                                    x: begin.x - day.offset.x,
-                                    y,
-                                },
-                                '#',
-                            );
-                        }
-                    } else if first.y == second.y {
-                        if first.x > second.x {
-                            begin.x = second.x;
-                            end.x = first.x;
-                        }
-                        for x in begin.x..=end.x {
-                            day.grid.set_at(
-                                BoardPoint {
-                                    x: x - day.offset.x,
-                                    y: begin.y,
-                                },
-                                '#',
-                            );
-                        }
-                    } else {
-                        panic!("Unexpected line connection");
-                    }
+                    day.grid.draw_line(
+                        BoardPoint::new(first.x() - day.offset.x(), first.y()),
+                        BoardPoint::new(second.x() - day.offset.x(), second.y()),
+                        '#',
+                    );
                 }
                 started = true;
-                first.x = second.x;
-                first.y = second.y;
+                first[X] = second.x();
+                first[Y] = second.y();
             }
         }
 
@@ -184,6 +163,7 @@ impl Puzzle for Day14 {
 
     fn solve_part1(&mut self) -> Result<String> {
         let count = drop_sand(self);
+        save_picture(self, "day14_part1.png")?;
         Ok(count.to_string())
     }
 
@@ -196,15 +176,10 @@ impl Puzzle for Day14 {
 
     fn solve_part2(&mut self) -> Result<String> {
         for x in 0..self.grid.width() {
-            self.grid.set_at(
-                BoardPoint {
-                    x,
-                    y: self.max.y + 2,
-                },
-                '#',
-            );
+            self.grid.set_at(BoardPoint::new(x, self.max.y() + 2), '#');
         }
         let count = drop_sand(self) + 1;
+        save_picture(self, "day14_part2.png")?;
         Ok(count.to_string())
     }
 
@@ -4,14 +4,14 @@
 // Draw circles using Manhattan distance
 // Part 1 just inspect one row and find known no-beacon squares
 // Part 2 need to search the whole map for unknown square, it's too big for brute
-// force. Optimization is when in a known area, jump to the edge of that known area.
-// The intented implementation was probably to look at radius+1 for each circle,
-// but that math is harder than just adding!
+// force. The square is boxed in by circles, so it sits just outside the edges of
+// some circles. Only check where those edges cross.
 
 use anyhow::Result;
 use std::cmp::*;
 
 use crate::puzzle::Puzzle;
+use crate::utils::geometry::*;
 use crate::utils::utils::*;
 
 pub struct Day15 {
@@ -25,12 +25,7 @@ struct Pair {
     sensor: UtilsPoint,
     beacon: UtilsPoint,
     distance: i32,
-}
-
-fn sensor_covers_row(pair: &Pair, row: i32) -> bool {
-    let minimum = pair.sensor.y - pair.distance;
-    let maximum = pair.sensor.y + pair.distance;
-    (minimum..=maximum).contains(&row)
+    area: Diamond,
 }
 
 impl Puzzle for Day15 {
@@ -57,18 +52,13 @@ impl Puzzle for Day15 {
 
         for line in input.lines() {
             let vals: Vec<i32> = find_vals(line);
-            let sensor = UtilsPoint {
-                x: vals[0],
-                y: vals[1],
-            };
-            let beacon = UtilsPoint {
-                x: vals[2],
-                y: vals[3],
-            };
+            let sensor = UtilsPoint::new(vals[0], vals[1]);
+            let beacon = UtilsPoint::new(vals[2], vals[3]);
             let pair = Pair {
                 sensor,
                 beacon,
                 distance: manhattan_distance(sensor, beacon),
+                area: Diamond::from_edge(sensor, beacon),
             };
             day.pairs.push(pair);
         }
@@ -76,19 +66,19 @@ impl Puzzle for Day15 {
         let min_x = day
             .pairs
             .iter()
-            .fold(i32::MAX, |a, p| min(a, min(p.sensor.x, p.beacon.x)));
+            .fold(i32::MAX, |a, p| min(a, min(p.sensor.x(), p.beacon.x())));
         let min_y = day
             .pairs
             .iter()
-            .fold(i32::MAX, |a, p| min(a, min(p.sensor.y, p.beacon.y)));
+            .fold(i32::MAX, |a, p| min(a, min(p.sensor.y(), p.beacon.y())));
         let max_x = day
             .pairs
             .iter()
-            .fold(0, |a, p| max(a, max(p.sensor.x, p.beacon.x)));
+            .fold(0, |a, p| max(a, max(p.sensor.x(), p.beacon.x())));
         let max_y = day
             .pairs
             .iter()
-            .fold(0, |a, p| max(a, max(p.sensor.y, p.beacon.y)));
+            .fold(0, |a, p| max(a, max(p.sensor.y(), p.beacon.y())));
         let max_dist = day.pairs.iter().fold(0, |a, p| max(a, p.distance));
 
         log::debug!("Pairs {:?}", day.pairs);
@@ -99,58 +89,15 @@ impl Puzzle for Day15 {
     }
 
     fn solve_part1(&mut self) -> Result<String> {
-        let mut pairs = vec![];
-        // For the given row, find which pairs cover this row
-        for pair in &self.pairs {
-            if sensor_covers_row(pair, self.target_row) {
-                pairs.push(pair);
-            }
-        }
-
-        let max_dist = pairs.iter().fold(0, |a, p| max(a, p.distance));
-        let min_x = pairs
-            .iter()
-            .fold(i32::MAX, |a, p| min(a, min(p.sensor.x, p.beacon.x)))
-            - max_dist;
-        let min_y = pairs
-            .iter()
-            .fold(i32::MAX, |a, p| min(a, min(p.sensor.y, p.beacon.y)))
-            - max_dist;
-        let max_x = pairs
-            .iter()
-            .fold(0, |a, p| max(a, max(p.sensor.x, p.beacon.x)))
-            + max_dist;
-        let max_y = pairs
-            .iter()
-            .fold(0, |a, p| max(a, max(p.sensor.y, p.beacon.y)))
-            + max_dist;
-
-        log::debug!("Pairs {:?}", pairs);
-        log::debug!("Min {min_x},{min_y} to max {max_x}, {max_y}");
-        log::debug!("Max distance {max_dist}");
-
-        let mut count = 0;
-        // Walk the length of this known row
-        for x in min_x..=max_x {
-            let here = UtilsPoint {
-                x,
-                y: self.target_row,
-            };
-
-            // Compare manhattan distance to each pair and if that's inside the distance
-            for pair in &self.pairs {
-                let dist = manhattan_distance(here, pair.sensor);
-                if pair.distance > dist {
-                    // Count if this area is known to not have a beacon
-                    count += 1;
-                    break;
-                } else if pair.distance == dist && pair.beacon.x != x {
-                    // If at edge of radius, only count if this is not a beacon
-                    count += 1;
-                    break;
-                }
+        // Count the known squares in the row, but a beacon is not a no-beacon square
+        let areas: Vec<Diamond> = self.pairs.iter().map(|p| p.area).collect();
+        let mut covered = row_coverage(&areas, self.target_row);
+        for pair in self.pairs.iter() {
+            if pair.beacon.y() == self.target_row {
+                covered.remove(pair.beacon.x()..=pair.beacon.x());
             }
         }
+        let count = covered.len();
 
         Ok(count.to_string())
     }
@@ -163,48 +110,15 @@ impl Puzzle for Day15 {
     }
 
     fn solve_part2(&mut self) -> Result<String> {
-        let mut perf = 0;
-        let mut y = 0;
-        // Walk the whole map
-        while y <= self.max {
-            let mut x = 0;
-            while x <= self.max {
-                let mut keep_going = false;
-                let here = UtilsPoint { x, y };
-
-                // Check all known area to see if this spot is covered
-                for pair in self.pairs.iter() {
-                    let dist = manhattan_distance(here, pair.sensor);
-                    perf += 1;
-                    if pair.distance >= dist {
-                        // This spot is covered, skip everything this sensor already covers
-                        keep_going = true;
-
-                        // Try to jump to the right most unknown spot for this sensor
-                        let y_from_sensor = (here.y - pair.sensor.y).abs();
-                        let x_to_move_to = pair.sensor.x + pair.distance - y_from_sensor + 1;
-                        if x_to_move_to <= self.max {
-                            x = x_to_move_to;
-                            break;
-                        }
-
-                        // Sensor goes beyond our search grid, skip to the start of the next line
-                        x = 0;
-                        y += 1;
-                        break;
-                    }
-                }
-                if !keep_going {
-                    let answer = here.x as i64 * 4000000 + here.y as i64;
-                    log::debug!("Perf {perf}");
-                    log::debug!("{:?}", here);
-                    return Ok(answer.to_string());
-                }
-            }
-            y += 1;
-        }
-
-        Ok("Unexpected result".to_string()) // Should be unreachable
+        // Find the one square that no sensor covers
+        let areas: Vec<Diamond> = self.pairs.iter().map(|p| p.area).collect();
+        let min = UtilsPoint::new(0, 0);
+        let max = UtilsPoint::new(self.max, self.max);
+        let here = find_uncovered(&areas, min, max).expect("Every square is covered");
+        log::debug!("{:?}", here);
+        let answer = here.x() as i64 * 4000000 + here.y() as i64;
+
+        Ok(answer.to_string())
     }
 
     fn answer_part2(&mut self, test: bool) -> Option<String> {
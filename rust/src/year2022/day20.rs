@@ -4,13 +4,11 @@
 // Walk a linked list and do operations to encode/decode data
 
 use anyhow::Result;
-use std::rc::Rc;
 
 use crate::puzzle::Puzzle;
 use crate::utils::linked_list::*;
 use crate::utils::utils::*;
 
-#[derive(Default)]
 pub struct Day20 {
     linked_list: LinkedList<i64>,
 }
@@ -20,52 +18,18 @@ pub struct Day20 {
 /// Before running logic, scale by `decription_key`.
 fn scramble(day: &mut Day20, iterations: usize, decryption_key: i64) {
     // Scale each node by the decription key
-    day.linked_list.move_to_head();
-    for _ in 0..day.linked_list.len() {
-        let value = day.linked_list.current_value() * decryption_key;
-        day.linked_list.set_current_value(&value);
-        day.linked_list.move_next();
+    for node in 0..day.linked_list.node_count() {
+        *day.linked_list.value_mut(node) *= decryption_key;
     }
 
     // Run the requested iterations
     for _ in 0..iterations {
         // Modify each node in the list in the original order
-        for i in 0..day.linked_list.len() {
-            // Get node from original order
-            let node = day.linked_list.values[i].clone();
-            let node_weak = Rc::downgrade(&node);
-
-            // Use value of node to determine the shift amount.
-            // Do not do more than one full loop.
-            let value = node.borrow().value;
-            let len = day.linked_list.len() as i64 - 1; // -1 as pop makes list 1 smaller
-            let mut shift = value % len;
-            if shift == 0 {
-                // No work when shift is zero
-                continue;
-            }
-
-            // Pop will move next one, so reduce shift count for positive shift.
-            // For negative shift, increase shift count by one as pop moved to next.
-            shift -= 1;
-
+        for node in 0..day.linked_list.node_count() {
+            // Use value of node to determine the shift amount
+            let value = *day.linked_list.value(node);
             log::debug!("Moving {value}");
-
-            // Pop this node
-            day.linked_list.set_current(&node_weak);
-            day.linked_list.pop();
-
-            // Now walk the list the requested times
-            for _ in 0..shift.abs() {
-                if shift > 0 {
-                    day.linked_list.move_next();
-                } else {
-                    day.linked_list.move_prev();
-                }
-            }
-
-            // Finally, insert the node at this new locaiton
-            day.linked_list.insert(&node_weak);
+            day.linked_list.move_node(node, value);
             day.linked_list.print();
         }
     }
@@ -75,19 +39,17 @@ fn scramble(day: &mut Day20, iterations: usize, decryption_key: i64) {
 /// the node with value zero.
 fn find_answer(day: &mut Day20) -> i64 {
     // Find the node with value zero
-    loop {
-        if day.linked_list.current_value() == 0 {
-            break;
-        }
-        day.linked_list.move_next();
-    }
+    let (zero, _) = day
+        .linked_list
+        .iter()
+        .find(|(_, value)| **value == 0)
+        .unwrap();
+    day.linked_list.set_current(zero);
 
     // Add values at index 1000, 2000, and 3000 to a vector
     let mut answers = vec![];
     for _ in 0..3 {
-        for _ in 0..1000 % day.linked_list.len() {
-            day.linked_list.move_next();
-        }
+        day.linked_list.move_by(1000);
         answers.push(day.linked_list.current_value());
     }
     log::debug!("{:?}", answers);
@@ -101,17 +63,15 @@ fn find_answer(day: &mut Day20) -> i64 {
 impl Puzzle for Day20 {
     #[allow(unused_variables)]
     fn from_input(input: &str) -> Result<Self> {
-        #[allow(unused_mut)]
-        let mut day = Day20 {
-            ..Default::default()
-        };
-
         let mut values = vec![];
         for (i, line) in input.lines().enumerate() {
             values.push(find_val(line));
         }
 
-        day.linked_list = LinkedList::new(&values);
+        #[allow(unused_mut)]
+        let mut day = Day20 {
+            linked_list: LinkedList::new(&values),
+        };
 
         Ok(day)
     }
@@ -6,7 +6,6 @@
 
 use anyhow::Result;
 use core::panic;
-use std::vec;
 
 use crate::puzzle::Puzzle;
 use crate::utils::board::*;
@@ -17,7 +16,6 @@ pub struct Day22 {
     board: Board<char>,     // part 1
     board3d: Board3D<char>, // part 2
     commands: Vec<Command>,
-    board_offsets: Vec<BoardPoint>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -30,23 +28,11 @@ enum Command {
 // Find the new direction based on the current direction and to turn 90 or -90 degrees.
 fn turn_me(current_direction: Direction, how_to_turn: Command) -> Direction {
     log::debug!("Turn {how_to_turn:?}");
-    let directions = vec![
-        Direction::Up,
-        Direction::Right,
-        Direction::Down,
-        Direction::Left,
-    ];
-    let offset = match how_to_turn {
-        Command::TurnClockwise => 1,
-        Command::TurnCounterClockwise => -1,
+    let new_direction = match how_to_turn {
+        Command::TurnClockwise => current_direction.turn_right(),
+        Command::TurnCounterClockwise => current_direction.turn_left(),
         _ => panic!("Unexpected turn"),
     };
-    let current_index = directions
-        .iter()
-        .position(|&x| x == current_direction)
-        .unwrap();
-    let new_index = (current_index as i32 + offset).rem_euclid(directions.len() as i32) as usize;
-    let new_direction = directions[new_index];
     log::debug!("Now facing {:?}", new_direction);
     new_direction
 }
@@ -114,7 +100,6 @@ impl Puzzle for Day22 {
             board: Board::new(),
             board3d: Board3D::new(),
             commands: vec![],
-            board_offsets: vec![],
         };
 
         // Get the commands
@@ -128,65 +113,33 @@ impl Puzzle for Day22 {
             width = std::cmp::max(line.chars().count(), width);
         }
 
-        // Find 3d width
-        let test = width < 20;
-        let width3d = match test {
-            true => 4,
-            false => 50,
-        };
-        let height3d = width3d;
-
         // Add map lines to board
-        let mut board_id_offset = 0;
-        let mut y_offset = 0;
-        for (i, line) in lines.iter().enumerate() {
+        let mut faces_area = 0;
+        for line in lines.iter() {
             let mut chars: Vec<char> = line.chars().collect();
+            faces_area += chars.iter().filter(|&&c| c != ' ').count();
             let need_more = width - chars.len();
             if need_more > 0 {
                 let more = vec![' '; need_more];
                 chars.extend(more);
             }
-            day.board.push_row(chars.clone());
-
-            // Add lines to 3d board
-            let lines3d: Vec<&[char]> = chars.chunks(width3d).collect();
-            let mut board_id = 0;
-            let mut x_offset = 0;
-            for line3d in lines3d {
-                if line3d.contains(&' ') {
-                    x_offset += width3d as i32;
-                    continue;
-                }
-                day.board3d
-                    .push_row(board_id + board_id_offset, line3d.to_vec());
-                if board_id + board_id_offset == day.board_offsets.len() {
-                    day.board_offsets.push(BoardPoint {
-                        x: x_offset,
-                        y: y_offset,
-                    });
-                }
-                board_id += 1;
-                x_offset += width3d as i32;
-            }
-
-            if (i + 1) % width3d == 0 {
-                board_id_offset += board_id;
-                y_offset += height3d as i32;
-            }
+            day.board.push_row(chars);
         }
 
-        log::debug!("board offsets {:?}", day.board_offsets);
+        // Fold the map into a 3d cube, the 6 faces are squares
+        let width3d = ((faces_area / 6) as f64).sqrt() as i32;
+        day.board3d = Board3D::new_from_net(&day.board, width3d, ' ');
 
         // Add player at left most top row
         let mut start_x = 0;
         for x in 0..width as i32 {
-            if day.board.value_at(BoardPoint { x, y: 0 }) == '.' {
+            if day.board.value_at(BoardPoint::new(x, 0)) == '.' {
                 start_x = x;
                 break;
             }
         }
-        day.board.add_player(BoardPoint { x: start_x, y: 0 }, '+');
-        day.board3d.add_player(0, BoardPoint { x: 0, y: 0 }, '+');
+        day.board.add_player(BoardPoint::new(start_x, 0), '+');
+        day.board3d.add_player(0, BoardPoint::new(0, 0), '+');
 
         // Config the board
         day.board.add_wraparound(' '); // This does the magic moves for part 1
@@ -212,95 +165,6 @@ impl Puzzle for Day22 {
         }
         log::trace!("Commands {:?}", day.commands);
 
-        // Build 3d board, need to connect the edges
-        // Each folding for input looks different, I'm hard coding how test and my real work here
-        if test {
-            // Fold is like
-            // __0_
-            // 123_
-            // __45
-
-            // 1 top -> 0 top, down (inverse)
-            // 1 left -> 5 bottom, up (inverse)
-            // 1 bottom -> 4 bottom, up (inverse)
-            // 2 top -> 0 left, right
-            // 2 bottom -> 4 left, right (inverse)
-            // 3 right -> 5 top, down (inverse)
-            // 4 left -> 2 bottom, up (inverse)
-            // 4 botom -> 1 bottom, up (inverse)
-            // 5 top -> 3 right, left (inverse)
-            // 5 right -> 0 right, left (inverse)
-            // 5 bottom -> 1 left, right (inverse)
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Top, 1, Edge::Top, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Right, 5, Edge::Right, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Bottom, 3, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Left, 2, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(1, Edge::Right, 2, Edge::Left, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(1, Edge::Bottom, 4, Edge::Bottom, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(1, Edge::Left, 5, Edge::Bottom, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(2, Edge::Right, 3, Edge::Left, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(2, Edge::Bottom, 4, Edge::Left, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(3, Edge::Right, 5, Edge::Top, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(3, Edge::Bottom, 4, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(4, Edge::Right, 5, Edge::Left, false));
-        } else {
-            // Fold is like
-            // _01
-            // _2_
-            // 34_
-            // 5__
-            // 0 top -> 5 left
-            // 0 left -> 3 left (inverse)
-            // 1 top -> 5 bottom
-            // 1 right -> 4 right (inverse)
-            // 1 bottom -> 2 right
-            // 2 left -> 3 top
-            // 2 right -> 1 bottom
-            // 3 top -> 2 left
-            // 3 left -> 0 left (inverse)
-            // 4 right -> 1 right (inverse)
-            // 4 bottom -> 5 right
-            // 5 left -> 0 top
-            // 5 right -> 4 bottom
-            // 5 bottom -> 1 top
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Top, 5, Edge::Left, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Right, 1, Edge::Left, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Bottom, 2, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(0, Edge::Left, 3, Edge::Left, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(1, Edge::Top, 5, Edge::Bottom, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(1, Edge::Right, 4, Edge::Right, true));
-            day.board3d
-                .set_edge(EdgeConnection::new(1, Edge::Bottom, 2, Edge::Right, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(2, Edge::Bottom, 4, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(2, Edge::Left, 3, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(3, Edge::Right, 4, Edge::Left, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(3, Edge::Bottom, 5, Edge::Top, false));
-            day.board3d
-                .set_edge(EdgeConnection::new(4, Edge::Bottom, 5, Edge::Right, false));
-        }
-
         day.board.add_wall('#');
         day.board3d.add_wall('#');
 
@@ -311,7 +175,7 @@ impl Puzzle for Day22 {
         let direction = navigate(self);
         let point = self.board.player_location(0);
         log::debug!("Ended at {:?}", point);
-        let answer = (point.y + 1) * 1000 + (point.x + 1) * 4 + direction_value(direction);
+        let answer = (point.y() + 1) * 1000 + (point.x() + 1) * 4 + direction_value(direction);
         Ok(answer.to_string())
     }
 
@@ -324,24 +188,22 @@ impl Puzzle for Day22 {
 
     fn solve_part2(&mut self) -> Result<String> {
         let direction = navigate3d(self);
-        // Need to convert from 2d based direction to 3d based direction
-        let real_direction = self.board3d.player_direction(0, direction);
-        let (board_id, point) = self.board3d.player_location(0);
-        // Also need to convert from 3d point to 2d point
-        let real_point = BoardPoint {
-            x: point.x + self.board_offsets[board_id].x,
-            y: point.y + self.board_offsets[board_id].y,
-        };
+        if log::log_enabled!(log::Level::Trace) {
+            self.board3d.print_net_with_trail(0);
+        }
+
+        // Need to convert from 3d point and direction back to the 2d map
+        let real_direction = self.board3d.global_facing(0, direction);
+        let real_point = self.board3d.global_location(0);
         log::debug!(
-            "Ended at {:?} on board {} ({:?} + {:?}). Direction {:?}",
+            "Ended at {:?} on board {:?}. Direction {:?}",
             real_point,
-            board_id,
-            point,
-            self.board_offsets[board_id],
+            self.board3d.player_location(0),
             real_direction,
         );
-        let answer =
-            (real_point.y + 1) * 1000 + (real_point.x + 1) * 4 + direction_value(real_direction);
+        let answer = (real_point.y() + 1) * 1000
+            + (real_point.x() + 1) * 4
+            + direction_value(real_direction);
         Ok(answer.to_string())
     }
 
@@ -41,14 +41,14 @@ fn play(day: &mut Day23, rounds: u32) -> u32 {
 
     // Lookup for how steps work
     let step_offsets = HashMap::from([
-        (Direction::Up, BoardPoint { x: 0, y: -1 }),
-        (Direction::Down, BoardPoint { x: 0, y: 1 }),
-        (Direction::Left, BoardPoint { x: -1, y: 0 }),
-        (Direction::Right, BoardPoint { x: 1, y: 0 }),
-        (Direction::UpLeft, BoardPoint { x: -1, y: -1 }),
-        (Direction::UpRight, BoardPoint { x: 1, y: -1 }),
-        (Direction::DownLeft, BoardPoint { x: -1, y: 1 }),
-        (Direction::DownRight, BoardPoint { x: 1, y: 1 }),
+        (Direction::Up, BoardPoint::new(0, -1)),
+        (Direction::Down, BoardPoint::new(0, 1)),
+        (Direction::Left, BoardPoint::new(-1, 0)),
+        (Direction::Right, BoardPoint::new(1, 0)),
+        (Direction::UpLeft, BoardPoint::new(-1, -1)),
+        (Direction::UpRight, BoardPoint::new(1, -1)),
+        (Direction::DownLeft, BoardPoint::new(-1, 1)),
+        (Direction::DownRight, BoardPoint::new(1, 1)),
     ]);
 
     // Get started with rounds
@@ -73,10 +73,7 @@ fn play(day: &mut Day23, rounds: u32) -> u32 {
                 let mut good = true;
                 for direction in proposal {
                     let offset = step_offsets[direction];
-                    let location = BoardPoint {
-                        x: elf_location.x + offset.x,
-                        y: elf_location.y + offset.y,
-                    };
+                    let location = elf_location + offset;
                     if day.board.is_player_here(location) {
                         good = false;
                         break;
@@ -87,10 +84,7 @@ fn play(day: &mut Day23, rounds: u32) -> u32 {
                 if good {
                     let good_direction = proposal_choice[i];
                     let good_offset = step_offsets[&good_direction];
-                    let good_location = BoardPoint {
-                        x: elf_location.x + good_offset.x,
-                        y: elf_location.y + good_offset.y,
-                    };
+                    let good_location = elf_location + good_offset;
                     let good_move = ElfMove {
                         location: good_location,
                         elf_id,
@@ -197,10 +191,10 @@ impl Puzzle for Day23 {
         let mut y_max = 0;
         for player in 0..self.board.players_len() {
             let location = self.board.player_location(player);
-            x_min = std::cmp::min(x_min, location.x);
-            y_min = std::cmp::min(y_min, location.y);
-            x_max = std::cmp::max(x_max, location.x);
-            y_max = std::cmp::max(y_max, location.y);
+            x_min = std::cmp::min(x_min, location.x());
+            y_min = std::cmp::min(y_min, location.y());
+            x_max = std::cmp::max(x_max, location.x());
+            y_max = std::cmp::max(y_max, location.y());
         }
 
         let width = x_max - x_min + 1;
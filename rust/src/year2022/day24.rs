@@ -11,6 +11,7 @@ use std::vec;
 
 use crate::puzzle::Puzzle;
 use crate::utils::board::*;
+use crate::utils::point::{X, Y};
 
 pub struct Day24 {
     grid: Board<char>,
@@ -33,17 +34,17 @@ struct Blizzard {
 /// Blizzards move based on current time, they have a predictable pattern
 fn set_blizzards_location(day: &mut Day24, time: i32) {
     let step_offsets = HashMap::from([
-        (Direction::Up, BoardPoint { x: 0, y: -1 }),
-        (Direction::Down, BoardPoint { x: 0, y: 1 }),
-        (Direction::Left, BoardPoint { x: -1, y: 0 }),
-        (Direction::Right, BoardPoint { x: 1, y: 0 }),
+        (Direction::Up, BoardPoint::new(0, -1)),
+        (Direction::Down, BoardPoint::new(0, 1)),
+        (Direction::Left, BoardPoint::new(-1, 0)),
+        (Direction::Right, BoardPoint::new(1, 0)),
     ]);
 
     for blizzard in &day.blizzards {
         let mut location = blizzard.start;
         let offset = step_offsets[&blizzard.direction];
-        location.x = (location.x - 1 + time * offset.x).rem_euclid(day.width) + 1;
-        location.y = (location.y - 1 + time * offset.y).rem_euclid(day.height) + 1;
+        location[X] = (location.x() - 1 + time * offset.x()).rem_euclid(day.width) + 1;
+        location[Y] = (location.y() - 1 + time * offset.y()).rem_euclid(day.height) + 1;
         day.grid.set_player_location(blizzard.id, location)
     }
 }
@@ -137,7 +138,7 @@ impl Puzzle for Day24 {
         day.grid.set_players_as_walls();
 
         // Add our main player, the expedition
-        day.grid.add_player(BoardPoint { x: 1, y: 0 }, 'E');
+        day.grid.add_player(BoardPoint::new(1, 0), 'E');
 
         // Scan the input for the blizzrds and add players for each one
         for (y, line) in input.lines().enumerate() {
@@ -161,10 +162,7 @@ impl Puzzle for Day24 {
                     },
                     _ => continue,
                 };
-                blizzard.start = BoardPoint {
-                    x: x as i32,
-                    y: y as i32,
-                };
+                blizzard.start = BoardPoint::new(x as i32, y as i32);
                 blizzard.id = day.grid.add_player(blizzard.start, char);
                 day.blizzards.push(blizzard);
             }
@@ -175,11 +173,8 @@ impl Puzzle for Day24 {
 
     fn solve_part1(&mut self) -> Result<String> {
         // Find how long it takes to go from start to end
-        let start = BoardPoint { x: 1, y: 0 };
-        let end = BoardPoint {
-            x: self.grid.width() - 2,
-            y: self.grid.height() - 1,
-        };
+        let start = BoardPoint::new(1, 0);
+        let end = BoardPoint::new(self.grid.width() - 2, self.grid.height() - 1);
         let answer = search(self, 1, start, end);
         Ok(answer.to_string())
     }
@@ -193,11 +188,8 @@ impl Puzzle for Day24 {
 
     fn solve_part2(&mut self) -> Result<String> {
         // Go from start -> end -> start -> end
-        let start = BoardPoint { x: 1, y: 0 };
-        let end = BoardPoint {
-            x: self.grid.width() - 2,
-            y: self.grid.height() - 1,
-        };
+        let start = BoardPoint::new(1, 0);
+        let end = BoardPoint::new(self.grid.width() - 2, self.grid.height() - 1);
         let mut time = search(self, 1, start, end);
         time = search(self, time, end, start);
         time = search(self, time, start, end);